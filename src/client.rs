@@ -12,7 +12,7 @@ impl Client {
     pub async fn connect<T: ToSocketAddrs>(addr: T) -> crate::Result<Client> {
         let socket = TcpStream::connect(addr).await?;
         let connection = Connection::new(socket);
-        Ok(Client { connection: connection })
+        Ok(Client { connection })
     }
 
     #[deprecated]
@@ -44,7 +44,7 @@ impl Client {
     }
 
     async fn write(&mut self, frame: &Frame) -> crate::Result<()> {
-        let _ = self.connection.write(frame).await?;
+        self.connection.write(frame).await?;
         Ok(())
     }
 }
\ No newline at end of file
@@ -101,13 +101,13 @@ impl<E: crate::storage::engine::Engine + 'static> Handler<E> {
                         self.connection.write(&Frame::String("PONG".to_string())).await?;
                     }
                     let response = tokio::task::block_in_place(|| {
-                        let mut response = None;
+                        let response;
                         let mut result_set = self.session.execute(&string);
                         match &mut result_set {
                             Ok(result_set) => {
                                 match result_set {
                                     ResultSet::Query { columns, rows: ref mut resultrows } => {
-                                        let schema = columns.iter().map(|c| c.name.clone().unwrap()).collect::<Vec<_>>();
+                                        let schema = columns.iter().map(|c| c.name.clone().unwrap_or_default()).collect::<Vec<_>>();
                                         let schema = schema.join(" | ");
 
                                         let resultrows = std::mem::replace(resultrows, Box::new(std::iter::empty()));
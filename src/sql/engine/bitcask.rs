@@ -1,38 +1,51 @@
 use super::Transaction as _;
 use crate::error::{Error, Result};
 use crate::sql::schema::catalog::Catalog;
-use crate::sql::schema::table::{Table, Tables};
+use crate::sql::schema::sequence::Sequence;
+use crate::sql::schema::table::{Column, ReferenceAction, Table, Tables};
+use crate::sql::schema::view::View;
 use crate::sql::types::expression::Expression;
-use crate::sql::types::{Value, Row};
+use crate::sql::types::{format_key, DataType, Row, Value};
 use crate::storage::mvcc::mvcc::MVCC;
 use crate::storage::{self, bincode, keycode};
 
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::clone::Clone;
+use std::collections::HashSet;
+use std::slice;
+use std::sync::{Arc, Mutex};
+
+/// 超过该字节数的 STRING、BLOB 和 JSON 值分块存储在行外，扫描时只在需要时读取
+const EXTERNAL_SIZE: usize = 1024;
+
+/// 行外存储的值的分块大小
+const CHUNK_SIZE: usize = 8192;
 
 /// SQL engine 基于 MVCC storage 实现
 pub struct KV<E: storage::engine::Engine> {
     pub kv: MVCC<E>,
+    /// 序列状态的锁，保证并发的 NEXTVAL 不会返回相同的值
+    sequence_lock: Arc<Mutex<()>>,
 }
 
 impl<E: storage::engine::Engine> Clone for KV<E> {
     fn clone(&self) -> Self {
-        KV { kv: self.kv.clone() }
+        KV { kv: self.kv.clone(), sequence_lock: self.sequence_lock.clone() }
     }
 }
 
 /// 实现一个 storage engine 需要实现的 trait
 impl<E: storage::engine::Engine> KV<E> {
     pub fn new(engine: E) -> Self {
-        Self { kv: MVCC::new(engine) }
+        Self { kv: MVCC::new(engine), sequence_lock: Arc::new(Mutex::new(())) }
     }
 
     pub fn resume(
         &self,
         state: crate::storage::mvcc::transaction::TransactionState,
     ) -> Result<<Self as super::Engine>::Transaction> {
-        Ok(<Self as super::Engine>::Transaction::new(self.kv.resume(state)?))
+        Ok(<Self as super::Engine>::Transaction::new(self, self.kv.resume(state)?))
     }
 
     pub fn get_metadata(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
@@ -49,15 +62,15 @@ impl<E: storage::engine::Engine> super::Engine for KV<E> {
     type Transaction = Transaction<E>;
 
     fn begin(&self) -> Result<Self::Transaction> {
-        Ok(Self::Transaction::new(self.kv.begin()?))
+        Ok(Self::Transaction::new(self, self.kv.begin()?))
     }
 
     fn begin_read_only(&self) -> Result<Self::Transaction> {
-        Ok(Self::Transaction::new(self.kv.begin_read_only()?))
+        Ok(Self::Transaction::new(self, self.kv.begin_read_only()?))
     }
 
     fn begin_as_of(&self, version: u64) -> Result<Self::Transaction> {
-        Ok(Self::Transaction::new(self.kv.begin_as_of(version)?))
+        Ok(Self::Transaction::new(self, self.kv.begin_as_of(version)?))
     }
 }
 
@@ -74,16 +87,296 @@ fn deserialize<'a, V: Deserialize<'a>>(bytes: &'a [u8]) -> Result<V> {
 /// SQL 事务
 pub struct Transaction<E: storage::engine::Engine> {
     txn: crate::storage::mvcc::transaction::Transaction<E>,
+    /// 用于读写非事务性的序列状态
+    kv: KV<E>,
 }
 
 impl<E: storage::engine::Engine> Transaction<E> {
-    fn new(txn: crate::storage::mvcc::transaction::Transaction<E>) -> Self {
-        Self { txn }
+    fn new(kv: &KV<E>, txn: crate::storage::mvcc::transaction::Transaction<E>) -> Self {
+        Self { txn, kv: kv.clone() }
     }
 
+    #[allow(dead_code)]
     pub(crate) fn state(&self) -> &crate::storage::mvcc::transaction::TransactionState {
         self.txn.state()
     }
+
+    /// 保存一个 index entry，如果为空则删除。NaN 不等于任何值，因此不写入索引
+    fn index_save(
+        &mut self,
+        table: &str,
+        column: &str,
+        value: &Value,
+        index: HashSet<Vec<Value>>,
+    ) -> Result<()> {
+        let Some(value) = index_value(value) else { return Ok(()) };
+        let key = Key::Index(table.into(), column.into(), value).encode()?;
+        if index.is_empty() {
+            self.txn.delete(&key)
+        } else {
+            self.txn.set(&key, serialize(&index)?)
+        }
+    }
+
+    /// 保存一个唯一索引 entry，id 为空则删除。NULL 和 NaN 不参与唯一性约束
+    fn unique_save(
+        &mut self,
+        table: &str,
+        column: &str,
+        value: &Value,
+        id: Option<&[Value]>,
+    ) -> Result<()> {
+        let Some(value) = unique_value(value) else { return Ok(()) };
+        let key = Key::Unique(table.into(), column.into(), value).encode()?;
+        match id {
+            Some(id) => self.txn.set(&key, serialize(&id)?),
+            None => self.txn.delete(&key),
+        }
+    }
+
+    /// 删除一行及其索引，不处理引用该行的外键
+    fn delete_row(&mut self, table: &Table, id: &[Value]) -> Result<()> {
+        let indexes: Vec<_> =
+            table.columns.iter().enumerate().filter(|(_, c)| c.index || is_unique(c)).collect();
+        if !indexes.is_empty() {
+            if let Some(row) = self.read(&table.name, id)? {
+                for (i, column) in indexes {
+                    if column.index {
+                        let mut index = self.read_index(&table.name, &column.name, &row[i])?;
+                        index.remove(id);
+                        self.index_save(&table.name, &column.name, &row[i], index)?;
+                    }
+                    if is_unique(column) {
+                        self.unique_save(&table.name, &column.name, &row[i], None)?;
+                    }
+                }
+            }
+        }
+        self.row_delete(&table.name, id)
+    }
+
+    /// 保存一行。较大的 STRING、BLOB 和 JSON 值按列分块保存在行外，行内只记录分块数
+    fn row_save(&mut self, table: &str, id: &[Value], row: &[Value]) -> Result<()> {
+        self.chunks_delete(table, id)?;
+        let mut stored = Vec::with_capacity(row.len());
+        for (i, value) in row.iter().enumerate() {
+            let (datatype, bytes) = match value {
+                Value::String(s) if s.len() > EXTERNAL_SIZE => (DataType::String, s.as_bytes()),
+                Value::Blob(b) if b.len() > EXTERNAL_SIZE => (DataType::Blob, b.as_slice()),
+                Value::Json(j) if j.len() > EXTERNAL_SIZE => (DataType::Json, j.as_bytes()),
+                value => {
+                    stored.push(StoredValue::Inline(value.into()));
+                    continue;
+                }
+            };
+            let mut chunks = 0;
+            for chunk in bytes.chunks(CHUNK_SIZE) {
+                let key = Key::Chunk(table.into(), id.into(), i as u64, chunks).encode()?;
+                self.txn.set(&key, chunk.to_vec())?;
+                chunks += 1;
+            }
+            stored.push(StoredValue::External(datatype, chunks));
+        }
+        self.txn.set(&Key::Row(table.into(), id.into()).encode()?, serialize(&stored)?)
+    }
+
+    /// 删除一行及其行外存储的值
+    fn row_delete(&mut self, table: &str, id: &[Value]) -> Result<()> {
+        self.chunks_delete(table, id)?;
+        self.txn.delete(&Key::Row(table.into(), id.into()).encode()?)
+    }
+
+    /// 删除一行在行外存储的值
+    fn chunks_delete(&mut self, table: &str, id: &[Value]) -> Result<()> {
+        let Some(value) = self.txn.get(&Key::Row(table.into(), id.into()).encode()?)? else {
+            return Ok(());
+        };
+        let stored: Vec<StoredValue> = deserialize(&value)?;
+        for (i, value) in stored.iter().enumerate() {
+            if let StoredValue::External(_, chunks) = value {
+                for n in 0..*chunks {
+                    self.txn.delete(&Key::Chunk(table.into(), id.into(), i as u64, n).encode()?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 将行的存储形式还原为行。如果给定了 columns，只读取这些列在行外存储的值，其余的返回 NULL
+    fn row_load(
+        &self,
+        table: &str,
+        id: &[Value],
+        stored: Vec<StoredValue>,
+        columns: Option<&HashSet<usize>>,
+    ) -> Result<Row> {
+        stored
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| match value {
+                StoredValue::Inline(value) => Ok(value.into_owned()),
+                StoredValue::External(..) if columns.is_some_and(|c| !c.contains(&i)) => {
+                    Ok(Value::Null)
+                }
+                StoredValue::External(datatype, chunks) => {
+                    let mut bytes = Vec::new();
+                    for n in 0..chunks {
+                        let key = Key::Chunk(table.into(), id.into(), i as u64, n).encode()?;
+                        bytes.extend(self.txn.get(&key)?.ok_or_else(|| {
+                            Error::Internal(format!(
+                                "Missing chunk {} of column {} in row {} of table {}",
+                                n,
+                                i,
+                                format_key(id),
+                                table
+                            ))
+                        })?);
+                    }
+                    match datatype {
+                        DataType::String => Ok(Value::String(String::from_utf8(bytes)?)),
+                        DataType::Blob => Ok(Value::Blob(bytes)),
+                        DataType::Json => Ok(Value::Json(String::from_utf8(bytes)?)),
+                        datatype => Err(Error::Internal(format!(
+                            "Unexpected out-of-row datatype {}",
+                            datatype
+                        ))),
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// 查找引用给定主键的行（行自身的引用除外），返回 (表, 列位置, 行主键)。
+    /// 存在外键动作为 RESTRICT 的引用时报错。外键列有二级索引时直接查找索引，
+    /// 否则需要扫描引用表
+    fn references_to(
+        &self,
+        table: &str,
+        id: &[Value],
+        update: bool,
+    ) -> Result<Vec<(Table, usize, Vec<Value>)>> {
+        let mut references = Vec::new();
+        for (t, cs) in self.table_references(table, true)? {
+            let t = self.must_read_table(&t)?;
+            let cs = cs.iter().map(|c| t.get_column_index(c)).collect::<Result<Vec<_>>>()?;
+            let (indexed, scanned): (Vec<usize>, Vec<usize>) =
+                cs.into_iter().partition(|&i| t.columns[i].index);
+            let mut matches = Vec::new();
+            // 外键只能引用单列主键
+            if let [value] = id {
+                for &i in &indexed {
+                    let keys = self.read_index(&t.name, &t.columns[i].name, value)?;
+                    matches.extend(keys.into_iter().map(|key| (i, key)));
+                }
+            }
+            if !scanned.is_empty() {
+                let mut scan = self.scan(&t.name, None, None)?;
+                while let Some(row) = scan.next().transpose()? {
+                    let key = t.get_row_key(&row)?;
+                    for &i in &scanned {
+                        if slice::from_ref(&row[i]) == id {
+                            matches.push((i, key.clone()));
+                        }
+                    }
+                }
+            }
+            for (i, key) in matches {
+                if table == t.name && id == key {
+                    continue;
+                }
+                let column = &t.columns[i];
+                let action = if update { &column.on_update } else { &column.on_delete };
+                if action == &ReferenceAction::Restrict {
+                    return Err(Error::Value(format!(
+                        "Primary key {} is referenced by table {} column {}",
+                        format_key(id),
+                        t.name,
+                        column.name
+                    )));
+                }
+                references.push((t.clone(), i, key));
+            }
+        }
+        Ok(references)
+    }
+
+    /// 修改引用了被删除或被修改主键的行，同一行的修改合并后再校验
+    fn update_references(&mut self, updates: Vec<(Table, Vec<Value>, usize, Value)>) -> Result<()> {
+        let mut rows: Vec<(Table, Vec<Value>)> = Vec::new();
+        let mut columns: Vec<Vec<(usize, Value)>> = Vec::new();
+        for (t, key, i, value) in updates {
+            match rows.iter().position(|(rt, rkey)| rt.name == t.name && rkey == &key) {
+                Some(r) => columns[r].push((i, value)),
+                None => {
+                    rows.push((t, key));
+                    columns.push(vec![(i, value)]);
+                }
+            }
+        }
+        for ((t, key), columns) in rows.into_iter().zip(columns) {
+            let Some(mut row) = self.read(&t.name, &key)? else { continue };
+            for (i, value) in columns {
+                row[i] = value;
+            }
+            self.update(&t.name, &key, row)?;
+        }
+        Ok(())
+    }
+
+    /// 扫描给定前缀下的所有 key/value 对
+    fn scan_keys(&self, prefix: KeyPrefix) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        self.txn.scan_prefix(&prefix.encode()?)?.iter().collect()
+    }
+
+    /// 扫描给定 key 前缀下的行，并使用可选的 filter 过滤。
+    /// 如果给定了 columns，只读取这些列和 filter 用到的列在行外存储的值
+    fn scan_rows(
+        &self,
+        prefix: &[u8],
+        filter: Option<Expression>,
+        columns: Option<&[usize]>,
+    ) -> Result<super::Scan> {
+        let columns: Option<HashSet<usize>> = columns.map(|columns| {
+            let mut columns: HashSet<usize> = columns.iter().copied().collect();
+            if let Some(filter) = &filter {
+                columns.extend(filter.fields());
+            }
+            columns
+        });
+        // 扫描期间持有存储引擎的锁，因此先取出全部键值，再读取行外存储的值
+        let rows = self.txn.scan_prefix(prefix)?.iter().collect::<Result<Vec<_>>>()?;
+        Ok(Box::new(
+            rows.into_iter()
+                .map(|(key, value)| {
+                    let Key::Row(table, id) = Key::decode(&key)? else {
+                        return Err(Error::Internal(format!("Unexpected row key {:x?}", key)));
+                    };
+                    self.row_load(&table, &id, deserialize(&value)?, columns.as_ref())
+                })
+                .filter_map(move |r| match r {
+                    Ok(row) => match &filter {
+                        Some(filter) => match filter.evaluate(Some(&row)) {
+                            Ok(Value::Boolean(b)) if b => Some(Ok(row)),
+                            Ok(Value::Boolean(_)) | Ok(Value::Null) => None,
+                            Ok(v) => Some(Err(Error::Value(format!(
+                                "Filter returned {}, expected boolean",
+                                v
+                            )))),
+                            Err(err) => Some(Err(err)),
+                        },
+                        None => Some(Ok(row)),
+                    },
+                    err => Some(err),
+                })
+                .collect::<Vec<_>>()
+                .into_iter(),
+        ))
+    }
+
+    /// 保存 table 元数据
+    fn table_save(&mut self, table: &Table) -> Result<()> {
+        self.txn.set(&Key::Table((&table.name).into()).encode()?, serialize(table)?)
+    }
 }
 
 impl<E: storage::engine::Engine> super::Transaction for Transaction<E> {
@@ -110,81 +403,189 @@ impl<E: storage::engine::Engine> super::Transaction for Transaction<E> {
         if self.read(&table.name, &id)?.is_some() {
             return Err(Error::Value(format!(
                 "Primary key {} already exists for table {}",
-                id, table.name
+                format_key(&id),
+                table.name
             )));
         }
-        self.txn.set(&Key::Row((&table.name).into(), (&id).into()).encode()?, serialize(&row)?)?;
+        self.row_save(&table.name, &id, &row)?;
+
+        // Update indexes
+        for (i, column) in table.columns.iter().enumerate().filter(|(_, c)| c.index) {
+            let mut index = self.read_index(&table.name, &column.name, &row[i])?;
+            index.insert(id.clone());
+            self.index_save(&table.name, &column.name, &row[i], index)?;
+        }
+        for (i, column) in table.columns.iter().enumerate().filter(|(_, c)| is_unique(c)) {
+            self.unique_save(&table.name, &column.name, &row[i], Some(&id))?;
+        }
         Ok(())
     }
 
-    fn delete(&mut self, table: &str, id: &Value) -> Result<()> {
-        let table = self.must_read_table(table)?;
-        for (t, cs) in self.table_references(&table.name, true)? {
-            let t = self.must_read_table(&t)?;
-            let cs = cs
-                .into_iter()
-                .map(|c| Ok((t.get_column_index(&c)?, c)))
-                .collect::<Result<Vec<_>>>()?;
-            let mut scan = self.scan(&t.name, None)?;
-            while let Some(row) = scan.next().transpose()? {
-                for (i, c) in &cs {
-                    if &row[*i] == id && (table.name != t.name || id != &table.get_row_key(&row)?) {
-                        return Err(Error::Value(format!(
-                            "Primary key {} is referenced by table {} column {}",
-                            id, t.name, c
-                        )));
+    fn delete(&mut self, table: &str, id: &[Value]) -> Result<()> {
+        // 先收集所有级联删除的行和需要修改的引用行，再统一执行。
+        // 这样 RESTRICT 会在修改任何数据之前报错，引用行也只在最终状态下校验
+        let mut deletes = vec![(self.must_read_table(table)?, id.to_vec())];
+        let mut updates = Vec::new();
+        let mut i = 0;
+        while let Some((table, id)) = deletes.get(i) {
+            for (t, ci, key) in self.references_to(&table.name, id, false)? {
+                match &t.columns[ci].on_delete {
+                    ReferenceAction::Cascade => {
+                        if !deletes.iter().any(|(dt, did)| dt.name == t.name && did == &key) {
+                            deletes.push((t, key));
+                        }
+                    }
+                    ReferenceAction::SetNull => updates.push((t, key, ci, Value::Null)),
+                    ReferenceAction::SetDefault => {
+                        let default = t.columns[ci].default.clone().unwrap_or(Value::Null);
+                        updates.push((t, key, ci, default))
+                    }
+                    ReferenceAction::Restrict => {
+                        return Err(Error::Internal("Unexpected RESTRICT reference".into()))
                     }
                 }
             }
+            i += 1;
         }
-        self.txn.delete(&Key::Row(table.name.into(), id.into()).encode()?)
+        for (table, id) in &deletes {
+            self.delete_row(table, id)?;
+        }
+        updates.retain(|(t, key, _, _)| {
+            !deletes.iter().any(|(dt, did)| dt.name == t.name && did == key)
+        });
+        self.update_references(updates)
     }
 
-    fn read(&self, table: &str, id: &Value) -> Result<Option<Row>> {
+    fn read(&self, table: &str, id: &[Value]) -> Result<Option<Row>> {
         self.txn
             .get(&Key::Row(table.into(), id.into()).encode()?)?
+            .map(|v| self.row_load(table, id, deserialize(&v)?, None))
+            .transpose()
+    }
+
+    fn read_index(
+        &self,
+        table: &str,
+        column: &str,
+        value: &Value,
+    ) -> Result<HashSet<Vec<Value>>> {
+        if !self.must_read_table(table)?.get_column(column)?.index {
+            return Err(Error::Value(format!("No index on {}.{}", table, column)));
+        }
+        let Some(value) = index_value(value) else { return Ok(HashSet::new()) };
+        Ok(self
+            .txn
+            .get(&Key::Index(table.into(), column.into(), value).encode()?)?
+            .map(|v| deserialize(&v))
+            .transpose()?
+            .unwrap_or_default())
+    }
+
+    fn read_unique(&self, table: &str, column: &str, value: &Value) -> Result<Option<Vec<Value>>> {
+        if !is_unique(self.must_read_table(table)?.get_column(column)?) {
+            return Err(Error::Value(format!("No unique constraint on {}.{}", table, column)));
+        }
+        let Some(value) = unique_value(value) else { return Ok(None) };
+        self.txn
+            .get(&Key::Unique(table.into(), column.into(), value).encode()?)?
             .map(|v| deserialize(&v))
             .transpose()
     }
 
-    fn scan(&self, table: &str, filter: Option<Expression>) -> Result<super::Scan> {
+    fn scan(
+        &self,
+        table: &str,
+        filter: Option<Expression>,
+        columns: Option<&[usize]>,
+    ) -> Result<super::Scan> {
         let table = self.must_read_table(table)?;
-        Ok(Box::new(
-            self.txn
-                .scan_prefix(&KeyPrefix::Row((&table.name).into()).encode()?)?
-                .iter()
-                .map(|r| r.and_then(|(_, v)| deserialize(&v)))
-                .filter_map(move |r| match r {
-                    Ok(row) => match &filter {
-                        Some(filter) => match filter.evaluate(Some(&row)) {
-                            Ok(Value::Boolean(b)) if b => Some(Ok(row)),
-                            Ok(Value::Boolean(_)) | Ok(Value::Null) => None,
-                            Ok(v) => Some(Err(Error::Value(format!(
-                                "Filter returned {}, expected boolean",
-                                v
-                            )))),
-                            Err(err) => Some(Err(err)),
-                        },
-                        None => Some(Ok(row)),
-                    },
-                    err => Some(err),
-                })
-                .collect::<Vec<_>>()
-                .into_iter(),
-        ))
+        self.scan_rows(&KeyPrefix::Row((&table.name).into()).encode()?, filter, columns)
     }
 
-    fn update(&mut self, table: &str, id: &Value, row: Row) -> Result<()> {
+    fn scan_prefix(&self, table: &str, prefix: &[Value]) -> Result<super::Scan> {
+        let table = self.must_read_table(table)?;
+        // 行 key 中的主键是各列取值的直接拼接，因此主键前缀对应 key 的前缀
+        self.scan_rows(&Key::Row((&table.name).into(), prefix.into()).encode()?, None, None)
+    }
+
+    fn next_value(&self, sequence: &str) -> Result<i64> {
+        let sequence = self.must_read_sequence(sequence)?;
+        let key = Key::Sequence((&sequence.name).into()).encode()?;
+        let _guard = self.kv.sequence_lock.lock()?;
+        // 序列状态为下一个值，None 表示序列已耗尽
+        let next: Option<i64> = match self.kv.get_metadata(&key)? {
+            Some(v) => deserialize(&v)?,
+            None => Some(sequence.start),
+        };
+        let value = next
+            .ok_or_else(|| Error::Value(format!("Sequence {} is exhausted", sequence.name)))?;
+        self.kv.set_metadata(&key, serialize(&value.checked_add(sequence.increment))?)?;
+        Ok(value)
+    }
+
+    fn update(&mut self, table: &str, id: &[Value], row: Row) -> Result<()> {
         let table = self.must_read_table(table)?;
         // If the primary key changes we do a delete and create, otherwise we replace the row
-        if id != &table.get_row_key(&row)? {
-            self.delete(&table.name, id)?;
+        let new_id = table.get_row_key(&row)?;
+        if id != new_id {
+            let references = self.references_to(&table.name, id, true)?;
+            self.delete_row(&table, id)?;
             self.create(&table.name, row)?;
-            return Ok(());
+            let updates = references
+                .into_iter()
+                .map(|(t, ci, key)| {
+                    let value = match &t.columns[ci].on_update {
+                        // 被引用的表只能有单列主键
+                        ReferenceAction::Cascade => new_id[0].clone(),
+                        ReferenceAction::SetNull => Value::Null,
+                        ReferenceAction::SetDefault => {
+                            t.columns[ci].default.clone().unwrap_or(Value::Null)
+                        }
+                        ReferenceAction::Restrict => {
+                            return Err(Error::Internal("Unexpected RESTRICT reference".into()))
+                        }
+                    };
+                    Ok((t, key, ci, value))
+                })
+                .collect::<Result<_>>()?;
+            return self.update_references(updates);
         }
 
         table.validate_row(&row, self)?;
-        self.txn.set(&Key::Row(table.name.into(), id.into()).encode()?, serialize(&row)?)
+
+        // Update indexes, knowing that the primary key has not changed
+        let indexes: Vec<_> =
+            table.columns.iter().enumerate().filter(|(_, c)| c.index || is_unique(c)).collect();
+        if !indexes.is_empty() {
+            let old = self.read(&table.name, id)?.ok_or_else(|| {
+                Error::Value(format!(
+                    "Primary key {} does not exist in table {}",
+                    format_key(id),
+                    table.name
+                ))
+            })?;
+            for (i, column) in indexes {
+                if old[i] == row[i] {
+                    continue;
+                }
+                if is_unique(column) {
+                    self.unique_save(&table.name, &column.name, &old[i], None)?;
+                    self.unique_save(&table.name, &column.name, &row[i], Some(id))?;
+                }
+                if !column.index {
+                    continue;
+                }
+                let mut index = self.read_index(&table.name, &column.name, &old[i])?;
+                index.remove(id);
+                self.index_save(&table.name, &column.name, &old[i], index)?;
+
+                let mut index = self.read_index(&table.name, &column.name, &row[i])?;
+                index.insert(id.to_vec());
+                self.index_save(&table.name, &column.name, &row[i], index)?;
+            }
+        }
+
+        self.row_save(&table.name, id, &row)
     }
 }
 
@@ -193,8 +594,14 @@ impl<E: storage::engine::Engine> Catalog for Transaction<E> {
         if self.read_table(&table.name)?.is_some() {
             return Err(Error::Value(format!("Table {} already exists", table.name)));
         }
+        if self.read_view(&table.name)?.is_some() {
+            return Err(Error::Value(format!("View {} already exists", table.name)));
+        }
         table.validate(self)?;
-        self.txn.set(&Key::Table((&table.name).into()).encode()?, serialize(&table)?)
+        for sequence in table.columns.iter().filter_map(|c| c.sequence.as_ref()) {
+            self.create_sequence(Sequence::new(sequence.clone()))?;
+        }
+        self.table_save(&table)
     }
 
     fn delete_table(&mut self, table: &str) -> Result<()> {
@@ -205,11 +612,15 @@ impl<E: storage::engine::Engine> Catalog for Transaction<E> {
                 table.name, t, cs[0]
             )));
         }
-        let mut scan = self.scan(&table.name, None)?;
+        let mut scan = self.scan(&table.name, None, None)?;
         while let Some(row) = scan.next().transpose()? {
             self.delete(&table.name, &table.get_row_key(&row)?)?
         }
-        self.txn.delete(&Key::Table(table.name.into()).encode()?)
+        self.txn.delete(&Key::Table((&table.name).into()).encode()?)?;
+        for sequence in table.columns.iter().filter_map(|c| c.sequence.as_ref()) {
+            self.delete_sequence(sequence)?;
+        }
+        Ok(())
     }
 
     fn read_table(&self, table: &str) -> Result<Option<Table>> {
@@ -226,14 +637,283 @@ impl<E: storage::engine::Engine> Catalog for Transaction<E> {
                 .into_iter(),
         ))
     }
+
+    fn add_column(&mut self, table: &str, column: Column, checks: Vec<Expression>) -> Result<()> {
+        let mut table = self.must_read_table(table)?;
+        if table.get_column(&column.name).is_ok() {
+            return Err(Error::Value(format!(
+                "Column {} already exists in table {}",
+                column.name, table.name
+            )));
+        }
+        if column.primary_key {
+            return Err(Error::Value(format!(
+                "Can't add primary key column {} to table {}",
+                column.name, table.name
+            )));
+        }
+        let (name, index, unique) = (column.name.clone(), column.index, is_unique(&column));
+        let (default, sequence) = (column.default.clone(), column.sequence.clone());
+        table.columns.push(column);
+        table.checks.extend(checks);
+        table.validate(self)?;
+        let rows = self.scan(&table.name, None, None)?.collect::<Result<Vec<_>>>()?;
+        if let Some(sequence) = &sequence {
+            self.create_sequence(Sequence::new(sequence.clone()))?;
+        }
+        self.table_save(&table)?;
+
+        // 使用默认值或自增序列填充已有的行，并校验约束
+        if default.is_none() && sequence.is_none() && !rows.is_empty() {
+            return Err(Error::Value(format!(
+                "Column {} needs a default value, since table {} is not empty",
+                name, table.name
+            )));
+        }
+        for mut row in rows {
+            let default = match &sequence {
+                Some(sequence) => Value::Integer(self.next_value(sequence)?),
+                None => default.clone().unwrap_or(Value::Null),
+            };
+            row.push(default.clone());
+            table.validate_row(&row, self)?;
+            let id = table.get_row_key(&row)?;
+            self.row_save(&table.name, &id, &row)?;
+            if unique {
+                self.unique_save(&table.name, &name, &default, Some(&id))?;
+            }
+            if index {
+                let mut index = self.read_index(&table.name, &name, &default)?;
+                index.insert(id);
+                self.index_save(&table.name, &name, &default, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn drop_column(&mut self, table: &str, column: &str) -> Result<()> {
+        let mut table = self.must_read_table(table)?;
+        let i = table.get_column_index(column)?;
+        if table.columns[i].primary_key {
+            return Err(Error::Value(format!(
+                "Can't drop primary key column {} from table {}",
+                column, table.name
+            )));
+        }
+        for (key, _) in self.scan_keys(KeyPrefix::Index((&table.name).into(), column.into()))? {
+            self.txn.delete(&key)?;
+        }
+        for (key, _) in self.scan_keys(KeyPrefix::Unique((&table.name).into(), column.into()))? {
+            self.txn.delete(&key)?;
+        }
+        for mut row in self.scan(&table.name, None, None)?.collect::<Result<Vec<_>>>()? {
+            let id = table.get_row_key(&row)?;
+            row.remove(i);
+            self.row_save(&table.name, &id, &row)?;
+        }
+        let column = table.columns.remove(i);
+        // 主键列不能删除，只需调整其后主键列的位置
+        for key in table.primary_key.iter_mut().filter(|k| **k > i) {
+            *key -= 1;
+        }
+
+        // 删除使用该列的 CHECK 约束，并调整其余约束中的列位置
+        table.checks.retain(|c| c.walk(&|e| !matches!(e, Expression::Field(f, _) if *f == i)));
+        table.checks = table
+            .checks
+            .into_iter()
+            .map(|c| {
+                c.transform(&Ok, &|e| match e {
+                    Expression::Field(f, label) if f > i => Ok(Expression::Field(f - 1, label)),
+                    e => Ok(e),
+                })
+            })
+            .collect::<Result<_>>()?;
+        self.table_save(&table)?;
+        if let Some(sequence) = &column.sequence {
+            self.delete_sequence(sequence)?;
+        }
+        Ok(())
+    }
+
+    fn rename_column(&mut self, table: &str, column: &str, name: &str) -> Result<()> {
+        let mut table = self.must_read_table(table)?;
+        let i = table.get_column_index(column)?;
+        if table.get_column(name).is_ok() {
+            return Err(Error::Value(format!(
+                "Column {} already exists in table {}",
+                name, table.name
+            )));
+        }
+        for (key, value) in self.scan_keys(KeyPrefix::Index((&table.name).into(), column.into()))? {
+            if let Key::Index(_, _, v) = Key::decode(&key)? {
+                self.txn.delete(&key)?;
+                let key = Key::Index((&table.name).into(), name.into(), v).encode()?;
+                self.txn.set(&key, value)?;
+            }
+        }
+        let prefix = KeyPrefix::Unique((&table.name).into(), column.into());
+        for (key, value) in self.scan_keys(prefix)? {
+            if let Key::Unique(_, _, v) = Key::decode(&key)? {
+                self.txn.delete(&key)?;
+                let key = Key::Unique((&table.name).into(), name.into(), v).encode()?;
+                self.txn.set(&key, value)?;
+            }
+        }
+        table.columns[i].name = name.to_string();
+        table.checks = table
+            .checks
+            .into_iter()
+            .map(|c| {
+                c.transform(&Ok, &|e| match e {
+                    Expression::Field(f, _) if f == i => {
+                        Ok(Expression::Field(f, Some((None, name.to_string()))))
+                    }
+                    e => Ok(e),
+                })
+            })
+            .collect::<Result<_>>()?;
+        self.table_save(&table)
+    }
+
+    fn rename_table(&mut self, table: &str, name: &str) -> Result<()> {
+        let mut table = self.must_read_table(table)?;
+        if self.read_table(name)?.is_some() {
+            return Err(Error::Value(format!("Table {} already exists", name)));
+        }
+        if self.read_view(name)?.is_some() {
+            return Err(Error::Value(format!("View {} already exists", name)));
+        }
+
+        // 迁移行数据和索引
+        for row in self.scan(&table.name, None, None)?.collect::<Result<Vec<_>>>()? {
+            let id = table.get_row_key(&row)?;
+            self.row_delete(&table.name, &id)?;
+            self.row_save(name, &id, &row)?;
+        }
+        for column in table.columns.iter().filter(|c| c.index) {
+            let prefix = KeyPrefix::Index((&table.name).into(), (&column.name).into());
+            for (key, value) in self.scan_keys(prefix)? {
+                if let Key::Index(_, _, v) = Key::decode(&key)? {
+                    self.txn.delete(&key)?;
+                    let key = Key::Index(name.into(), (&column.name).into(), v).encode()?;
+                    self.txn.set(&key, value)?;
+                }
+            }
+        }
+        for column in table.columns.iter().filter(|c| is_unique(c)) {
+            let prefix = KeyPrefix::Unique((&table.name).into(), (&column.name).into());
+            for (key, value) in self.scan_keys(prefix)? {
+                if let Key::Unique(_, _, v) = Key::decode(&key)? {
+                    self.txn.delete(&key)?;
+                    let key = Key::Unique(name.into(), (&column.name).into(), v).encode()?;
+                    self.txn.set(&key, value)?;
+                }
+            }
+        }
+
+        // 更新引用该表的外键，包括自引用
+        for (t, cs) in self.table_references(&table.name, false)? {
+            let mut t = self.must_read_table(&t)?;
+            for column in t.columns.iter_mut().filter(|c| cs.contains(&c.name)) {
+                column.references = Some(name.to_string());
+            }
+            self.table_save(&t)?;
+        }
+        for column in table.columns.iter_mut() {
+            if column.references.as_deref() == Some(&table.name) {
+                column.references = Some(name.to_string());
+            }
+        }
+
+        self.txn.delete(&Key::Table((&table.name).into()).encode()?)?;
+        table.name = name.to_string();
+        self.table_save(&table)
+    }
+
+    fn create_sequence(&mut self, sequence: Sequence) -> Result<()> {
+        if self.read_sequence(&sequence.name)?.is_some() {
+            return Err(Error::Value(format!("Sequence {} already exists", sequence.name)));
+        }
+        sequence.validate()?;
+        let key = Key::Sequence((&sequence.name).into()).encode()?;
+        {
+            let _guard = self.kv.sequence_lock.lock()?;
+            self.kv.set_metadata(&key, serialize(&Some(sequence.start))?)?;
+        }
+        self.txn.set(&key, serialize(&sequence)?)
+    }
+
+    fn delete_sequence(&mut self, sequence: &str) -> Result<()> {
+        let sequence = self.must_read_sequence(sequence)?;
+        for table in self.scan_tables()? {
+            let mut columns = table.columns.iter();
+            if let Some(c) = columns.find(|c| c.sequence.as_ref() == Some(&sequence.name)) {
+                return Err(Error::Value(format!(
+                    "Sequence {} is used by table {} column {}",
+                    sequence.name, table.name, c.name
+                )));
+            }
+        }
+        self.txn.delete(&Key::Sequence(sequence.name.into()).encode()?)
+    }
+
+    fn read_sequence(&self, sequence: &str) -> Result<Option<Sequence>> {
+        self.txn
+            .get(&Key::Sequence(sequence.into()).encode()?)?
+            .map(|v| deserialize(&v))
+            .transpose()
+    }
+
+    fn create_view(&mut self, view: View) -> Result<()> {
+        if self.read_table(&view.name)?.is_some() {
+            return Err(Error::Value(format!("Table {} already exists", view.name)));
+        }
+        if self.read_view(&view.name)?.is_some() {
+            return Err(Error::Value(format!("View {} already exists", view.name)));
+        }
+        self.txn.set(&Key::View((&view.name).into()).encode()?, serialize(&view)?)
+    }
+
+    fn delete_view(&mut self, view: &str) -> Result<()> {
+        let view = self.must_read_view(view)?;
+        self.txn.delete(&Key::View(view.name.into()).encode()?)
+    }
+
+    fn read_view(&self, view: &str) -> Result<Option<View>> {
+        self.txn.get(&Key::View(view.into()).encode()?)?.map(|v| deserialize(&v)).transpose()
+    }
+
+    fn scan_views(&self) -> Result<Vec<View>> {
+        self.scan_keys(KeyPrefix::View)?.into_iter().map(|(_, v)| deserialize(&v)).collect()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 enum Key<'a> {
     /// 用于 Table 元数据原理
     Table(Cow<'a, str>),
-    /// 用于管理 Table 的数据
-    Row(Cow<'a, str>, Cow<'a, Value>),
+    /// 用于管理 Table 的数据，主键的各列取值直接拼接，从而支持按主键前缀扫描
+    Row(Cow<'a, str>, Cow<'a, [Value]>),
+    /// 用于管理二级索引，value 为该列取值对应的主键集合
+    Index(Cow<'a, str>, Cow<'a, str>, Cow<'a, Value>),
+    /// 用于管理唯一约束，value 为拥有该列取值的行的主键
+    Unique(Cow<'a, str>, Cow<'a, str>, Cow<'a, Value>),
+    /// 用于管理序列元数据，同一个 key 在非事务性存储中保存序列状态
+    Sequence(Cow<'a, str>),
+    /// 用于管理行外存储的值，由表、主键、列位置和分块序号确定
+    Chunk(Cow<'a, str>, Cow<'a, [Value]>, u64, u64),
+    /// 用于管理视图元数据
+    View(Cow<'a, str>),
+}
+
+/// 行在存储中的列值
+#[derive(Debug, Deserialize, Serialize)]
+enum StoredValue<'a> {
+    /// 保存在行内的值
+    Inline(Cow<'a, Value>),
+    /// 分块保存在行外的值，记录其数据类型和分块数
+    External(DataType, u64),
 }
 
 impl<'a> Key<'a> {
@@ -250,10 +930,39 @@ impl<'a> Key<'a> {
 enum KeyPrefix<'a> {
     Table,
     Row(Cow<'a, str>),
+    Index(Cow<'a, str>, Cow<'a, str>),
+    Unique(Cow<'a, str>, Cow<'a, str>),
+    /// 不按前缀扫描，仅用于与 Key 的变体序号保持一致
+    Sequence,
+    /// 不按前缀扫描，仅用于与 Key 的变体序号保持一致
+    Chunk,
+    View,
 }
 
 impl<'a> KeyPrefix<'a> {
     fn encode(self) -> Result<Vec<u8>> {
         keycode::serialize(&self)
     }
-}
\ No newline at end of file
+}
+/// 判断列是否需要维护唯一索引，主键本身即保证唯一
+fn is_unique(column: &Column) -> bool {
+    column.unique && !column.primary_key
+}
+
+/// 返回唯一索引中使用的取值：NULL 和 NaN 之间互不相等，因此不参与唯一性约束，-0.0 则等同于 0.0
+fn unique_value(value: &Value) -> Option<Cow<'_, Value>> {
+    match value {
+        Value::Null => None,
+        Value::Float(f) if f.is_nan() => None,
+        Value::Float(f) if *f == 0.0 => Some(Cow::Owned(Value::Float(0.0))),
+        value => Some(Cow::Borrowed(value)),
+    }
+}
+
+/// 返回二级索引中使用的取值：与唯一索引相同，但保留 NULL 以支持 IS NULL 查找
+fn index_value(value: &Value) -> Option<Cow<'_, Value>> {
+    match value {
+        Value::Null => Some(Cow::Borrowed(value)),
+        value => unique_value(value),
+    }
+}
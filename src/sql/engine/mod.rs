@@ -5,6 +5,7 @@ use std::collections::HashSet;
 use crate::error::Result;
 
 use super::{schema::catalog::Catalog, types::{Row, Value, expression::Expression}, session::Session};
+use super::plan::Plan;
 
 
 pub trait Engine: Clone {
@@ -17,7 +18,11 @@ pub trait Engine: Clone {
     fn begin_as_of(&self, version: u64) -> Result<Self::Transaction>;
 
     fn session(&self) -> Result<Session<Self>> {
-        Ok(Session { engine: self.clone(), txn: None })
+        Ok(Session {
+            engine: self.clone(),
+            txn: None,
+            max_recursion: Plan::DEFAULT_MAX_RECURSION,
+        })
     }
 }
 
@@ -29,10 +34,23 @@ pub trait Transaction: Catalog {
     fn rollback(self) -> Result<()>;
 
     fn create(&mut self, table: &str, row: Row) -> Result<()>;
-    fn delete(&mut self, table: &str, id: &Value) -> Result<()>;
-    fn read(&self, table: &str, id: &Value) -> Result<Option<Row>>;
-    fn scan(&self, table: &str, filter: Option<Expression>) -> Result<Scan>;
-    fn update(&mut self, table: &str, id: &Value, row: Row) -> Result<()>;
+    fn delete(&mut self, table: &str, id: &[Value]) -> Result<()>;
+    fn read(&self, table: &str, id: &[Value]) -> Result<Option<Row>>;
+    fn read_index(&self, table: &str, column: &str, value: &Value) -> Result<HashSet<Vec<Value>>>;
+    fn read_unique(&self, table: &str, column: &str, value: &Value) -> Result<Option<Vec<Value>>>;
+    /// Scans a table's rows, optionally filtered. If columns are given, large values stored
+    /// out-of-row are only read for those columns (and the filter), and NULL otherwise.
+    fn scan(
+        &self,
+        table: &str,
+        filter: Option<Expression>,
+        columns: Option<&[usize]>,
+    ) -> Result<Scan>;
+    fn scan_prefix(&self, table: &str, prefix: &[Value]) -> Result<Scan>;
+    fn update(&mut self, table: &str, id: &[Value], row: Row) -> Result<()>;
+    /// Fetches the next value of a sequence. This is not transactional: the value is consumed
+    /// even if the transaction is rolled back.
+    fn next_value(&self, sequence: &str) -> Result<i64>;
 }
 
 pub type Scan = Box<dyn DoubleEndedIterator<Item = Result<Row>> + Send>;
\ No newline at end of file
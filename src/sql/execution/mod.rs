@@ -1,13 +1,16 @@
 pub mod operator;
 
+use std::cell::RefCell;
+use std::sync::Arc;
+
 use derivative::Derivative;
 use serde_derive::{Serialize, Deserialize};
 
 use crate::error::{Error, Result};
 
-use self::operator::{create_table::CreateTable, delete::Delete, drop_table::DropTable, scan::Scan, insert::Insert, projection::Projection, filter::Filter, update::Update, nothing::Nothing};
+use self::operator::{aggregation::Aggregation, alter_table::AlterTable, analyze::{Analyze, NodeStats}, create_sequence::CreateSequence, create_table::CreateTable, create_view::CreateView, delete::Delete, distinct::Distinct, drop_sequence::DropSequence, drop_table::DropTable, drop_view::DropView, except::Except, hash_join::HashJoin, index_lookup::IndexLookup, scan::Scan, insert::Insert, intersect::Intersect, key_lookup::KeyLookup, limit::Limit, nested_loop_join::NestedLoopJoin, offset::Offset, order::Order, projection::Projection, recursive::Recursive, top_n::TopN, filter::Filter, union::Union, update::Update, values::Values, nothing::Nothing, window::Window, working_table::WorkingTable};
 
-use super::{types::{Columns, Rows, Row, Value}, engine::Transaction, plan::Node};
+use super::{types::{expression::Expression, Columns, Rows, Row, Value}, engine::Transaction, plan::{Node, SetOperator}};
 
 /// A plan executor
 pub trait Executor<T: Transaction> {
@@ -18,28 +21,117 @@ pub trait Executor<T: Transaction> {
 impl<T: Transaction + 'static> dyn Executor<T> {
     /// Builds an executor for a plan node, consuming it
     pub fn build(node: Node) -> Box<dyn Executor<T>> {
-        match node {
+        Self::build_node(&mut None, node)
+    }
+
+    /// Builds an executor for a plan node like build(), but wraps every executor in the tree to
+    /// collect execution statistics. The statistics are returned in pre-order, i.e. in the same
+    /// order as the nodes are formatted.
+    pub fn build_analyzed(node: Node) -> (Box<dyn Executor<T>>, Vec<Arc<NodeStats>>) {
+        let mut stats = Some(Vec::new());
+        let executor = Self::build_node(&mut stats, node);
+        (executor, stats.unwrap_or_default())
+    }
+
+    /// Builds an executor for a plan node, collecting statistics if given a vector for them.
+    fn build_node(stats: &mut Option<Vec<Arc<NodeStats>>>, node: Node) -> Box<dyn Executor<T>> {
+        let slot = stats.as_mut().map(|stats| {
+            let slot = Arc::new(NodeStats::default());
+            stats.push(slot.clone());
+            slot
+        });
+        let mut build = |node| Self::build_node(stats, node);
+        let executor: Box<dyn Executor<T>> = match node {
+            Node::Aggregation { source, aggregates } => {
+                Aggregation::new(build(*source), aggregates)
+            }
+            Node::AlterTable { table, alteration } => AlterTable::new(table, alteration),
+            Node::CreateSequence { schema } => CreateSequence::new(schema),
             Node::CreateTable { schema } => CreateTable::new(schema),
-            Node::Delete { table, source } => Delete::new(table, Self::build(*source)),
+            Node::CreateView { schema } => CreateView::new(schema),
+            Node::Delete { table, source } => Delete::new(table, build(*source)),
+            Node::Distinct { source, on } => Distinct::new(build(*source), on),
+            Node::DropSequence { sequence } => DropSequence::new(sequence),
             Node::DropTable { table } => DropTable::new(table),
-            Node::Filter { source, predicate } => Filter::new(Self::build(*source), predicate),
+            Node::DropView { view } => DropView::new(view),
+            Node::Filter { source, predicate } => Filter::new(build(*source), predicate),
+            Node::IndexLookup { table, alias: _, column, values } => {
+                IndexLookup::new(table, column, values)
+            }
             Node::Insert { table, columns, expressions } => {
                 Insert::new(table, columns, expressions)
             }
+            Node::KeyLookup { table, alias: _, keys } => KeyLookup::new(table, keys),
+            Node::HashJoin { left, left_field, right, right_field, r#type } => HashJoin::new(
+                build(*left),
+                left_field.0,
+                build(*right),
+                right_field.0,
+                r#type,
+            ),
+            Node::Limit { source, limit } => Limit::new(build(*source), limit),
+            Node::NestedLoopJoin { left, left_size: _, right, predicate, r#type } => {
+                NestedLoopJoin::new(build(*left), build(*right), predicate, r#type)
+            }
+            Node::Offset { source, offset } => Offset::new(build(*source), offset),
+            Node::Order { source, orders } => Order::new(build(*source), orders),
             Node::Projection { source, expressions } => {
-                Projection::new(Self::build(*source), expressions)
+                Projection::new(build(*source), expressions)
+            }
+            Node::Recursive { name, anchor, recursive, all, max_iterations } => {
+                let anchor = build(*anchor);
+                // The recursive term is built anew for every iteration, so only the statistics
+                // slots of its nodes are allocated here, following the anchor's.
+                let slots = stats.as_mut().map(|stats| {
+                    let (_, slots) = Self::build_analyzed((*recursive).clone());
+                    stats.extend(slots.iter().cloned());
+                    slots
+                });
+                Recursive::new(name, anchor, *recursive, all, max_iterations, slots)
             }
-            Node::Scan { table, filter, alias: _ } => Scan::new(table, filter),
+            Node::Scan { table, filter, alias: _, columns } => Scan::new(table, filter, columns),
+            Node::SetOperation { left, right, operator, all } => match operator {
+                SetOperator::Union => Union::new(build(*left), build(*right), all),
+                SetOperator::Intersect => Intersect::new(build(*left), build(*right), all),
+                SetOperator::Except => Except::new(build(*left), build(*right), all),
+            },
+            Node::TopN { source, orders, limit } => TopN::new(build(*source), orders, limit),
             Node::Update { table, source, expressions } => Update::new(
                 table,
-                Self::build(*source),
+                build(*source),
                 expressions.into_iter().map(|(i, _, e)| (i, e)).collect(),
             ),
-            Node::Nothing => Nothing::new(),
+            Node::Window { source, partition, orders, functions } => {
+                Window::new(build(*source), partition, orders, functions)
+            }
+            Node::WorkingTable { name: _, columns, rows } => WorkingTable::new(columns, rows),
+            Node::Values { rows } => Values::new(rows),
+            Node::Nothing { columns } => Nothing::new(columns),
+        };
+        match slot {
+            Some(slot) => Analyze::new(executor, slot),
+            None => executor,
         }
     }
 }
 
+/// Resolves the subqueries of an expression by executing their plans in the transaction, see
+/// Expression::resolve_subqueries().
+pub fn resolve_subqueries<T: Transaction + 'static>(
+    expr: Expression,
+    row: Option<&Row>,
+    txn: &mut T,
+) -> Result<Expression> {
+    let txn = RefCell::new(txn);
+    expr.resolve_subqueries(row, &|node| {
+        match <dyn Executor<T>>::build(node).execute(&mut **txn.borrow_mut())? {
+            ResultSet::Query { rows, .. } => Ok(rows),
+            r => Err(Error::Internal(format!("Unexpected result {:?}", r))),
+        }
+    })
+}
+
+
 
 /// executor 返回的 result
 #[derive(Derivative, Serialize, Deserialize)]
@@ -70,6 +162,21 @@ pub enum ResultSet {
     DropTable {
         name: String,
     },
+    AlterTable {
+        name: String,
+    },
+    CreateSequence {
+        name: String,
+    },
+    DropSequence {
+        name: String,
+    },
+    CreateView {
+        name: String,
+    },
+    DropView {
+        name: String,
+    },
     Query {
         columns: Columns,
         #[derivative(Debug = "ignore")]
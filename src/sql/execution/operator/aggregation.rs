@@ -0,0 +1,231 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::Aggregate, types::{Column, Value}}, error::{Error, Result}};
+
+/// Computes aggregates over the source rows. The first aggregates.len() source columns are the
+/// aggregate arguments, and any remaining columns are the group keys, which are passed through.
+pub struct Aggregation<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    aggregates: Vec<Aggregate>,
+    accumulators: HashMap<Vec<Value>, Vec<Box<dyn Accumulator>>>,
+}
+
+impl<T: Transaction> Aggregation<T> {
+    pub fn new(source: Box<dyn Executor<T>>, aggregates: Vec<Aggregate>) -> Box<Self> {
+        Box::new(Self { source, aggregates, accumulators: HashMap::new() })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Aggregation<T> {
+    fn execute(mut self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let agg_count = self.aggregates.len();
+        match self.source.execute(txn)? {
+            ResultSet::Query { columns, mut rows } => {
+                while let Some(mut row) = rows.next().transpose()? {
+                    let aggregates = &self.aggregates;
+                    self.accumulators
+                        .entry(row.split_off(agg_count))
+                        .or_insert_with(|| aggregates.iter().map(<dyn Accumulator>::from).collect())
+                        .iter_mut()
+                        .zip(row)
+                        .try_for_each(|(acc, value)| acc.accumulate(value))?
+                }
+                // If there were no rows and no group-by columns, return a row of empty
+                // accumulators, e.g. SELECT COUNT(*) FROM t WHERE FALSE
+                if self.accumulators.is_empty() && agg_count == columns.len() {
+                    self.accumulators.insert(
+                        Vec::new(),
+                        self.aggregates.iter().map(<dyn Accumulator>::from).collect(),
+                    );
+                }
+                Ok(ResultSet::Query {
+                    columns: columns
+                        .into_iter()
+                        .enumerate()
+                        .map(|(i, c)| if i < agg_count { Column { name: None } } else { c })
+                        .collect(),
+                    rows: Box::new(self.accumulators.into_iter().map(|(group, accs)| {
+                        Ok(accs.into_iter().map(|acc| acc.aggregate()).chain(group).collect())
+                    })),
+                })
+            }
+            r => Err(Error::Internal(format!("Unexpected result {:?}", r))),
+        }
+    }
+}
+
+/// Accumulates values for an aggregate. NULL values are ignored by all accumulators.
+pub trait Accumulator: Send {
+    /// Accumulates a value
+    fn accumulate(&mut self, value: Value) -> Result<()>;
+
+    /// Calculates a final aggregate value
+    fn aggregate(&self) -> Value;
+}
+
+impl dyn Accumulator {
+    pub(super) fn from(aggregate: &Aggregate) -> Box<dyn Accumulator> {
+        match aggregate {
+            Aggregate::Average => Box::new(Average::new()),
+            Aggregate::Count => Box::new(Count::new()),
+            Aggregate::CountDistinct => Box::new(CountDistinct::new()),
+            Aggregate::Max => Box::new(Extremum::new(Ordering::Greater)),
+            Aggregate::Min => Box::new(Extremum::new(Ordering::Less)),
+            Aggregate::Sum => Box::new(Sum::new()),
+        }
+    }
+}
+
+/// Counts non-NULL values
+struct Count {
+    count: u64,
+}
+
+impl Count {
+    fn new() -> Self {
+        Self { count: 0 }
+    }
+}
+
+impl Accumulator for Count {
+    fn accumulate(&mut self, value: Value) -> Result<()> {
+        if value != Value::Null {
+            self.count += 1;
+        }
+        Ok(())
+    }
+
+    fn aggregate(&self) -> Value {
+        Value::Integer(self.count as i64)
+    }
+}
+
+/// Counts distinct non-NULL values
+struct CountDistinct {
+    seen: HashSet<Value>,
+}
+
+impl CountDistinct {
+    fn new() -> Self {
+        Self { seen: HashSet::new() }
+    }
+}
+
+impl Accumulator for CountDistinct {
+    fn accumulate(&mut self, value: Value) -> Result<()> {
+        if value != Value::Null {
+            self.seen.insert(value);
+        }
+        Ok(())
+    }
+
+    fn aggregate(&self) -> Value {
+        Value::Integer(self.seen.len() as i64)
+    }
+}
+
+/// Sums numeric values. The sum of no values is NULL.
+struct Sum {
+    sum: Value,
+}
+
+impl Sum {
+    fn new() -> Self {
+        Self { sum: Value::Null }
+    }
+}
+
+impl Accumulator for Sum {
+    fn accumulate(&mut self, value: Value) -> Result<()> {
+        self.sum = match (&self.sum, value) {
+            (_, Value::Null) => return Ok(()),
+            (Value::Null, v @ (Value::Integer(_) | Value::Float(_) | Value::Decimal(_))) => v,
+            (Value::Integer(s), Value::Integer(i)) => Value::Integer(
+                s.checked_add(i).ok_or_else(|| Error::Value("Integer overflow".into()))?,
+            ),
+            (Value::Integer(s), Value::Float(f)) => Value::Float(*s as f64 + f),
+            (Value::Float(s), Value::Integer(i)) => Value::Float(s + i as f64),
+            (Value::Float(s), Value::Float(f)) => Value::Float(s + f),
+            (Value::Decimal(s), Value::Decimal(d)) => Value::Decimal(s.checked_add(d)?),
+            (Value::Decimal(s), Value::Integer(i)) => Value::Decimal(s.checked_add(i.into())?),
+            (Value::Integer(s), Value::Decimal(d)) => Value::Decimal(d.checked_add((*s).into())?),
+            (Value::Decimal(s), Value::Float(f)) => Value::Float(s.to_float() + f),
+            (Value::Float(s), Value::Decimal(d)) => Value::Float(s + d.to_float()),
+            (_, v) => return Err(Error::Value(format!("Can't sum {}", v))),
+        };
+        Ok(())
+    }
+
+    fn aggregate(&self) -> Value {
+        self.sum.clone()
+    }
+}
+
+/// Averages numeric values as a float, or as a decimal for decimal values. The average of no
+/// values is NULL.
+struct Average {
+    count: Count,
+    sum: Sum,
+}
+
+impl Average {
+    fn new() -> Self {
+        Self { count: Count::new(), sum: Sum::new() }
+    }
+}
+
+impl Accumulator for Average {
+    fn accumulate(&mut self, value: Value) -> Result<()> {
+        self.sum.accumulate(value.clone())?;
+        self.count.accumulate(value)
+    }
+
+    fn aggregate(&self) -> Value {
+        match self.sum.aggregate() {
+            Value::Integer(s) => Value::Float(s as f64 / self.count.count as f64),
+            Value::Float(s) => Value::Float(s / self.count.count as f64),
+            Value::Decimal(s) => s
+                .checked_div((self.count.count as i64).into())
+                .map(Value::Decimal)
+                .unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+}
+
+/// Finds the minimum or maximum value, depending on the ordering to keep.
+struct Extremum {
+    keep: Ordering,
+    value: Value,
+}
+
+impl Extremum {
+    fn new(keep: Ordering) -> Self {
+        Self { keep, value: Value::Null }
+    }
+}
+
+impl Accumulator for Extremum {
+    fn accumulate(&mut self, value: Value) -> Result<()> {
+        if value == Value::Null {
+            return Ok(());
+        }
+        if self.value == Value::Null {
+            self.value = value;
+            return Ok(());
+        }
+        match value.partial_cmp(&self.value) {
+            Some(ordering) if ordering == self.keep => self.value = value,
+            Some(_) => {}
+            None => {
+                return Err(Error::Value(format!("Can't compare {} and {}", value, self.value)))
+            }
+        }
+        Ok(())
+    }
+
+    fn aggregate(&self) -> Value {
+        self.value.clone()
+    }
+}
@@ -0,0 +1,36 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::Alteration}, error::Result};
+
+pub struct AlterTable {
+    table: String,
+    alteration: Alteration,
+}
+
+impl AlterTable {
+    pub fn new(table: String, alteration: Alteration) -> Box<Self> {
+        Box::new(Self { table, alteration })
+    }
+}
+
+impl<T: Transaction> Executor<T> for AlterTable {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let name = match self.alteration {
+            Alteration::AddColumn(column, checks) => {
+                txn.add_column(&self.table, column, checks)?;
+                self.table
+            }
+            Alteration::DropColumn(column) => {
+                txn.drop_column(&self.table, &column)?;
+                self.table
+            }
+            Alteration::RenameColumn { column, name } => {
+                txn.rename_column(&self.table, &column, &name)?;
+                self.table
+            }
+            Alteration::RenameTable(name) => {
+                txn.rename_table(&self.table, &name)?;
+                name
+            }
+        };
+        Ok(ResultSet::AlterTable { name })
+    }
+}
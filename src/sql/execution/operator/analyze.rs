@@ -0,0 +1,79 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+
+/// Execution statistics for a plan node, collected by EXPLAIN ANALYZE.
+#[derive(Debug, Default)]
+pub struct NodeStats {
+    rows: AtomicU64,
+    nanos: AtomicU64,
+}
+
+impl NodeStats {
+    /// The number of rows produced by the node, or affected by a mutation.
+    pub fn rows(&self) -> u64 {
+        self.rows.load(Ordering::Relaxed)
+    }
+
+    /// The wall time spent executing the node, including its sources.
+    pub fn time(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::Relaxed))
+    }
+
+    /// Adds the statistics of another execution of the same node.
+    pub fn add(&self, other: &NodeStats) {
+        self.rows.fetch_add(other.rows(), Ordering::Relaxed);
+        self.nanos.fetch_add(other.nanos.load(Ordering::Relaxed), Ordering::Relaxed);
+    }
+
+    fn add_time(&self, start: Instant) {
+        self.nanos.fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Wraps an executor, recording the rows it produces and the time spent in it.
+pub struct Analyze<T: Transaction> {
+    inner: Box<dyn Executor<T>>,
+    stats: Arc<NodeStats>,
+}
+
+impl<T: Transaction> Analyze<T> {
+    pub fn new(inner: Box<dyn Executor<T>>, stats: Arc<NodeStats>) -> Box<Self> {
+        Box::new(Self { inner, stats })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Analyze<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let start = Instant::now();
+        let result = self.inner.execute(txn);
+        self.stats.add_time(start);
+        let count = match &result {
+            Ok(ResultSet::Create { count })
+            | Ok(ResultSet::Delete { count })
+            | Ok(ResultSet::Update { count }) => *count,
+            _ => 0,
+        };
+        self.stats.rows.fetch_add(count, Ordering::Relaxed);
+        match result? {
+            ResultSet::Query { columns, mut rows } => {
+                let stats = self.stats;
+                Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(std::iter::from_fn(move || {
+                        let start = Instant::now();
+                        let row = rows.next();
+                        stats.add_time(start);
+                        if let Some(Ok(_)) = row {
+                            stats.rows.fetch_add(1, Ordering::Relaxed);
+                        }
+                        row
+                    })),
+                })
+            }
+            result => Ok(result),
+        }
+    }
+}
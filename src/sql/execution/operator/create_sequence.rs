@@ -0,0 +1,19 @@
+use crate::{sql::{schema::sequence::Sequence, engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+
+pub struct CreateSequence {
+    sequence: Sequence,
+}
+
+impl CreateSequence {
+    pub fn new(sequence: Sequence) -> Box<Self> {
+        Box::new(Self { sequence })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CreateSequence {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let name = self.sequence.name.clone();
+        txn.create_sequence(self.sequence)?;
+        Ok(ResultSet::CreateSequence { name })
+    }
+}
@@ -0,0 +1,19 @@
+use crate::{sql::{schema::view::View, engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+
+pub struct CreateView {
+    view: View,
+}
+
+impl CreateView {
+    pub fn new(view: View) -> Box<Self> {
+        Box::new(Self { view })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CreateView {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let name = self.view.name.clone();
+        txn.create_view(self.view)?;
+        Ok(ResultSet::CreateView { name })
+    }
+}
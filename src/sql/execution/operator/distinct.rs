@@ -0,0 +1,41 @@
+use std::collections::HashSet;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{expression::Expression, Row}}, error::{Error, Result}};
+
+/// Emits the first source row for each distinct key, where the key is the values of the given
+/// expressions, or the entire row if there are none. Keys seen so far are kept in a hash set,
+/// and rows are streamed, so with an ordered source the first row of each key is kept.
+pub struct Distinct<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    on: Vec<Expression>,
+}
+
+impl<T: Transaction> Distinct<T> {
+    pub fn new(source: Box<dyn Executor<T>>, on: Vec<Expression>) -> Box<Self> {
+        Box::new(Self { source, on })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Distinct<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, rows } = self.source.execute(txn)? {
+            let (on, mut seen) = (self.on, HashSet::new());
+            Ok(ResultSet::Query {
+                columns,
+                rows: Box::new(rows.filter_map(move |r| {
+                    r.and_then(|row| {
+                        let key: Row = if on.is_empty() {
+                            row.clone()
+                        } else {
+                            on.iter().map(|e| e.evaluate(Some(&row))).collect::<Result<_>>()?
+                        };
+                        Ok(seen.insert(key).then_some(row))
+                    })
+                    .transpose()
+                })),
+            })
+        } else {
+            Err(Error::Internal("Unexpected result".into()))
+        }
+    }
+}
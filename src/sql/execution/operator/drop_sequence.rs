@@ -0,0 +1,18 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+
+pub struct DropSequence {
+    sequence: String,
+}
+
+impl DropSequence {
+    pub fn new(sequence: String) -> Box<Self> {
+        Box::new(Self { sequence })
+    }
+}
+
+impl<T: Transaction> Executor<T> for DropSequence {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.delete_sequence(&self.sequence)?;
+        Ok(ResultSet::DropSequence { name: self.sequence })
+    }
+}
@@ -0,0 +1,18 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+
+pub struct DropView {
+    view: String,
+}
+
+impl DropView {
+    pub fn new(view: String) -> Box<Self> {
+        Box::new(Self { view })
+    }
+}
+
+impl<T: Transaction> Executor<T> for DropView {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.delete_view(&self.view)?;
+        Ok(ResultSet::DropView { name: self.view })
+    }
+}
@@ -0,0 +1,51 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{normalize_row, Row}}, error::{Error, Result}};
+
+/// Emits the left-hand rows that are not in the right source. The right-hand rows are counted
+/// in a hash table, and the left-hand rows are streamed. With all set, each right-hand row
+/// cancels out one left-hand occurrence, otherwise all of them, and the remaining rows are
+/// emitted once.
+pub struct Except<T: Transaction> {
+    left: Box<dyn Executor<T>>,
+    right: Box<dyn Executor<T>>,
+    all: bool,
+}
+
+impl<T: Transaction> Except<T> {
+    pub fn new(left: Box<dyn Executor<T>>, right: Box<dyn Executor<T>>, all: bool) -> Box<Self> {
+        Box::new(Self { left, right, all })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Except<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, rows } = self.left.execute(txn)? {
+            if let ResultSet::Query { rows: rrows, .. } = self.right.execute(txn)? {
+                let mut counts: HashMap<Row, usize> = HashMap::new();
+                for row in rrows {
+                    *counts.entry(normalize_row(&row?)).or_default() += 1;
+                }
+                let (all, mut seen) = (self.all, HashSet::new());
+                return Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(rows.filter(move |row| match row {
+                        Ok(row) if all => match counts.get_mut(&normalize_row(row)) {
+                            Some(count) if *count > 0 => {
+                                *count -= 1;
+                                false
+                            }
+                            _ => true,
+                        },
+                        Ok(row) => {
+                            let key = normalize_row(row);
+                            !counts.contains_key(&key) && seen.insert(key)
+                        }
+                        Err(_) => true,
+                    })),
+                });
+            }
+        }
+        Err(Error::Internal("Unexpected result set".into()))
+    }
+}
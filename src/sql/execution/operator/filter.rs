@@ -1,4 +1,4 @@
-use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{expression::Expression, Value}}, error::{Error, Result}};
+use crate::{sql::{engine::Transaction, execution::{resolve_subqueries, Executor, ResultSet}, types::{expression::Expression, Row, Value}}, error::{Error, Result}};
 
 pub struct Filter<T: Transaction> {
     source: Box<dyn Executor<T>>,
@@ -11,27 +11,43 @@ impl<T: Transaction> Filter<T> {
     }
 }
 
-impl<T: Transaction> Executor<T> for Filter<T> {
+impl<T: Transaction + 'static> Executor<T> for Filter<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        // Uncorrelated subqueries are executed once, before reading any rows.
+        let predicate = resolve_subqueries(self.predicate, None, txn)?;
         if let ResultSet::Query { columns, rows } = self.source.execute(txn)? {
-            let predicate = self.predicate;
+            // Correlated subqueries need the transaction for every row, so rows are filtered
+            // eagerly.
+            if predicate.has_subqueries() {
+                let mut filtered = Vec::new();
+                for row in rows.collect::<Result<Vec<_>>>()? {
+                    let predicate = resolve_subqueries(predicate.clone(), Some(&row), txn)?;
+                    if matches(&predicate, &row)? {
+                        filtered.push(row);
+                    }
+                }
+                return Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(filtered.into_iter().map(Ok)),
+                });
+            }
             Ok(ResultSet::Query {
                 columns,
                 rows: Box::new(rows.filter_map(move |r| {
-                    r.and_then(|row| match predicate.evaluate(Some(&row))? {
-                        Value::Boolean(true) => Ok(Some(row)),
-                        Value::Boolean(false) => Ok(None),
-                        Value::Null => Ok(None),
-                        value => Err(Error::Value(format!(
-                            "Filter returned {}, expected boolean",
-                            value
-                        ))),
-                    })
-                    .transpose()
+                    r.and_then(|row| Ok(matches(&predicate, &row)?.then_some(row))).transpose()
                 })),
             })
         } else {
             Err(Error::Internal("Unexpected result".into()))
         }
     }
-}
\ No newline at end of file
+}
+
+/// Evaluates the filter predicate for a row.
+fn matches(predicate: &Expression, row: &Row) -> Result<bool> {
+    match predicate.evaluate(Some(row))? {
+        Value::Boolean(b) => Ok(b),
+        Value::Null => Ok(false),
+        value => Err(Error::Value(format!("Filter returned {}, expected boolean", value))),
+    }
+}
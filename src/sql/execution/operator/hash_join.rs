@@ -0,0 +1,142 @@
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::JoinType, types::{Row, Value}}, error::{Error, Result}};
+
+pub struct HashJoin<T: Transaction> {
+    left: Box<dyn Executor<T>>,
+    left_field: usize,
+    right: Box<dyn Executor<T>>,
+    right_field: usize,
+    r#type: JoinType,
+}
+
+impl<T: Transaction> HashJoin<T> {
+    pub fn new(
+        left: Box<dyn Executor<T>>,
+        left_field: usize,
+        right: Box<dyn Executor<T>>,
+        right_field: usize,
+        r#type: JoinType,
+    ) -> Box<Self> {
+        Box::new(Self { left, left_field, right, right_field, r#type })
+    }
+}
+
+impl<T: Transaction> Executor<T> for HashJoin<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { mut columns, rows } = self.left.execute(txn)? {
+            if let ResultSet::Query { columns: rcolumns, rows: rrows } = self.right.execute(txn)? {
+                let (left_width, right_width) = (columns.len(), rcolumns.len());
+                let left: Vec<Row> = rows.collect::<Result<_>>()?;
+                let right: Vec<Row> = rrows.collect::<Result<_>>()?;
+
+                // Semi and anti joins only emit left-hand rows, so they always look up the
+                // left-hand keys in the set of right-hand keys. NULL keys never match.
+                if matches!(self.r#type, JoinType::Semi | JoinType::Anti) {
+                    let build = Side { rows: right, field: self.right_field, width: right_width };
+                    let keys: HashSet<Cow<Value>> =
+                        build.rows.iter().map(|r| build.key(r)).collect::<Result<_>>()?;
+                    let probe = Side { rows: left, field: self.left_field, width: left_width };
+                    let mut rows = Vec::new();
+                    for row in &probe.rows {
+                        let key = probe.key(row)?;
+                        let hit = *key != Value::Null && keys.contains(&key);
+                        if hit == (self.r#type == JoinType::Semi) {
+                            rows.push(row.clone());
+                        }
+                    }
+                    let rows = Box::new(rows.into_iter().map(Ok));
+                    return Ok(ResultSet::Query { columns, rows });
+                }
+                columns.extend(rcolumns);
+
+                // Build the hash table from the smaller input, and probe it with the larger one.
+                let (left_outer, right_outer) = match self.r#type {
+                    JoinType::Inner => (false, false),
+                    JoinType::Left => (true, false),
+                    JoinType::Full => (true, true),
+                    JoinType::Semi | JoinType::Anti => unreachable!("handled above"),
+                };
+                let rows = if right.len() <= left.len() {
+                    let probe = Side { rows: left, field: self.left_field, width: left_width };
+                    let build = Side { rows: right, field: self.right_field, width: right_width };
+                    join(probe, left_outer, build, right_outer, |mut l, r| {
+                        l.extend(r);
+                        l
+                    })?
+                } else {
+                    let probe = Side { rows: right, field: self.right_field, width: right_width };
+                    let build = Side { rows: left, field: self.left_field, width: left_width };
+                    join(probe, right_outer, build, left_outer, |r, mut l| {
+                        l.extend(r);
+                        l
+                    })?
+                };
+                return Ok(ResultSet::Query { columns, rows: Box::new(rows.into_iter().map(Ok)) });
+            }
+        }
+        Err(Error::Internal("Unexpected result set".into()))
+    }
+}
+
+/// One input of a hash join: its rows, the join field index, and the row width.
+struct Side {
+    rows: Vec<Row>,
+    field: usize,
+    width: usize,
+}
+
+impl Side {
+    /// Fetches the join key of a row, normalized such that numerically equal values of
+    /// different numeric types (e.g. 1 and 1.0) hash and compare equal.
+    fn key<'a>(&self, row: &'a Row) -> Result<Cow<'a, Value>> {
+        row.get(self.field)
+            .map(|v| v.normalize())
+            .ok_or_else(|| Error::Internal(format!("Join field {} not found", self.field)))
+    }
+}
+
+/// Joins the probe rows against a hash table built from the build rows. NULL keys never match.
+/// Unmatched rows of an outer side are padded with NULLs for the other side. The closure
+/// combines a probe row and a build row into an output row.
+fn join<F: Fn(Row, Row) -> Row>(
+    probe: Side,
+    probe_outer: bool,
+    build: Side,
+    build_outer: bool,
+    combine: F,
+) -> Result<Vec<Row>> {
+    let mut table: HashMap<Cow<Value>, Vec<usize>> = HashMap::new();
+    for (i, row) in build.rows.iter().enumerate() {
+        let key = build.key(row)?;
+        if *key != Value::Null {
+            table.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut matched = vec![false; build.rows.len()];
+    let mut rows = Vec::new();
+    for row in &probe.rows {
+        let key = probe.key(row)?;
+        let hits = if *key == Value::Null { None } else { table.get(&key) };
+        match hits {
+            Some(hits) => {
+                for i in hits {
+                    matched[*i] = true;
+                    rows.push(combine(row.clone(), build.rows[*i].clone()));
+                }
+            }
+            None if probe_outer => rows.push(combine(row.clone(), vec![Value::Null; build.width])),
+            None => {}
+        }
+    }
+    if build_outer {
+        for (row, matched) in build.rows.iter().zip(matched) {
+            if !matched {
+                rows.push(combine(vec![Value::Null; probe.width], row.clone()));
+            }
+        }
+    }
+    Ok(rows)
+}
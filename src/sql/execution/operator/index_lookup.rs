@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{Column, Value}}, error::Result};
+
+/// Looks up rows via a secondary index on the given column, returning the rows whose column
+/// value is any of the given values.
+pub struct IndexLookup {
+    table: String,
+    column: String,
+    values: Vec<Value>,
+}
+
+impl IndexLookup {
+    pub fn new(table: String, column: String, values: Vec<Value>) -> Box<Self> {
+        Box::new(Self { table, column, values })
+    }
+}
+
+impl<T: Transaction> Executor<T> for IndexLookup {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_read_table(&self.table)?;
+
+        let mut pks: HashSet<Vec<Value>> = HashSet::new();
+        for value in self.values {
+            pks.extend(txn.read_index(&self.table, &self.column, &value)?);
+        }
+
+        let rows = pks
+            .into_iter()
+            .filter_map(|pk| txn.read(&table.name, &pk).transpose())
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ResultSet::Query {
+            columns: table.columns.iter().map(|c| Column { name: Some(c.name.clone()) }).collect(),
+            rows: Box::new(rows.into_iter().map(Ok)),
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::mem::replace;
 
 use crate::{sql::{engine::Transaction, types::{expression::Expression, Value, Row}, schema::table::Table, execution::{Executor, ResultSet}}, error::{Result, Error}};
 
@@ -14,12 +15,17 @@ impl Insert {
     }
 
     // Builds a row from a set of column names and values, padding it with default values.
-    pub fn make_row(table: &Table, columns: &[String], values: Vec<Value>) -> Result<Row> {
+    pub fn make_row<T: Transaction>(
+        txn: &T,
+        table: &Table,
+        columns: &[String],
+        values: Vec<Value>,
+    ) -> Result<Row> {
         if columns.len() != values.len() {
             return Err(Error::Value("Column and value counts do not match".into()));
         }
         let mut inputs = HashMap::new();
-        for (c, v) in columns.iter().zip(values.into_iter()) {
+        for (c, v) in columns.iter().zip(values) {
             table.get_column(c)?;
             if inputs.insert(c.clone(), v).is_some() {
                 return Err(Error::Value(format!("Column {} given multiple times", c)));
@@ -29,6 +35,8 @@ impl Insert {
         for column in table.columns.iter() {
             if let Some(value) = inputs.get(&column.name) {
                 row.push(value.clone())
+            } else if let Some(sequence) = &column.sequence {
+                row.push(Value::Integer(txn.next_value(sequence)?))
             } else if let Some(value) = &column.default {
                 row.push(value.clone())
             } else {
@@ -39,9 +47,11 @@ impl Insert {
     }
 
     /// Pads a row with default values where possible.
-    fn pad_row(table: &Table, mut row: Row) -> Result<Row> {
+    fn pad_row<T: Transaction>(txn: &T, table: &Table, mut row: Row) -> Result<Row> {
         for column in table.columns.iter().skip(row.len()) {
-            if let Some(default) = &column.default {
+            if let Some(sequence) = &column.sequence {
+                row.push(Value::Integer(txn.next_value(sequence)?))
+            } else if let Some(default) = &column.default {
                 row.push(default.clone())
             } else {
                 return Err(Error::Value(format!("No default value for column {}", column.name)));
@@ -56,12 +66,17 @@ impl<T: Transaction> Executor<T> for Insert {
         let table = txn.must_read_table(&self.table)?;
         let mut count = 0;
         for expressions in self.rows {
-            let mut row =
-                expressions.into_iter().map(|expr| expr.evaluate(None)).collect::<Result<_>>()?;
+            let mut row = expressions
+                .into_iter()
+                .map(|expr| expr.resolve_sequences(&|s| txn.next_value(s))?.evaluate(None))
+                .collect::<Result<_>>()?;
             if self.columns.is_empty() {
-                row = Self::pad_row(&table, row)?;
+                row = Self::pad_row(txn, &table, row)?;
             } else {
-                row = Self::make_row(&table, &self.columns, row)?;
+                row = Self::make_row(txn, &table, &self.columns, row)?;
+            }
+            for (value, column) in row.iter_mut().zip(&table.columns) {
+                *value = replace(value, Value::Null).coerce(&column.datatype)?;
             }
             txn.create(&table.name, row)?;
             count += 1;
@@ -0,0 +1,47 @@
+use std::collections::HashMap;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{normalize_row, Row}}, error::{Error, Result}};
+
+/// Emits the left-hand rows that are also in the right source. The right-hand rows are counted
+/// in a hash table, and the left-hand rows are streamed. With all set, a row is emitted as many
+/// times as it occurs in both sources, otherwise once.
+pub struct Intersect<T: Transaction> {
+    left: Box<dyn Executor<T>>,
+    right: Box<dyn Executor<T>>,
+    all: bool,
+}
+
+impl<T: Transaction> Intersect<T> {
+    pub fn new(left: Box<dyn Executor<T>>, right: Box<dyn Executor<T>>, all: bool) -> Box<Self> {
+        Box::new(Self { left, right, all })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Intersect<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, rows } = self.left.execute(txn)? {
+            if let ResultSet::Query { rows: rrows, .. } = self.right.execute(txn)? {
+                let mut counts: HashMap<Row, usize> = HashMap::new();
+                for row in rrows {
+                    *counts.entry(normalize_row(&row?)).or_default() += 1;
+                }
+                let all = self.all;
+                return Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(rows.filter(move |row| match row {
+                        Ok(row) => match counts.get_mut(&normalize_row(row)) {
+                            Some(count) if *count > 0 => {
+                                // Without all, the row is only emitted once.
+                                *count = if all { *count - 1 } else { 0 };
+                                true
+                            }
+                            _ => false,
+                        },
+                        Err(_) => true,
+                    })),
+                });
+            }
+        }
+        Err(Error::Internal("Unexpected result set".into()))
+    }
+}
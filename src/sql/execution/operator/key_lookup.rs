@@ -0,0 +1,41 @@
+use std::collections::HashSet;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{Column, Value}}, error::Result};
+
+/// Looks up rows by primary key, skipping keys that don't exist. Keys may also be a prefix of a
+/// composite primary key, in which case all rows with that prefix are returned.
+pub struct KeyLookup {
+    table: String,
+    keys: Vec<Vec<Value>>,
+}
+
+impl KeyLookup {
+    pub fn new(table: String, keys: Vec<Vec<Value>>) -> Box<Self> {
+        Box::new(Self { table, keys })
+    }
+}
+
+impl<T: Transaction> Executor<T> for KeyLookup {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_read_table(&self.table)?;
+        let key_size = table.get_primary_key_columns().len();
+
+        // Keys may be repeated, e.g. WHERE id = 1 OR id = 1, but each row is only returned once.
+        let mut seen = HashSet::new();
+        let mut rows = Vec::new();
+        for key in self.keys.into_iter().filter(|key| seen.insert(key.clone())) {
+            if key.len() == key_size {
+                rows.extend(txn.read(&table.name, &key)?);
+            } else {
+                for row in txn.scan_prefix(&table.name, &key)? {
+                    rows.push(row?);
+                }
+            }
+        }
+
+        Ok(ResultSet::Query {
+            columns: table.columns.iter().map(|c| Column { name: Some(c.name.clone()) }).collect(),
+            rows: Box::new(rows.into_iter().map(Ok)),
+        })
+    }
+}
@@ -1,9 +1,32 @@
+pub mod aggregation;
+pub mod alter_table;
+pub mod analyze;
+pub mod create_sequence;
 pub mod create_table;
+pub mod create_view;
 pub mod delete;
+pub mod distinct;
+pub mod drop_sequence;
 pub mod drop_table;
+pub mod drop_view;
+pub mod except;
+pub mod hash_join;
+pub mod index_lookup;
 pub mod insert;
+pub mod intersect;
+pub mod key_lookup;
+pub mod limit;
+pub mod nested_loop_join;
+pub mod offset;
+pub mod order;
 pub mod projection;
+pub mod recursive;
 pub mod scan;
+pub mod top_n;
 pub mod filter;
+pub mod union;
 pub mod update;
-pub mod nothing;
\ No newline at end of file
+pub mod values;
+pub mod nothing;
+pub mod window;
+pub mod working_table;
\ No newline at end of file
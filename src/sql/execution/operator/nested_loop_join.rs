@@ -0,0 +1,172 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::JoinType, types::{expression::Expression, Row, Rows, Value}}, error::{Error, Result}};
+
+pub struct NestedLoopJoin<T: Transaction> {
+    left: Box<dyn Executor<T>>,
+    right: Box<dyn Executor<T>>,
+    predicate: Option<Expression>,
+    r#type: JoinType,
+}
+
+impl<T: Transaction> NestedLoopJoin<T> {
+    pub fn new(
+        left: Box<dyn Executor<T>>,
+        right: Box<dyn Executor<T>>,
+        predicate: Option<Expression>,
+        r#type: JoinType,
+    ) -> Box<Self> {
+        Box::new(Self { left, right, predicate, r#type })
+    }
+}
+
+impl<T: Transaction> Executor<T> for NestedLoopJoin<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { mut columns, rows } = self.left.execute(txn)? {
+            if let ResultSet::Query { columns: rcolumns, rows: rrows } = self.right.execute(txn)? {
+                let (left_width, right_width) = (columns.len(), rcolumns.len());
+                if !matches!(self.r#type, JoinType::Semi | JoinType::Anti) {
+                    columns.extend(rcolumns);
+                }
+                // The right-hand source is iterated once per left-hand row, so we buffer it.
+                let right: Vec<Row> = rrows.collect::<Result<_>>()?;
+                return Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(NestedLoopRows::new(
+                        rows,
+                        left_width,
+                        right,
+                        right_width,
+                        self.predicate,
+                        self.r#type,
+                    )),
+                });
+            }
+        }
+        Err(Error::Internal("Unexpected result set".into()))
+    }
+}
+
+/// Joins every left-hand row with every buffered right-hand row matching the predicate.
+struct NestedLoopRows {
+    left: Rows,
+    left_row: Option<Row>,
+    left_width: usize,
+    right: Vec<Row>,
+    right_index: usize,
+    right_width: usize,
+    right_hit: bool,
+    // For full outer joins, which right-hand rows have been matched by any left-hand row.
+    right_matched: Vec<bool>,
+    predicate: Option<Expression>,
+    r#type: JoinType,
+}
+
+impl NestedLoopRows {
+    fn new(
+        left: Rows,
+        left_width: usize,
+        right: Vec<Row>,
+        right_width: usize,
+        predicate: Option<Expression>,
+        r#type: JoinType,
+    ) -> Self {
+        Self {
+            left,
+            left_row: None,
+            left_width,
+            right_matched: vec![false; right.len()],
+            right,
+            right_index: 0,
+            right_width,
+            right_hit: false,
+            predicate,
+            r#type,
+        }
+    }
+
+    /// Tries to get the next joined row, with error handling.
+    fn try_next(&mut self) -> Result<Option<Row>> {
+        loop {
+            let left_row = match self.left_row.take() {
+                Some(row) => row,
+                None => match self.left.next().transpose()? {
+                    Some(row) => {
+                        self.right_index = 0;
+                        self.right_hit = false;
+                        row
+                    }
+                    None => return Ok(self.next_unmatched_right()),
+                },
+            };
+
+            // Semi and anti joins only check whether the left-hand row has any match.
+            if matches!(self.r#type, JoinType::Semi | JoinType::Anti) {
+                let hit = self.try_next_hit(&left_row)?.is_some();
+                if hit == (self.r#type == JoinType::Semi) {
+                    return Ok(Some(left_row));
+                }
+                continue;
+            }
+
+            // If there is a hit in the remaining right rows, return it.
+            if let Some(row) = self.try_next_hit(&left_row)? {
+                self.right_hit = true;
+                self.right_matched[self.right_index - 1] = true;
+                self.left_row = Some(left_row);
+                return Ok(Some(row));
+            }
+
+            // If this is an outer join, when we reach the end of the right rows without a hit
+            // we return a row with nulls for the right fields.
+            if self.r#type != JoinType::Inner && !self.right_hit {
+                let mut row = left_row;
+                row.extend(std::iter::repeat_n(Value::Null, self.right_width));
+                return Ok(Some(row));
+            }
+        }
+    }
+
+    /// For full outer joins, once the left-hand rows are exhausted, returns the right-hand rows
+    /// that were never matched, padded with nulls for the left fields.
+    fn next_unmatched_right(&mut self) -> Option<Row> {
+        if self.r#type != JoinType::Full {
+            return None;
+        }
+        let index = self.right_matched.iter().position(|matched| !matched)?;
+        self.right_matched[index] = true;
+        let mut row = vec![Value::Null; self.left_width];
+        row.extend(self.right[index].iter().cloned());
+        Some(row)
+    }
+
+    /// Looks for the next right-hand row matching the given left-hand row.
+    fn try_next_hit(&mut self, left_row: &[Value]) -> Result<Option<Row>> {
+        while let Some(right_row) = self.right.get(self.right_index) {
+            self.right_index += 1;
+            let mut row = left_row.to_vec();
+            row.extend(right_row.iter().cloned());
+            if let Some(predicate) = &self.predicate {
+                match predicate.evaluate(Some(&row))? {
+                    Value::Boolean(true) => return Ok(Some(row)),
+                    Value::Boolean(false) | Value::Null => {}
+                    value => {
+                        return Err(Error::Value(format!(
+                            "Join predicate returned {}, expected boolean",
+                            value
+                        )))
+                    }
+                }
+            } else {
+                return Ok(Some(row));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl Iterator for NestedLoopRows {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.try_next().transpose()
+    }
+}
@@ -1,18 +1,18 @@
-use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::Row}, error::Result};
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::Columns}, error::Result};
 
-pub struct Nothing;
+/// Emits no rows, with the given columns.
+pub struct Nothing {
+    columns: Columns,
+}
 
 impl Nothing {
-    pub fn new() -> Box<Self> {
-        Box::new(Self)
+    pub fn new(columns: Columns) -> Box<Self> {
+        Box::new(Self { columns })
     }
 }
 
 impl<T: Transaction> Executor<T> for Nothing {
     fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
-        Ok(ResultSet::Query {
-            columns: Vec::new(),
-            rows: Box::new(std::iter::once(Ok(Row::new()))),
-        })
+        Ok(ResultSet::Query { columns: self.columns, rows: Box::new(std::iter::empty()) })
     }
 }
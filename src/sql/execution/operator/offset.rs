@@ -0,0 +1,22 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}}, error::{Error, Result}};
+
+pub struct Offset<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    offset: u64,
+}
+
+impl<T: Transaction> Offset<T> {
+    pub fn new(source: Box<dyn Executor<T>>, offset: u64) -> Box<Self> {
+        Box::new(Self { source, offset })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Offset<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, rows } = self.source.execute(txn)? {
+            Ok(ResultSet::Query { columns, rows: Box::new(rows.skip(self.offset as usize)) })
+        } else {
+            Err(Error::Internal("Unexpected result".into()))
+        }
+    }
+}
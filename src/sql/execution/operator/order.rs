@@ -0,0 +1,61 @@
+use std::cmp::Ordering;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::Direction, types::{expression::Expression, Row, Value}}, error::{Error, Result}};
+
+pub struct Order<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    orders: Vec<(Expression, Direction)>,
+}
+
+impl<T: Transaction> Order<T> {
+    pub fn new(source: Box<dyn Executor<T>>, orders: Vec<(Expression, Direction)>) -> Box<Self> {
+        Box::new(Self { source, orders })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Order<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, mut rows } = self.source.execute(txn)? {
+            let mut items = Vec::new();
+            while let Some(row) = rows.next().transpose()? {
+                items.push(Item::new(row, &self.orders)?);
+            }
+            let directions: Vec<Direction> = self.orders.into_iter().map(|(_, d)| d).collect();
+            items.sort_by(|a, b| a.compare(b, &directions));
+            Ok(ResultSet::Query {
+                columns,
+                rows: Box::new(items.into_iter().map(|item| Ok(item.row))),
+            })
+        } else {
+            Err(Error::Internal("Unexpected result".into()))
+        }
+    }
+}
+
+/// A row along with its pre-evaluated sort values. Since we can't return errors from the sort
+/// comparator, the order expressions are evaluated up front.
+pub(super) struct Item {
+    pub row: Row,
+    pub values: Vec<Value>,
+}
+
+impl Item {
+    pub fn new(row: Row, orders: &[(Expression, Direction)]) -> Result<Self> {
+        let values =
+            orders.iter().map(|(expr, _)| expr.evaluate(Some(&row))).collect::<Result<_>>()?;
+        Ok(Self { row, values })
+    }
+
+    /// Compares the sort values of two items. NULLs sort first in ascending order, following
+    /// the Value ordering, and incomparable values are considered equal.
+    pub fn compare(&self, other: &Self, directions: &[Direction]) -> Ordering {
+        for ((a, b), direction) in self.values.iter().zip(&other.values).zip(directions) {
+            match a.partial_cmp(b) {
+                Some(Ordering::Equal) | None => {}
+                Some(o) if *direction == Direction::Ascending => return o,
+                Some(o) => return o.reverse(),
+            }
+        }
+        Ordering::Equal
+    }
+}
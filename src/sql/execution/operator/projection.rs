@@ -1,4 +1,4 @@
-use crate::{sql::{engine::Transaction, types::{expression::Expression, Column}, execution::{Executor, ResultSet}}, error::{Result, Error}};
+use crate::{sql::{engine::Transaction, types::{expression::Expression, Column}, execution::{resolve_subqueries, Executor, ResultSet}}, error::{Result, Error}};
 
 pub struct Projection<T: Transaction> {
     source: Box<dyn Executor<T>>,
@@ -14,11 +14,16 @@ impl<T: Transaction> Projection<T> {
     }
 }
 
-impl<T: Transaction> Executor<T> for Projection<T> {
+impl<T: Transaction + 'static> Executor<T> for Projection<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        // Uncorrelated subqueries are executed once, before reading any rows.
+        let (expressions, labels): (Vec<Expression>, Vec<Option<String>>) =
+            self.expressions.into_iter().unzip();
+        let expressions = expressions
+            .into_iter()
+            .map(|e| resolve_subqueries(e, None, txn))
+            .collect::<Result<Vec<_>>>()?;
         if let ResultSet::Query { columns, rows } = self.source.execute(txn)? {
-            let (expressions, labels): (Vec<Expression>, Vec<Option<String>>) =
-                self.expressions.into_iter().unzip();
             let columns = expressions
                 .iter()
                 .enumerate()
@@ -32,6 +37,24 @@ impl<T: Transaction> Executor<T> for Projection<T> {
                     }
                 })
                 .collect();
+            if expressions.iter().any(|e| e.is_volatile() || e.has_subqueries()) {
+                // NEXTVAL() and correlated subqueries need the transaction, so rows are projected
+                // eagerly.
+                let rows = rows
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .map(|row| {
+                        expressions
+                            .iter()
+                            .map(|e| {
+                                let e = e.clone().resolve_sequences(&|s| txn.next_value(s))?;
+                                resolve_subqueries(e, Some(&row), txn)?.evaluate(Some(&row))
+                            })
+                            .collect::<Result<_>>()
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                return Ok(ResultSet::Query { columns, rows: Box::new(rows.into_iter().map(Ok)) });
+            }
             let rows = Box::new(rows.map(move |r| {
                 r.and_then(|row| {
                     expressions.iter().map(|e| e.evaluate(Some(&row))).collect::<Result<_>>()
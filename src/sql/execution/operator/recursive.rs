@@ -0,0 +1,109 @@
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::{sql::{engine::Transaction, execution::{operator::analyze::NodeStats, Executor, ResultSet}, plan::Node, types::{normalize_row, Row}}, error::{Error, Result}};
+
+/// Executes a WITH RECURSIVE query as an iterative fixpoint: the anchor rows seed the working
+/// table, and the recursive term is executed with the working table bound to the rows produced
+/// by the previous iteration, until an iteration produces no (new) rows.
+pub struct Recursive<T: Transaction> {
+    name: String,
+    anchor: Box<dyn Executor<T>>,
+    recursive: Node,
+    all: bool,
+    max_iterations: u64,
+    /// Statistics for the nodes of the recursive term, accumulated across iterations, if
+    /// analyzed.
+    stats: Option<Vec<Arc<NodeStats>>>,
+}
+
+impl<T: Transaction> Recursive<T> {
+    pub fn new(
+        name: String,
+        anchor: Box<dyn Executor<T>>,
+        recursive: Node,
+        all: bool,
+        max_iterations: u64,
+        stats: Option<Vec<Arc<NodeStats>>>,
+    ) -> Box<Self> {
+        Box::new(Self { name, anchor, recursive, all, max_iterations, stats })
+    }
+}
+
+impl<T: Transaction + 'static> Executor<T> for Recursive<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let Self { name, anchor, recursive, all, max_iterations, stats } = *self;
+        let ResultSet::Query { columns, rows } = anchor.execute(txn)? else {
+            return Err(Error::Internal("Unexpected result".into()));
+        };
+
+        // Executes the recursive term with the given working table rows. When analyzed, the
+        // statistics of each execution are added to the statistics of the recursive term.
+        let iterate = |working: &[Row], referenced: &Cell<bool>, txn: &mut T| {
+            let node = bind(recursive.clone(), &name, working, referenced)?;
+            let (executor, iteration_stats) = match stats {
+                Some(_) => {
+                    let (executor, stats) = <dyn Executor<T>>::build_analyzed(node);
+                    (executor, Some(stats))
+                }
+                None => (<dyn Executor<T>>::build(node), None),
+            };
+            let rows = match executor.execute(txn)? {
+                ResultSet::Query { rows, .. } => rows.collect::<Result<Vec<_>>>()?,
+                r => return Err(Error::Internal(format!("Unexpected result {:?}", r))),
+            };
+            if let (Some(stats), Some(iteration_stats)) = (&stats, iteration_stats) {
+                stats.iter().zip(iteration_stats).for_each(|(s, i)| s.add(&i));
+            }
+            Ok(rows)
+        };
+
+        // Unless all is set, rows that were already emitted are discarded, which also ends the
+        // recursion once no new rows are found.
+        let mut seen = HashSet::new();
+        let mut keep = |row: &Row| all || seen.insert(normalize_row(row));
+        let mut working = Vec::new();
+        for row in rows {
+            let row = row?;
+            if keep(&row) {
+                working.push(row);
+            }
+        }
+        let mut result = working.clone();
+
+        let mut iterations = 0;
+        while !working.is_empty() {
+            if iterations == max_iterations {
+                return Err(Error::Value(format!(
+                    "Recursive query {} exceeded the maximum of {} iterations",
+                    name, max_iterations
+                )));
+            }
+            iterations += 1;
+            let referenced = Cell::new(false);
+            working = iterate(&working, &referenced, txn)?;
+            working.retain(&mut keep);
+            result.extend_from_slice(&working);
+            // A recursive term that doesn't reference the working table is a plain union.
+            if !referenced.get() {
+                break;
+            }
+        }
+        Ok(ResultSet::Query { columns, rows: Box::new(result.into_iter().map(Ok)) })
+    }
+}
+
+/// Fills in the rows of the named working table in a plan, including in subqueries, recording
+/// whether it was referenced.
+fn bind(node: Node, name: &str, rows: &[Row], referenced: &Cell<bool>) -> Result<Node> {
+    node.transform(&Ok, &|n| match n {
+        Node::WorkingTable { name: table, columns, rows: _ } if table == name => {
+            referenced.set(true);
+            Ok(Node::WorkingTable { name: table, columns, rows: rows.to_vec() })
+        }
+        n => n.transform_expressions(&Ok, &|e| {
+            e.transform_subquery(&|node| bind(node, name, rows, referenced))
+        }),
+    })
+}
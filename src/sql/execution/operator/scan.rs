@@ -1,22 +1,30 @@
-use crate::{sql::{types::{expression::Expression, Column}, engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
+use crate::{sql::{types::{expression::Expression, Column, Value}, engine::Transaction, execution::{Executor, ResultSet}}, error::Result};
 
 pub struct Scan {
     table: String,
     filter: Option<Expression>,
+    columns: Option<Vec<usize>>,
 }
 
 impl Scan {
-    pub fn new(table: String, filter: Option<Expression>) -> Box<Self> {
-        Box::new(Self { table, filter })
+    pub fn new(
+        table: String,
+        filter: Option<Expression>,
+        columns: Option<Vec<usize>>,
+    ) -> Box<Self> {
+        Box::new(Self { table, filter, columns })
     }
 }
 
 impl<T: Transaction> Executor<T> for Scan {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
         let table = txn.must_read_table(&self.table)?;
-        Ok(ResultSet::Query {
-            columns: table.columns.iter().map(|c| Column { name: Some(c.name.clone()) }).collect(),
-            rows: Box::new(txn.scan(&table.name, self.filter)?),
-        })
+        let columns = table.columns.iter().map(|c| Column { name: Some(c.name.clone()) }).collect();
+        // A filter that is always false or NULL can't match any rows, so don't read them.
+        if let Some(Expression::Constant(Value::Boolean(false) | Value::Null)) = self.filter {
+            return Ok(ResultSet::Query { columns, rows: Box::new(std::iter::empty()) });
+        }
+        let rows = txn.scan(&table.name, self.filter, self.columns.as_deref())?;
+        Ok(ResultSet::Query { columns, rows: Box::new(rows) })
     }
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+use std::cmp::Ordering;
+
+use super::order::Item;
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::Direction, types::expression::Expression}, error::{Error, Result}};
+
+/// Sorts the source rows and returns the first `limit` of them. Only the best rows seen so far
+/// are retained, so the input is never materialized in full.
+pub struct TopN<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    orders: Vec<(Expression, Direction)>,
+    limit: u64,
+}
+
+impl<T: Transaction> TopN<T> {
+    pub fn new(
+        source: Box<dyn Executor<T>>,
+        orders: Vec<(Expression, Direction)>,
+        limit: u64,
+    ) -> Box<Self> {
+        Box::new(Self { source, orders, limit })
+    }
+}
+
+impl<T: Transaction> Executor<T> for TopN<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, mut rows } = self.source.execute(txn)? {
+            let limit = self.limit as usize;
+            let directions: Vec<Direction> = self.orders.iter().map(|(_, d)| d.clone()).collect();
+            // The retained items are kept sorted. New items are inserted after any equal ones,
+            // which keeps the sort stable like the Order executor.
+            let mut items: Vec<Item> = Vec::new();
+            while let Some(row) = rows.next().transpose()? {
+                if limit == 0 {
+                    break;
+                }
+                let item = Item::new(row, &self.orders)?;
+                if items.len() == limit
+                    && items.last().map(|l| item.compare(l, &directions)) != Some(Ordering::Less)
+                {
+                    continue;
+                }
+                let index =
+                    items.partition_point(|i| i.compare(&item, &directions) != Ordering::Greater);
+                items.insert(index, item);
+                items.truncate(limit);
+            }
+            Ok(ResultSet::Query {
+                columns,
+                rows: Box::new(items.into_iter().map(|item| Ok(item.row))),
+            })
+        } else {
+            Err(Error::Internal("Unexpected result".into()))
+        }
+    }
+}
@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::normalize_row}, error::{Error, Result}};
+
+/// Emits the rows of the left source followed by the rows of the right source. Unless all is
+/// set, duplicate rows are removed as they're streamed, remembering the rows emitted so far.
+/// Rows are compared by normalized value, so e.g. 1 and 1.0 are duplicates.
+pub struct Union<T: Transaction> {
+    left: Box<dyn Executor<T>>,
+    right: Box<dyn Executor<T>>,
+    all: bool,
+}
+
+impl<T: Transaction> Union<T> {
+    pub fn new(left: Box<dyn Executor<T>>, right: Box<dyn Executor<T>>, all: bool) -> Box<Self> {
+        Box::new(Self { left, right, all })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Union<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { columns, rows } = self.left.execute(txn)? {
+            if let ResultSet::Query { rows: rrows, .. } = self.right.execute(txn)? {
+                let rows = rows.chain(rrows);
+                if self.all {
+                    return Ok(ResultSet::Query { columns, rows: Box::new(rows) });
+                }
+                let mut seen = HashSet::new();
+                return Ok(ResultSet::Query {
+                    columns,
+                    rows: Box::new(rows.filter(move |row| match row {
+                        Ok(row) => seen.insert(normalize_row(row)),
+                        Err(_) => true,
+                    })),
+                });
+            }
+        }
+        Err(Error::Internal("Unexpected result set".into()))
+    }
+}
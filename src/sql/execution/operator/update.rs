@@ -1,6 +1,6 @@
 use std::collections::HashSet;
 
-use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::expression::Expression}, error::{Result, Error}};
+use crate::{sql::{engine::Transaction, execution::{resolve_subqueries, Executor, ResultSet}, types::expression::Expression}, error::{Result, Error}};
 
 pub struct Update<T: Transaction> {
     table: String,
@@ -18,8 +18,14 @@ impl<T: Transaction> Update<T> {
     }
 }
 
-impl<T: Transaction> Executor<T> for Update<T> {
+impl<T: Transaction + 'static> Executor<T> for Update<T> {
     fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        // Uncorrelated subqueries are executed once, before reading any rows.
+        let expressions = self
+            .expressions
+            .into_iter()
+            .map(|(field, expr)| Ok((field, resolve_subqueries(expr, None, txn)?)))
+            .collect::<Result<Vec<_>>>()?;
         match self.source.execute(txn)? {
             ResultSet::Query { mut rows, .. } => {
                 let table = txn.must_read_table(&self.table)?;
@@ -30,8 +36,17 @@ impl<T: Transaction> Executor<T> for Update<T> {
                         continue;
                     }
                     let mut new = row.clone();
-                    for (field, expr) in &self.expressions {
-                        new[*field] = expr.evaluate(Some(&row))?;
+                    for (field, expr) in &expressions {
+                        let value = if expr.is_volatile() || expr.has_subqueries() {
+                            let expr = expr.clone().resolve_sequences(&|s| txn.next_value(s))?;
+                            resolve_subqueries(expr, Some(&row), txn)?.evaluate(Some(&row))?
+                        } else {
+                            expr.evaluate(Some(&row))?
+                        };
+                        new[*field] = match table.columns.get(*field) {
+                            Some(column) => value.coerce(&column.datatype)?,
+                            None => value,
+                        };
                     }
                     txn.update(&table.name, &id, new)?;
                     updated.insert(id);
@@ -0,0 +1,20 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{Column, Row}}, error::Result};
+
+/// Emits the given rows, with unnamed columns.
+pub struct Values {
+    rows: Vec<Row>,
+}
+
+impl Values {
+    pub fn new(rows: Vec<Row>) -> Box<Self> {
+        Box::new(Self { rows })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Values {
+    fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+        let width = self.rows.first().map_or(0, |row| row.len());
+        let columns = vec![Column { name: None }; width];
+        Ok(ResultSet::Query { columns, rows: Box::new(self.rows.into_iter().map(Ok)) })
+    }
+}
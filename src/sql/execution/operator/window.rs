@@ -0,0 +1,239 @@
+use std::cmp::Ordering;
+
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, plan::{Direction, Frame, FrameBound, WindowFunction}, types::{expression::Expression, Column, Value}}, error::{Error, Result}};
+
+use super::{aggregation::Accumulator, order::Item};
+
+/// Computes window functions over the source rows. The rows are sorted by the partition
+/// expressions and then the orders, split into partitions of rows with equal partition values,
+/// and the function values of each row are appended to it.
+pub struct Window<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    partition: Vec<Expression>,
+    orders: Vec<(Expression, Direction)>,
+    functions: Vec<(WindowFunction, Frame)>,
+}
+
+impl<T: Transaction> Window<T> {
+    pub fn new(
+        source: Box<dyn Executor<T>>,
+        partition: Vec<Expression>,
+        orders: Vec<(Expression, Direction)>,
+        functions: Vec<(WindowFunction, Frame)>,
+    ) -> Box<Self> {
+        Box::new(Self { source, partition, orders, functions })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Window<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        if let ResultSet::Query { mut columns, mut rows } = self.source.execute(txn)? {
+            let width = self.partition.len();
+            let keys: Vec<_> = self
+                .partition
+                .into_iter()
+                .map(|e| (e, Direction::Ascending))
+                .chain(self.orders)
+                .collect();
+            let mut items = Vec::new();
+            while let Some(row) = rows.next().transpose()? {
+                items.push(Item::new(row, &keys)?);
+            }
+            let directions: Vec<Direction> = keys.into_iter().map(|(_, d)| d).collect();
+            items.sort_by(|a, b| a.compare(b, &directions));
+
+            // Compute the function values of each partition, in row order.
+            let mut values: Vec<Vec<Value>> = vec![Vec::new(); items.len()];
+            let mut start = 0;
+            while start < items.len() {
+                let end = (start + 1..items.len())
+                    .find(|&i| items[i].values[..width] != items[start].values[..width])
+                    .unwrap_or(items.len());
+                let partition = Partition::new(&items[start..end], width, &directions[width..]);
+                for (function, frame) in &self.functions {
+                    for (i, value) in partition.compute(function, frame)?.into_iter().enumerate() {
+                        values[start + i].push(value);
+                    }
+                }
+                start = end;
+            }
+
+            columns.extend(self.functions.iter().map(|_| Column { name: None }));
+            Ok(ResultSet::Query {
+                columns,
+                rows: Box::new(items.into_iter().zip(values).map(|(item, values)| {
+                    let mut row = item.row;
+                    row.extend(values);
+                    Ok(row)
+                })),
+            })
+        } else {
+            Err(Error::Internal("Unexpected result".into()))
+        }
+    }
+}
+
+/// The sorted rows of a partition, whose sort values start with the partition values, followed
+/// by the order values.
+struct Partition<'a> {
+    items: &'a [Item],
+    /// The number of partition values.
+    width: usize,
+    /// The directions of the order values.
+    directions: &'a [Direction],
+    /// The start and end of each row's peers, i.e. rows with equal order values.
+    peers: Vec<(usize, usize)>,
+}
+
+impl<'a> Partition<'a> {
+    fn new(items: &'a [Item], width: usize, directions: &'a [Direction]) -> Self {
+        let mut peers = Vec::with_capacity(items.len());
+        let mut start = 0;
+        while start < items.len() {
+            let end = (start + 1..items.len())
+                .find(|&i| items[i].values[width..] != items[start].values[width..])
+                .unwrap_or(items.len());
+            peers.extend((start..end).map(|_| (start, end)));
+            start = end;
+        }
+        Self { items, width, directions, peers }
+    }
+
+    /// Computes a window function for each row of the partition.
+    fn compute(&self, function: &WindowFunction, frame: &Frame) -> Result<Vec<Value>> {
+        let (items, len) = (self.items, self.items.len());
+        Ok(match function {
+            WindowFunction::RowNumber => (1..=len).map(|i| Value::Integer(i as i64)).collect(),
+            WindowFunction::Rank => {
+                self.peers.iter().map(|(start, _)| Value::Integer(*start as i64 + 1)).collect()
+            }
+            WindowFunction::DenseRank => {
+                let mut rank = 0;
+                (0..len)
+                    .map(|i| {
+                        if self.peers[i].0 == i {
+                            rank += 1;
+                        }
+                        Value::Integer(rank)
+                    })
+                    .collect()
+            }
+            WindowFunction::Lag(expr, offset, default) => (0..len)
+                .map(|i| match i.checked_sub(*offset as usize) {
+                    Some(j) => expr.evaluate(Some(&items[j].row)),
+                    None => default.evaluate(Some(&items[i].row)),
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::Lead(expr, offset, default) => (0..len)
+                .map(|i| match i.checked_add(*offset as usize).filter(|j| *j < len) {
+                    Some(j) => expr.evaluate(Some(&items[j].row)),
+                    None => default.evaluate(Some(&items[i].row)),
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::FirstValue(expr) => (0..len)
+                .map(|i| match self.frame(frame, i)? {
+                    (start, end) if start < end => expr.evaluate(Some(&items[start].row)),
+                    _ => Ok(Value::Null),
+                })
+                .collect::<Result<_>>()?,
+            WindowFunction::Aggregate(aggregate, expr) => {
+                let args: Vec<Value> =
+                    items.iter().map(|item| expr.evaluate(Some(&item.row))).collect::<Result<_>>()?;
+                let mut values = Vec::with_capacity(len);
+                if frame.start == FrameBound::UnboundedPreceding {
+                    // Frame ends never move backwards, so a running aggregate can be used.
+                    let (mut accumulator, mut next) = (<dyn Accumulator>::from(aggregate), 0);
+                    for i in 0..len {
+                        let (_, end) = self.frame(frame, i)?;
+                        while next < end {
+                            accumulator.accumulate(args[next].clone())?;
+                            next += 1;
+                        }
+                        values.push(accumulator.aggregate());
+                    }
+                } else {
+                    for i in 0..len {
+                        let (start, end) = self.frame(frame, i)?;
+                        let mut accumulator = <dyn Accumulator>::from(aggregate);
+                        for arg in args.iter().take(end).skip(start) {
+                            accumulator.accumulate(arg.clone())?;
+                        }
+                        values.push(accumulator.aggregate());
+                    }
+                }
+                values
+            }
+        })
+    }
+
+    /// Returns the frame of the given row, as a range of row indexes. The range is empty if the
+    /// start comes after the end.
+    fn frame(&self, frame: &Frame, i: usize) -> Result<(usize, usize)> {
+        let len = self.items.len();
+        let start = match &frame.start {
+            FrameBound::UnboundedPreceding => 0,
+            FrameBound::CurrentRow if frame.rows => i,
+            FrameBound::CurrentRow => self.peers[i].0,
+            FrameBound::Preceding(offset) if frame.rows => i.saturating_sub(rows(offset)?),
+            FrameBound::Following(offset) if frame.rows => i.saturating_add(rows(offset)?),
+            bound => self.range(bound, i, true)?,
+        };
+        let end = match &frame.end {
+            FrameBound::UnboundedFollowing => len,
+            FrameBound::CurrentRow if frame.rows => i + 1,
+            FrameBound::CurrentRow => self.peers[i].1,
+            FrameBound::Preceding(offset) if frame.rows => (i + 1).saturating_sub(rows(offset)?),
+            FrameBound::Following(offset) if frame.rows => {
+                i.saturating_add(rows(offset)?).saturating_add(1)
+            }
+            bound => self.range(bound, i, false)?,
+        };
+        Ok((start.min(len), end.min(len)))
+    }
+
+    /// Returns a RANGE frame bound of the given row, which is the first row within the offset
+    /// from the row's order value for a start bound, or the first row beyond it for an end
+    /// bound. Rows with a NULL order value only have their peers in the frame.
+    fn range(&self, bound: &FrameBound, i: usize, start: bool) -> Result<usize> {
+        let value = &self.items[i].values[self.width];
+        let descending = self.directions.first() == Some(&Direction::Descending);
+        let target = match bound {
+            _ if *value == Value::Null => {
+                return Ok(if start { self.peers[i].0 } else { self.peers[i].1 })
+            }
+            FrameBound::Preceding(offset) | FrameBound::Following(offset) => {
+                let (value, offset) = (Expression::Constant(value.clone()), offset.clone());
+                let offset = Box::new(Expression::Constant(offset));
+                // Preceding rows have smaller values in ascending order.
+                match (matches!(bound, FrameBound::Preceding(_)), descending) {
+                    (true, false) | (false, true) => Expression::Subtract(value.into(), offset),
+                    (true, true) | (false, false) => Expression::Add(value.into(), offset),
+                }
+                .evaluate(None)?
+            }
+            bound => return Err(Error::Internal(format!("Unexpected frame bound {}", bound))),
+        };
+        // Compares a row's order value to the target value, in sort order.
+        let compare = |item: &Item| {
+            let ordering = item.values[self.width].partial_cmp(&target).unwrap_or(Ordering::Equal);
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        };
+        Ok(if start {
+            self.items.partition_point(|item| compare(item) == Ordering::Less)
+        } else {
+            self.items.partition_point(|item| compare(item) != Ordering::Greater)
+        })
+    }
+}
+
+/// Converts a ROWS frame offset to a number of rows.
+fn rows(offset: &Value) -> Result<usize> {
+    match offset {
+        Value::Integer(i) if *i >= 0 => Ok(*i as usize),
+        offset => Err(Error::Internal(format!("Invalid frame offset {}", offset))),
+    }
+}
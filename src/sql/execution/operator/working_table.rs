@@ -0,0 +1,20 @@
+use crate::{sql::{engine::Transaction, execution::{Executor, ResultSet}, types::{Columns, Row}}, error::Result};
+
+/// Emits the rows of a WITH RECURSIVE working table, as filled in by the Recursive executor.
+pub struct WorkingTable {
+    columns: Columns,
+    rows: Vec<Row>,
+}
+
+impl WorkingTable {
+    pub fn new(columns: Columns, rows: Vec<Row>) -> Box<Self> {
+        Box::new(Self { columns, rows })
+    }
+}
+
+impl<T: Transaction> Executor<T> for WorkingTable {
+    fn execute(self: Box<Self>, _: &mut T) -> Result<ResultSet> {
+        let rows = Box::new(self.rows.into_iter().map(Ok));
+        Ok(ResultSet::Query { columns: self.columns, rows })
+    }
+}
@@ -1,3 +1,4 @@
+use super::super::schema::table::ReferenceAction;
 use super::super::types::DataType;
 use crate::error::Result;
 
@@ -14,13 +15,47 @@ pub enum Statement {
     },
     Commit,
     Rollback,
-    Explain(Box<Statement>),
+    Explain {
+        statement: Box<Statement>,
+        analyze: bool,
+    },
 
     CreateTable {
         name: String,
         columns: Vec<Column>,
+        primary_key: Vec<String>,
+        checks: Vec<Expression>,
     },
     DropTable(String),
+    CreateSequence {
+        name: String,
+        start: Option<i64>,
+        increment: Option<i64>,
+    },
+    DropSequence(String),
+    /// A view, given as the SQL text of its SELECT query
+    CreateView {
+        name: String,
+        query: String,
+    },
+    DropView(String),
+    AddColumn {
+        table: String,
+        column: Column,
+    },
+    DropColumn {
+        table: String,
+        column: String,
+    },
+    RenameColumn {
+        table: String,
+        column: String,
+        name: String,
+    },
+    RenameTable {
+        table: String,
+        name: String,
+    },
 
     Delete {
         table: String,
@@ -38,6 +73,9 @@ pub enum Statement {
     },
 
     Select {
+        with: Vec<CommonTableExpression>,
+        /// DISTINCT ON expressions, if given. Plain DISTINCT has no expressions.
+        distinct: Option<Vec<Expression>>,
         select: Vec<(Expression, Option<String>)>,
         from: Vec<FromItem>,
         r#where: Option<Expression>,
@@ -47,6 +85,30 @@ pub enum Statement {
         offset: Option<Expression>,
         limit: Option<Expression>,
     },
+    /// A compound query, combining the rows of two queries. ORDER BY, OFFSET and LIMIT apply to
+    /// the combined rows.
+    SetOperation {
+        with: Vec<CommonTableExpression>,
+        operator: SetOperator,
+        all: bool,
+        left: Box<Statement>,
+        right: Box<Statement>,
+        order: Vec<(Expression, Order)>,
+        offset: Option<Expression>,
+        limit: Option<Expression>,
+    },
+}
+
+/// A common table expression, i.e. a named query in a WITH clause
+#[derive(Clone, Debug, PartialEq)]
+pub struct CommonTableExpression {
+    pub name: String,
+    /// Column names, if given, replacing the query's column labels
+    pub columns: Vec<String>,
+    pub query: Box<Statement>,
+    /// For WITH RECURSIVE, the recursive term following UNION [ALL] in the query, and whether
+    /// it is UNION ALL
+    pub recursive: Option<(Box<Statement>, bool)>,
 }
 
 /// A FROM item
@@ -62,6 +124,11 @@ pub enum FromItem {
         r#type: JoinType,
         predicate: Option<Expression>,
     },
+    /// A derived table, i.e. a SELECT query in the FROM clause
+    Subquery {
+        query: Box<Statement>,
+        alias: String,
+    },
 }
 
 /// A JOIN type
@@ -71,6 +138,7 @@ pub enum JoinType {
     Inner,
     Left,
     Right,
+    Full,
 }
 
 /// A column
@@ -78,12 +146,25 @@ pub enum JoinType {
 pub struct Column {
     pub name: String,
     pub datatype: DataType,
+    pub max_length: Option<usize>,
     pub primary_key: bool,
     pub nullable: Option<bool>,
     pub default: Option<Expression>,
     pub unique: bool,
     pub index: bool,
     pub references: Option<String>,
+    pub on_delete: Option<ReferenceAction>,
+    pub on_update: Option<ReferenceAction>,
+    pub auto_increment: bool,
+    pub checks: Vec<Expression>,
+}
+
+/// Set operators of compound queries
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
 }
 
 /// Sort orders
@@ -100,7 +181,14 @@ pub enum Expression {
     Column(usize), // only used during plan building to break off expression subtrees
     Literal(Literal),
     Function(String, Vec<Expression>),
+    Cast(Box<Expression>, DataType),
     Operation(Operation),
+    /// A scalar subquery, returning a single value
+    Subquery(Box<Statement>),
+    Exists(Box<Statement>),
+    InSubquery(Box<Expression>, Box<Statement>),
+    /// A window function call, e.g. RANK() OVER (PARTITION BY a ORDER BY b)
+    WindowFunction(String, Vec<Expression>, Box<Window>),
 }
 
 impl From<Literal> for Expression {
@@ -115,6 +203,39 @@ impl From<Operation> for Expression {
     }
 }
 
+/// The window of a window function call
+#[derive(Clone, Debug, PartialEq)]
+pub struct Window {
+    pub partition: Vec<Expression>,
+    pub order: Vec<(Expression, Order)>,
+    pub frame: Option<Frame>,
+}
+
+/// A window frame, i.e. the rows around the current row that a window function is computed over
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+    pub units: FrameUnits,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+/// Window frame units. ROWS counts rows, while RANGE compares ORDER BY values.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FrameUnits {
+    Rows,
+    Range,
+}
+
+/// Window frame bounds, with the given offset for PRECEDING and FOLLOWING
+#[derive(Clone, Debug, PartialEq)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Expression),
+    CurrentRow,
+    Following(Expression),
+    UnboundedFollowing,
+}
+
 /// Literals
 #[derive(Clone, Debug, PartialEq)]
 pub enum Literal {
@@ -123,6 +244,7 @@ pub enum Literal {
     Integer(i64),
     Float(f64),
     String(String),
+    Blob(Vec<u8>),
 }
 
 /// Operations (done by operators)
@@ -154,7 +276,13 @@ pub enum Operation {
     Subtract(Box<Expression>, Box<Expression>),
 
     // String operators
+    ILike(Box<Expression>, Box<Expression>),
     Like(Box<Expression>, Box<Expression>),
+    Regex(Box<Expression>, Box<Expression>),
+
+    // JSON operators
+    JsonGet(Box<Expression>, Box<Expression>),
+    JsonGetText(Box<Expression>, Box<Expression>),
 }
 
 impl Expression {
@@ -191,7 +319,11 @@ impl Expression {
             | Self::Operation(GreaterThanOrEqual(lhs, rhs))
             | Self::Operation(LessThan(lhs, rhs))
             | Self::Operation(LessThanOrEqual(lhs, rhs))
+            | Self::Operation(ILike(lhs, rhs))
+            | Self::Operation(JsonGet(lhs, rhs))
+            | Self::Operation(JsonGetText(lhs, rhs))
             | Self::Operation(Like(lhs, rhs))
+            | Self::Operation(Regex(lhs, rhs))
             | Self::Operation(Modulo(lhs, rhs))
             | Self::Operation(Multiply(lhs, rhs))
             | Self::Operation(NotEqual(lhs, rhs))
@@ -201,7 +333,9 @@ impl Expression {
                 Self::replace_with(rhs, |e| e.transform(before, after))?;
             }
 
-            Self::Operation(Assert(expr))
+            Self::Cast(expr, _)
+            | Self::InSubquery(expr, _)
+            | Self::Operation(Assert(expr))
             | Self::Operation(Factorial(expr))
             | Self::Operation(IsNull(expr))
             | Self::Operation(Negate(expr))
@@ -215,7 +349,20 @@ impl Expression {
                 }
             }
 
-            Self::Literal(_) | Self::Field(_, _) | Self::Column(_) => {}
+            // The function itself is not descended into, only its arguments and window.
+            Self::WindowFunction(_, args, window) => {
+                let order = window.order.iter_mut().map(|(e, _)| e);
+                for expr in args.iter_mut().chain(window.partition.iter_mut()).chain(order) {
+                    Self::replace_with(expr, |e| e.transform(before, after))?;
+                }
+            }
+
+            // Subqueries have their own scope, so they're not descended into.
+            Self::Literal(_)
+            | Self::Field(_, _)
+            | Self::Column(_)
+            | Self::Subquery(_)
+            | Self::Exists(_) => {}
         };
         after(self)
     }
@@ -243,14 +390,20 @@ impl Expression {
                 | Self::Operation(GreaterThanOrEqual(lhs, rhs))
                 | Self::Operation(LessThan(lhs, rhs))
                 | Self::Operation(LessThanOrEqual(lhs, rhs))
+                | Self::Operation(ILike(lhs, rhs))
+                | Self::Operation(JsonGet(lhs, rhs))
+                | Self::Operation(JsonGetText(lhs, rhs))
                 | Self::Operation(Like(lhs, rhs))
+                | Self::Operation(Regex(lhs, rhs))
                 | Self::Operation(Modulo(lhs, rhs))
                 | Self::Operation(Multiply(lhs, rhs))
                 | Self::Operation(NotEqual(lhs, rhs))
                 | Self::Operation(Or(lhs, rhs))
                 | Self::Operation(Subtract(lhs, rhs)) => lhs.walk(visitor) && rhs.walk(visitor),
 
-                Self::Operation(Assert(expr))
+                Self::Cast(expr, _)
+                | Self::InSubquery(expr, _)
+                | Self::Operation(Assert(expr))
                 | Self::Operation(Factorial(expr))
                 | Self::Operation(IsNull(expr))
                 | Self::Operation(Negate(expr))
@@ -265,7 +418,17 @@ impl Expression {
                     true
                 }
 
-                Self::Literal(_) | Self::Field(_, _) | Self::Column(_) => true,
+                Self::WindowFunction(_, args, window) => args
+                    .iter()
+                    .chain(&window.partition)
+                    .chain(window.order.iter().map(|(e, _)| e))
+                    .all(|e| e.walk(visitor)),
+
+                Self::Literal(_)
+                | Self::Field(_, _)
+                | Self::Column(_)
+                | Self::Subquery(_)
+                | Self::Exists(_) => true,
             }
     }
 }
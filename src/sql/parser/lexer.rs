@@ -8,6 +8,8 @@ use std::str::Chars;
 pub enum Token {
     Number(String),
     String(String),
+    /// A hex-encoded blob literal, e.g. X'00ff'
+    HexString(String),
     Ident(String),
     Keyword(Keyword),
     Period,
@@ -26,6 +28,10 @@ pub enum Token {
     Exclamation,
     NotEqual,
     Question,
+    Tilde,
+    NotTilde,
+    Arrow,
+    DoubleArrow,
     OpenParen,
     CloseParen,
     Comma,
@@ -37,6 +43,7 @@ impl std::fmt::Display for Token {
         f.write_str(match self {
             Token::Number(n) => n,
             Token::String(s) => s,
+            Token::HexString(s) => s,
             Token::Ident(s) => s,
             Token::Keyword(k) => k.to_str(),
             Token::Period => ".",
@@ -55,6 +62,10 @@ impl std::fmt::Display for Token {
             Token::Exclamation => "!",
             Token::NotEqual => "!=",
             Token::Question => "?",
+            Token::Tilde => "~",
+            Token::NotTilde => "!~",
+            Token::Arrow => "->",
+            Token::DoubleArrow => "->>",
             Token::OpenParen => "(",
             Token::CloseParen => ")",
             Token::Comma => ",",
@@ -72,44 +83,72 @@ impl From<Keyword> for Token {
 /// Lexer keywords
 #[derive(Clone, Debug, PartialEq)]
 pub enum Keyword {
+    Action,
+    Add,
+    All,
+    Alter,
+    Analyze,
     And,
     As,
     Asc,
+    AutoIncrement,
     Begin,
+    Between,
+    Blob,
     Bool,
     Boolean,
     By,
+    Bytea,
+    Cascade,
+    Cast,
     Char,
+    Check,
+    Column,
     Commit,
     Create,
     Cross,
+    Current,
+    Date,
+    Decimal,
     Default,
     Delete,
     Desc,
+    Distinct,
     Double,
     Drop,
+    Except,
+    Exists,
     Explain,
     False,
     Float,
+    Following,
     From,
+    Full,
     Group,
     Having,
+    ILike,
+    In,
+    Increment,
     Index,
     Infinity,
     Inner,
     Insert,
     Int,
     Integer,
+    Intersect,
     Into,
     Is,
     Join,
+    Json,
     Key,
     Left,
     Like,
     Limit,
     NaN,
+    No,
     Not,
     Null,
+    Numeric,
     Of,
     Offset,
     On,
@@ -117,25 +156,44 @@ pub enum Keyword {
     Or,
     Order,
     Outer,
+    Over,
+    Partition,
+    Preceding,
     Primary,
+    Range,
     Read,
+    Recursive,
     References,
+    Rename,
+    Restrict,
     Right,
     Rollback,
+    Row,
+    Rows,
     Select,
+    Sequence,
+    Serial,
     Set,
+    Start,
     String,
     System,
     Table,
     Text,
     Time,
+    Timestamp,
+    To,
     Transaction,
     True,
+    Unbounded,
+    Union,
     Unique,
     Update,
+    Uuid,
     Values,
     Varchar,
+    View,
     Where,
+    With,
     Write,
 }
 
@@ -145,42 +203,70 @@ impl Keyword {
         Some(match ident.to_uppercase().as_ref() {
             "AS" => Self::As,
             "ASC" => Self::Asc,
+            "AUTO_INCREMENT" => Self::AutoIncrement,
+            "ACTION" => Self::Action,
+            "ADD" => Self::Add,
+            "ALL" => Self::All,
+            "ALTER" => Self::Alter,
+            "ANALYZE" => Self::Analyze,
             "AND" => Self::And,
             "BEGIN" => Self::Begin,
+            "BETWEEN" => Self::Between,
+            "BLOB" => Self::Blob,
             "BOOL" => Self::Bool,
             "BOOLEAN" => Self::Boolean,
             "BY" => Self::By,
+            "BYTEA" => Self::Bytea,
+            "CASCADE" => Self::Cascade,
+            "CAST" => Self::Cast,
             "CHAR" => Self::Char,
+            "CHECK" => Self::Check,
+            "COLUMN" => Self::Column,
             "COMMIT" => Self::Commit,
             "CREATE" => Self::Create,
             "CROSS" => Self::Cross,
+            "CURRENT" => Self::Current,
+            "DATE" => Self::Date,
+            "DECIMAL" => Self::Decimal,
             "DEFAULT" => Self::Default,
             "DELETE" => Self::Delete,
             "DESC" => Self::Desc,
+            "DISTINCT" => Self::Distinct,
             "DOUBLE" => Self::Double,
             "DROP" => Self::Drop,
+            "EXCEPT" => Self::Except,
+            "EXISTS" => Self::Exists,
             "EXPLAIN" => Self::Explain,
             "FALSE" => Self::False,
             "FLOAT" => Self::Float,
+            "FOLLOWING" => Self::Following,
             "FROM" => Self::From,
+            "FULL" => Self::Full,
             "GROUP" => Self::Group,
             "HAVING" => Self::Having,
+            "ILIKE" => Self::ILike,
+            "IN" => Self::In,
+            "INCREMENT" => Self::Increment,
             "INDEX" => Self::Index,
             "INFINITY" => Self::Infinity,
             "INNER" => Self::Inner,
             "INSERT" => Self::Insert,
             "INT" => Self::Int,
             "INTEGER" => Self::Integer,
+            "INTERSECT" => Self::Intersect,
             "INTO" => Self::Into,
             "IS" => Self::Is,
             "JOIN" => Self::Join,
+            "JSON" => Self::Json,
             "KEY" => Self::Key,
             "LEFT" => Self::Left,
             "LIKE" => Self::Like,
             "LIMIT" => Self::Limit,
             "NAN" => Self::NaN,
+            "NO" => Self::No,
             "NOT" => Self::Not,
             "NULL" => Self::Null,
+            "NUMERIC" => Self::Numeric,
             "OF" => Self::Of,
             "OFFSET" => Self::Offset,
             "ON" => Self::On,
@@ -188,25 +274,44 @@ impl Keyword {
             "OR" => Self::Or,
             "ORDER" => Self::Order,
             "OUTER" => Self::Outer,
+            "OVER" => Self::Over,
+            "PARTITION" => Self::Partition,
+            "PRECEDING" => Self::Preceding,
             "PRIMARY" => Self::Primary,
+            "RANGE" => Self::Range,
             "READ" => Self::Read,
+            "RECURSIVE" => Self::Recursive,
             "REFERENCES" => Self::References,
+            "RENAME" => Self::Rename,
+            "RESTRICT" => Self::Restrict,
             "RIGHT" => Self::Right,
             "ROLLBACK" => Self::Rollback,
+            "ROW" => Self::Row,
+            "ROWS" => Self::Rows,
             "SELECT" => Self::Select,
+            "SEQUENCE" => Self::Sequence,
+            "SERIAL" => Self::Serial,
             "SET" => Self::Set,
+            "START" => Self::Start,
             "STRING" => Self::String,
             "SYSTEM" => Self::System,
             "TABLE" => Self::Table,
             "TEXT" => Self::Text,
             "TIME" => Self::Time,
+            "TIMESTAMP" => Self::Timestamp,
+            "TO" => Self::To,
             "TRANSACTION" => Self::Transaction,
             "TRUE" => Self::True,
+            "UNBOUNDED" => Self::Unbounded,
+            "UNION" => Self::Union,
             "UNIQUE" => Self::Unique,
             "UPDATE" => Self::Update,
+            "UUID" => Self::Uuid,
             "VALUES" => Self::Values,
             "VARCHAR" => Self::Varchar,
+            "VIEW" => Self::View,
             "WHERE" => Self::Where,
+            "WITH" => Self::With,
             "WRITE" => Self::Write,
             _ => return None,
         })
@@ -216,42 +321,70 @@ impl Keyword {
         match self {
             Self::As => "AS",
             Self::Asc => "ASC",
+            Self::AutoIncrement => "AUTO_INCREMENT",
+            Self::Action => "ACTION",
+            Self::Add => "ADD",
+            Self::All => "ALL",
+            Self::Alter => "ALTER",
+            Self::Analyze => "ANALYZE",
             Self::And => "AND",
             Self::Begin => "BEGIN",
+            Self::Between => "BETWEEN",
+            Self::Blob => "BLOB",
             Self::Bool => "BOOL",
             Self::Boolean => "BOOLEAN",
             Self::By => "BY",
+            Self::Bytea => "BYTEA",
+            Self::Cascade => "CASCADE",
+            Self::Cast => "CAST",
             Self::Char => "CHAR",
+            Self::Check => "CHECK",
+            Self::Column => "COLUMN",
             Self::Commit => "COMMIT",
             Self::Create => "CREATE",
             Self::Cross => "CROSS",
+            Self::Current => "CURRENT",
+            Self::Date => "DATE",
+            Self::Decimal => "DECIMAL",
             Self::Default => "DEFAULT",
             Self::Delete => "DELETE",
             Self::Desc => "DESC",
+            Self::Distinct => "DISTINCT",
             Self::Double => "DOUBLE",
             Self::Drop => "DROP",
+            Self::Except => "EXCEPT",
+            Self::Exists => "EXISTS",
             Self::Explain => "EXPLAIN",
             Self::False => "FALSE",
             Self::Float => "FLOAT",
+            Self::Following => "FOLLOWING",
             Self::From => "FROM",
+            Self::Full => "FULL",
             Self::Group => "GROUP",
             Self::Having => "HAVING",
+            Self::ILike => "ILIKE",
+            Self::In => "IN",
+            Self::Increment => "INCREMENT",
             Self::Index => "INDEX",
             Self::Infinity => "INFINITY",
             Self::Inner => "INNER",
             Self::Insert => "INSERT",
             Self::Int => "INT",
             Self::Integer => "INTEGER",
+            Self::Intersect => "INTERSECT",
             Self::Into => "INTO",
             Self::Is => "IS",
             Self::Join => "JOIN",
+            Self::Json => "JSON",
             Self::Key => "KEY",
             Self::Left => "LEFT",
             Self::Like => "LIKE",
             Self::Limit => "LIMIT",
             Self::NaN => "NAN",
+            Self::No => "NO",
             Self::Not => "NOT",
             Self::Null => "NULL",
+            Self::Numeric => "NUMERIC",
             Self::Of => "OF",
             Self::Offset => "OFFSET",
             Self::On => "ON",
@@ -259,25 +392,44 @@ impl Keyword {
             Self::Outer => "OUTER",
             Self::Or => "OR",
             Self::Order => "ORDER",
+            Self::Over => "OVER",
+            Self::Partition => "PARTITION",
+            Self::Preceding => "PRECEDING",
             Self::Primary => "PRIMARY",
+            Self::Range => "RANGE",
             Self::Read => "READ",
+            Self::Recursive => "RECURSIVE",
             Self::References => "REFERENCES",
+            Self::Rename => "RENAME",
+            Self::Restrict => "RESTRICT",
             Self::Right => "RIGHT",
             Self::Rollback => "ROLLBACK",
+            Self::Row => "ROW",
+            Self::Rows => "ROWS",
             Self::Select => "SELECT",
+            Self::Sequence => "SEQUENCE",
+            Self::Serial => "SERIAL",
             Self::Set => "SET",
+            Self::Start => "START",
             Self::String => "STRING",
             Self::System => "SYSTEM",
             Self::Table => "TABLE",
             Self::Text => "TEXT",
             Self::Time => "TIME",
+            Self::Timestamp => "TIMESTAMP",
+            Self::To => "TO",
             Self::Transaction => "TRANSACTION",
             Self::True => "TRUE",
+            Self::Unbounded => "UNBOUNDED",
+            Self::Union => "UNION",
             Self::Unique => "UNIQUE",
             Self::Update => "UPDATE",
+            Self::Uuid => "UUID",
             Self::Values => "VALUES",
             Self::Varchar => "VARCHAR",
+            Self::View => "VIEW",
             Self::Where => "WHERE",
+            Self::With => "WITH",
             Self::Write => "WRITE",
         }
     }
@@ -290,7 +442,9 @@ impl std::fmt::Display for Keyword {
 }
 
 /// A lexer tokenizes an input string as an iterator
+#[derive(Clone)]
 pub struct Lexer<'a> {
+    input: &'a str,
     iter: Peekable<Chars<'a>>,
 }
 
@@ -312,7 +466,13 @@ impl<'a> Lexer<'a> {
     /// Creates a new lexer for the given input string
     #[allow(dead_code)]
     pub fn new(input: &'a str) -> Lexer<'a> {
-        Lexer { iter: input.chars().peekable() }
+        Lexer { input, iter: input.chars().peekable() }
+    }
+
+    /// Returns the input that hasn't been scanned yet
+    pub fn remaining(&self) -> &'a str {
+        let len: usize = self.iter.clone().map(char::len_utf8).sum();
+        &self.input[self.input.len() - len..]
     }
 
     /// Consumes any whitespace characters
@@ -349,6 +509,7 @@ impl<'a> Lexer<'a> {
             Some('\'') => self.scan_string(),
             Some('"') => self.scan_ident_quoted(),
             Some(c) if c.is_ascii_digit() => Ok(self.scan_number()),
+            Some('x' | 'X') => self.scan_hex_string(),
             Some(c) if c.is_alphabetic() => Ok(self.scan_ident()),
             Some(_) => Ok(self.scan_symbol()),
             None => Ok(None),
@@ -366,6 +527,26 @@ impl<'a> Lexer<'a> {
             .or_else(|| Some(Token::Ident(name.to_lowercase())))
     }
 
+    /// Scans the input for the next hex string literal X'...', or an ident or keyword starting
+    /// with X
+    fn scan_hex_string(&mut self) -> Result<Option<Token>> {
+        let mut lookahead = self.iter.clone();
+        lookahead.next();
+        if lookahead.peek() != Some(&'\'') {
+            return Ok(self.scan_ident());
+        }
+        self.iter.next();
+        match self.scan_string()? {
+            Some(Token::String(s)) => {
+                if s.len() % 2 != 0 || !s.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(Error::Parse(format!("Invalid hex string {}", s)));
+                }
+                Ok(Some(Token::HexString(s.to_lowercase())))
+            }
+            token => Ok(token),
+        }
+    }
+
     /// Scans the input for the next quoted ident, if any
     fn scan_ident_quoted(&mut self) -> Result<Option<Token>> {
         if self.next_if(|c| c == '"').is_none() {
@@ -437,6 +618,7 @@ impl<'a> Lexer<'a> {
             '%' => Some(Token::Percent),
             '!' => Some(Token::Exclamation),
             '?' => Some(Token::Question),
+            '~' => Some(Token::Tilde),
             '(' => Some(Token::OpenParen),
             ')' => Some(Token::CloseParen),
             ',' => Some(Token::Comma),
@@ -447,6 +629,8 @@ impl<'a> Lexer<'a> {
             Token::Exclamation => {
                 if self.next_if(|c| c == '=').is_some() {
                     Token::NotEqual
+                } else if self.next_if(|c| c == '~').is_some() {
+                    Token::NotTilde
                 } else {
                     token
                 }
@@ -467,6 +651,17 @@ impl<'a> Lexer<'a> {
                     token
                 }
             }
+            Token::Minus => {
+                if self.next_if(|c| c == '>').is_some() {
+                    if self.next_if(|c| c == '>').is_some() {
+                        Token::DoubleArrow
+                    } else {
+                        Token::Arrow
+                    }
+                } else {
+                    token
+                }
+            }
             _ => token,
         })
     }
@@ -2,22 +2,24 @@ pub mod ast;
 mod lexer;
 pub use lexer::{Keyword, Lexer, Token};
 
+use super::schema::table::ReferenceAction;
 use super::types::DataType;
 use crate::error::{Error, Result};
 
-use lazy_static::lazy_static;
-use regex::Regex;
 use std::collections::BTreeMap;
 
 /// An SQL parser
 pub struct Parser<'a> {
+    query: &'a str,
     lexer: std::iter::Peekable<Lexer<'a>>,
+    /// The number of tokens consumed so far
+    consumed: usize,
 }
 
 impl<'a> Parser<'a> {
     /// Creates a new parser for the given string input
-    pub fn new(query: &str) -> Parser {
-        Parser { lexer: Lexer::new(query).peekable() }
+    pub fn new(query: &str) -> Parser<'_> {
+        Parser { query, lexer: Lexer::new(query).peekable(), consumed: 0 }
     }
 
     /// Parses the input string into an AST statement
@@ -30,7 +32,22 @@ impl<'a> Parser<'a> {
 
     /// Grabs the next lexer token, or throws an error if none is found.
     fn next(&mut self) -> Result<Token> {
-        self.lexer.next().unwrap_or_else(|| Err(Error::Parse("Unexpected end of input".into())))
+        let token = self
+            .lexer
+            .next()
+            .unwrap_or_else(|| Err(Error::Parse("Unexpected end of input".into())))?;
+        self.consumed += 1;
+        Ok(token)
+    }
+
+    /// Returns the input following the tokens consumed so far, by rescanning the input. The
+    /// peekable lexer may have scanned further ahead.
+    fn remaining(&self) -> &'a str {
+        let mut lexer = Lexer::new(self.query);
+        for _ in 0..self.consumed {
+            lexer.next();
+        }
+        lexer.remaining()
     }
 
     /// Grabs the next lexer token, and returns it if it was expected or
@@ -96,6 +113,11 @@ impl<'a> Parser<'a> {
         self.lexer.peek().cloned().transpose()
     }
 
+    /// Peeks the lexer token following the next one
+    fn peek_second(&self) -> Result<Option<Token>> {
+        self.lexer.clone().nth(1).transpose()
+    }
+
     /// Parses an SQL statement
     fn parse_statement(&mut self) -> Result<ast::Statement> {
         match self.peek()? {
@@ -103,6 +125,7 @@ impl<'a> Parser<'a> {
             Some(Token::Keyword(Keyword::Commit)) => self.parse_transaction(),
             Some(Token::Keyword(Keyword::Rollback)) => self.parse_transaction(),
 
+            Some(Token::Keyword(Keyword::Alter)) => self.parse_ddl(),
             Some(Token::Keyword(Keyword::Create)) => self.parse_ddl(),
             Some(Token::Keyword(Keyword::Drop)) => self.parse_ddl(),
 
@@ -110,6 +133,8 @@ impl<'a> Parser<'a> {
             Some(Token::Keyword(Keyword::Insert)) => self.parse_statement_insert(),
             Some(Token::Keyword(Keyword::Select)) => self.parse_statement_select(),
             Some(Token::Keyword(Keyword::Update)) => self.parse_statement_update(),
+            Some(Token::Keyword(Keyword::With)) => self.parse_statement_select(),
+            Some(Token::OpenParen) => self.parse_statement_select(),
 
             Some(Token::Keyword(Keyword::Explain)) => self.parse_statement_explain(),
 
@@ -121,18 +146,54 @@ impl<'a> Parser<'a> {
     /// Parses a DDL statement
     fn parse_ddl(&mut self) -> Result<ast::Statement> {
         match self.next()? {
+            Token::Keyword(Keyword::Alter) => match self.next()? {
+                Token::Keyword(Keyword::Table) => self.parse_ddl_alter_table(),
+                token => Err(Error::Parse(format!("Unexpected token {}", token))),
+            },
             Token::Keyword(Keyword::Create) => match self.next()? {
+                Token::Keyword(Keyword::Sequence) => self.parse_ddl_create_sequence(),
                 Token::Keyword(Keyword::Table) => self.parse_ddl_create_table(),
+                Token::Keyword(Keyword::View) => self.parse_ddl_create_view(),
                 token => Err(Error::Parse(format!("Unexpected token {}", token))),
             },
             Token::Keyword(Keyword::Drop) => match self.next()? {
+                Token::Keyword(Keyword::Sequence) => {
+                    Ok(ast::Statement::DropSequence(self.next_ident()?))
+                }
                 Token::Keyword(Keyword::Table) => self.parse_ddl_drop_table(),
+                Token::Keyword(Keyword::View) => Ok(ast::Statement::DropView(self.next_ident()?)),
                 token => Err(Error::Parse(format!("Unexpected token {}", token))),
             },
             token => Err(Error::Parse(format!("Unexpected token {}", token))),
         }
     }
 
+    /// Parses an ALTER TABLE DDL statement. The ALTER TABLE prefix has
+    /// already been consumed.
+    fn parse_ddl_alter_table(&mut self) -> Result<ast::Statement> {
+        let table = self.next_ident()?;
+        match self.next()? {
+            Token::Keyword(Keyword::Add) => {
+                self.next_if_token(Keyword::Column.into());
+                Ok(ast::Statement::AddColumn { table, column: self.parse_ddl_columnspec()? })
+            }
+            Token::Keyword(Keyword::Drop) => {
+                self.next_if_token(Keyword::Column.into());
+                Ok(ast::Statement::DropColumn { table, column: self.next_ident()? })
+            }
+            Token::Keyword(Keyword::Rename) => {
+                if self.next_if_token(Keyword::To.into()).is_some() {
+                    return Ok(ast::Statement::RenameTable { table, name: self.next_ident()? });
+                }
+                self.next_if_token(Keyword::Column.into());
+                let column = self.next_ident()?;
+                self.next_expect(Some(Keyword::To.into()))?;
+                Ok(ast::Statement::RenameColumn { table, column, name: self.next_ident()? })
+            }
+            token => Err(Error::Parse(format!("Unexpected token {}", token))),
+        }
+    }
+
     /// Parses a CREATE TABLE DDL statement. The CREATE TABLE prefix has
     /// already been consumed.
     fn parse_ddl_create_table(&mut self) -> Result<ast::Statement> {
@@ -140,14 +201,79 @@ impl<'a> Parser<'a> {
         self.next_expect(Some(Token::OpenParen))?;
 
         let mut columns = Vec::new();
+        let mut primary_key = Vec::new();
+        let mut checks = Vec::new();
         loop {
-            columns.push(self.parse_ddl_columnspec()?);
+            if self.next_if_token(Keyword::Primary.into()).is_some() {
+                // A PRIMARY KEY (a, b, ...) table constraint
+                self.next_expect(Some(Keyword::Key.into()))?;
+                self.next_expect(Some(Token::OpenParen))?;
+                loop {
+                    primary_key.push(self.next_ident()?);
+                    if self.next_if_token(Token::Comma).is_none() {
+                        break;
+                    }
+                }
+                self.next_expect(Some(Token::CloseParen))?;
+            } else if self.next_if_token(Keyword::Check.into()).is_some() {
+                checks.push(self.parse_ddl_check()?);
+            } else {
+                columns.push(self.parse_ddl_columnspec()?);
+            }
             if self.next_if_token(Token::Comma).is_none() {
                 break;
             }
         }
         self.next_expect(Some(Token::CloseParen))?;
-        Ok(ast::Statement::CreateTable { name, columns })
+        Ok(ast::Statement::CreateTable { name, columns, primary_key, checks })
+    }
+
+    /// Parses a CREATE SEQUENCE DDL statement. The CREATE SEQUENCE prefix has
+    /// already been consumed.
+    fn parse_ddl_create_sequence(&mut self) -> Result<ast::Statement> {
+        let name = self.next_ident()?;
+        let (mut start, mut increment) = (None, None);
+        loop {
+            let (option, keyword) = if self.next_if_token(Keyword::Start.into()).is_some() {
+                (&mut start, Keyword::With)
+            } else if self.next_if_token(Keyword::Increment.into()).is_some() {
+                (&mut increment, Keyword::By)
+            } else {
+                break;
+            };
+            if option.is_some() {
+                return Err(Error::Parse(format!(
+                    "Sequence option given multiple times for {}",
+                    name
+                )));
+            }
+            self.next_if_token(keyword.into());
+            let negate = self.next_if_token(Token::Minus).is_some();
+            let value = match self.next()? {
+                Token::Number(n) => n.parse::<i64>()?,
+                token => {
+                    return Err(Error::Parse(format!("Unexpected token {}, wanted number", token)))
+                }
+            };
+            *option = Some(if negate { -value } else { value });
+        }
+        Ok(ast::Statement::CreateSequence { name, start, increment })
+    }
+
+    /// Parses a CREATE VIEW DDL statement. The CREATE VIEW prefix has
+    /// already been consumed. The query is kept as SQL text, which is
+    /// stored in the catalog.
+    fn parse_ddl_create_view(&mut self) -> Result<ast::Statement> {
+        let name = self.next_ident()?;
+        self.next_expect(Some(Keyword::As.into()))?;
+        let start = self.remaining();
+        match self.peek()? {
+            Some(Token::Keyword(Keyword::Select | Keyword::With)) => self.parse_statement_select()?,
+            Some(token) => return Err(Error::Parse(format!("Unexpected token {}", token))),
+            None => return Err(Error::Parse("Unexpected end of input".into())),
+        };
+        let query = start[..start.len() - self.remaining().len()].trim().to_string();
+        Ok(ast::Statement::CreateView { name, query })
     }
 
     /// Parses a DROP TABLE DDL statement. The DROP TABLE prefix has
@@ -156,29 +282,62 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::DropTable(self.next_ident()?))
     }
 
+    /// Parses a column data type
+    fn parse_datatype(&mut self) -> Result<DataType> {
+        Ok(match self.next()? {
+            Token::Keyword(Keyword::Blob) => DataType::Blob,
+            Token::Keyword(Keyword::Bool) => DataType::Boolean,
+            Token::Keyword(Keyword::Boolean) => DataType::Boolean,
+            Token::Keyword(Keyword::Bytea) => DataType::Blob,
+            Token::Keyword(Keyword::Char) => DataType::String,
+            Token::Keyword(Keyword::Date) => DataType::Date,
+            Token::Keyword(Keyword::Decimal) => DataType::Decimal,
+            Token::Keyword(Keyword::Double) => DataType::Float,
+            Token::Keyword(Keyword::Float) => DataType::Float,
+            Token::Keyword(Keyword::Int) => DataType::Integer,
+            Token::Keyword(Keyword::Integer) => DataType::Integer,
+            Token::Keyword(Keyword::Json) => DataType::Json,
+            Token::Keyword(Keyword::Numeric) => DataType::Decimal,
+            Token::Keyword(Keyword::String) => DataType::String,
+            Token::Keyword(Keyword::Text) => DataType::String,
+            Token::Keyword(Keyword::Timestamp) => DataType::Timestamp,
+            Token::Keyword(Keyword::Uuid) => DataType::Uuid,
+            Token::Keyword(Keyword::Varchar) => DataType::String,
+            token => return Err(Error::Parse(format!("Unexpected token {}", token))),
+        })
+    }
+
     /// Parses a column specification
     fn parse_ddl_columnspec(&mut self) -> Result<ast::Column> {
+        let name = self.next_ident()?;
+        // SERIAL is shorthand for an auto-increment integer
+        let auto_increment = self.next_if_token(Keyword::Serial.into()).is_some();
+        let datatype = if auto_increment { DataType::Integer } else { self.parse_datatype()? };
+        // String types take an optional length limit, e.g. VARCHAR(255)
+        let mut max_length = None;
+        if datatype == DataType::String && self.next_if_token(Token::OpenParen).is_some() {
+            max_length = Some(match self.next()? {
+                Token::Number(n) => n.parse::<usize>()?,
+                token => {
+                    return Err(Error::Parse(format!("Unexpected token {}, wanted number", token)))
+                }
+            });
+            self.next_expect(Some(Token::CloseParen))?;
+        }
         let mut column = ast::Column {
-            name: self.next_ident()?,
-            datatype: match self.next()? {
-                Token::Keyword(Keyword::Bool) => DataType::Boolean,
-                Token::Keyword(Keyword::Boolean) => DataType::Boolean,
-                Token::Keyword(Keyword::Char) => DataType::String,
-                Token::Keyword(Keyword::Double) => DataType::Float,
-                Token::Keyword(Keyword::Float) => DataType::Float,
-                Token::Keyword(Keyword::Int) => DataType::Integer,
-                Token::Keyword(Keyword::Integer) => DataType::Integer,
-                Token::Keyword(Keyword::String) => DataType::String,
-                Token::Keyword(Keyword::Text) => DataType::String,
-                Token::Keyword(Keyword::Varchar) => DataType::String,
-                token => return Err(Error::Parse(format!("Unexpected token {}", token))),
-            },
+            name,
+            datatype,
+            max_length,
             primary_key: false,
             nullable: None,
             default: None,
             unique: false,
             index: false,
             references: None,
+            on_delete: None,
+            on_update: None,
+            auto_increment,
+            checks: Vec::new(),
         };
         while let Some(Token::Keyword(keyword)) = self.next_if_keyword() {
             match keyword {
@@ -207,14 +366,61 @@ impl<'a> Parser<'a> {
                 }
                 Keyword::Default => column.default = Some(self.parse_expression(0)?),
                 Keyword::Unique => column.unique = true,
+                Keyword::AutoIncrement => column.auto_increment = true,
                 Keyword::Index => column.index = true,
-                Keyword::References => column.references = Some(self.next_ident()?),
+                Keyword::References => {
+                    column.references = Some(self.next_ident()?);
+                    while self.next_if_token(Keyword::On.into()).is_some() {
+                        let action = match self.next()? {
+                            Token::Keyword(Keyword::Delete) => &mut column.on_delete,
+                            Token::Keyword(Keyword::Update) => &mut column.on_update,
+                            token => {
+                                return Err(Error::Parse(format!("Unexpected token {}", token)))
+                            }
+                        };
+                        if action.is_some() {
+                            return Err(Error::Parse(format!(
+                                "Multiple referential actions for column {}",
+                                column.name
+                            )));
+                        }
+                        *action = Some(self.parse_ddl_reference_action()?);
+                    }
+                }
+                Keyword::Check => column.checks.push(self.parse_ddl_check()?),
                 keyword => return Err(Error::Parse(format!("Unexpected keyword {}", keyword))),
             }
         }
         Ok(column)
     }
 
+    /// Parses a referential action for ON DELETE or ON UPDATE
+    fn parse_ddl_reference_action(&mut self) -> Result<ReferenceAction> {
+        Ok(match self.next()? {
+            Token::Keyword(Keyword::Cascade) => ReferenceAction::Cascade,
+            Token::Keyword(Keyword::Restrict) => ReferenceAction::Restrict,
+            Token::Keyword(Keyword::No) => {
+                self.next_expect(Some(Keyword::Action.into()))?;
+                ReferenceAction::Restrict
+            }
+            Token::Keyword(Keyword::Set) => match self.next()? {
+                Token::Keyword(Keyword::Null) => ReferenceAction::SetNull,
+                Token::Keyword(Keyword::Default) => ReferenceAction::SetDefault,
+                token => return Err(Error::Parse(format!("Unexpected token {}", token))),
+            },
+            token => return Err(Error::Parse(format!("Unexpected token {}", token))),
+        })
+    }
+
+    /// Parses a parenthesized CHECK constraint expression. The CHECK keyword has already been
+    /// consumed.
+    fn parse_ddl_check(&mut self) -> Result<ast::Expression> {
+        self.next_expect(Some(Token::OpenParen))?;
+        let expr = self.parse_expression(0)?;
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(expr)
+    }
+
     /// Parses a delete statement
     fn parse_statement_delete(&mut self) -> Result<ast::Statement> {
         self.next_expect(Some(Keyword::Delete.into()))?;
@@ -223,13 +429,14 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::Delete { table, r#where: self.parse_clause_where()? })
     }
 
-    /// Parses a delete statement
+    /// Parses an explain statement
     fn parse_statement_explain(&mut self) -> Result<ast::Statement> {
         self.next_expect(Some(Keyword::Explain.into()))?;
+        let analyze = self.next_if_token(Keyword::Analyze.into()).is_some();
         if let Some(Token::Keyword(Keyword::Explain)) = self.peek()? {
             return Err(Error::Parse("Cannot nest EXPLAIN statements".into()));
         }
-        Ok(ast::Statement::Explain(Box::new(self.parse_statement()?)))
+        Ok(ast::Statement::Explain { statement: Box::new(self.parse_statement()?), analyze })
     }
 
     /// Parses an insert statement
@@ -275,26 +482,98 @@ impl<'a> Parser<'a> {
         Ok(ast::Statement::Insert { table, columns, values })
     }
 
-    /// Parses a select statement
+    /// Parses a select statement, which may be a compound query combining several SELECT
+    /// clauses with set operators. The WITH, ORDER BY, LIMIT and OFFSET clauses apply to the
+    /// entire statement.
     fn parse_statement_select(&mut self) -> Result<ast::Statement> {
-        Ok(ast::Statement::Select {
-            select: self.parse_clause_select()?,
-            from: self.parse_clause_from()?,
-            r#where: self.parse_clause_where()?,
-            group_by: self.parse_clause_group_by()?,
-            having: self.parse_clause_having()?,
-            order: self.parse_clause_order()?,
-            limit: if self.next_if_token(Keyword::Limit.into()).is_some() {
-                Some(self.parse_expression(0)?)
-            } else {
-                None
-            },
-            offset: if self.next_if_token(Keyword::Offset.into()).is_some() {
-                Some(self.parse_expression(0)?)
-            } else {
-                None
-            },
-        })
+        let ctes = self.parse_clause_with()?;
+        let mut statement = self.parse_set_operation(0)?;
+        let orders = self.parse_clause_order()?;
+        let limits = if self.next_if_token(Keyword::Limit.into()).is_some() {
+            Some(self.parse_expression(0)?)
+        } else {
+            None
+        };
+        let offsets = if self.next_if_token(Keyword::Offset.into()).is_some() {
+            Some(self.parse_expression(0)?)
+        } else {
+            None
+        };
+        match &mut statement {
+            // A parenthesized query may have its own clauses, but these can't be repeated.
+            ast::Statement::Select { with, order, offset, limit, .. }
+            | ast::Statement::SetOperation { with, order, offset, limit, .. } => {
+                *with = ctes;
+                if !orders.is_empty() || offsets.is_some() || limits.is_some() {
+                    if !order.is_empty() || offset.is_some() || limit.is_some() {
+                        return Err(Error::Parse(
+                            "Multiple ORDER BY, LIMIT or OFFSET clauses".into(),
+                        ));
+                    }
+                    (*order, *offset, *limit) = (orders, offsets, limits);
+                }
+            }
+            statement => {
+                return Err(Error::Internal(format!("Unexpected statement {:?}", statement)))
+            }
+        }
+        Ok(statement)
+    }
+
+    /// Parses a compound query using precedence climbing, where INTERSECT binds tighter than
+    /// UNION and EXCEPT, which are left-associative. Operands can be parenthesized queries, e.g.
+    /// to group them or give them their own ORDER BY and LIMIT clauses.
+    fn parse_set_operation(&mut self, min_precedence: u8) -> Result<ast::Statement> {
+        let mut lhs = if self.next_if_token(Token::OpenParen).is_some() {
+            self.parse_subquery()?
+        } else {
+            let (distinct, select) = self.parse_clause_select()?;
+            ast::Statement::Select {
+                with: Vec::new(),
+                distinct,
+                select,
+                from: self.parse_clause_from()?,
+                r#where: self.parse_clause_where()?,
+                group_by: self.parse_clause_group_by()?,
+                having: self.parse_clause_having()?,
+                order: Vec::new(),
+                offset: None,
+                limit: None,
+            }
+        };
+        loop {
+            let (operator, precedence) = match self.peek()? {
+                Some(Token::Keyword(Keyword::Union)) => (ast::SetOperator::Union, 1),
+                Some(Token::Keyword(Keyword::Except)) => (ast::SetOperator::Except, 1),
+                Some(Token::Keyword(Keyword::Intersect)) => (ast::SetOperator::Intersect, 2),
+                _ => break,
+            };
+            if precedence < min_precedence {
+                break;
+            }
+            self.next()?;
+            let all = self.next_if_token(Keyword::All.into()).is_some();
+            if !all {
+                self.next_if_token(Keyword::Distinct.into());
+            }
+            match self.peek()? {
+                Some(Token::Keyword(Keyword::Select)) | Some(Token::OpenParen) => {}
+                Some(token) => return Err(Error::Parse(format!("Unexpected token {}", token))),
+                None => return Err(Error::Parse("Unexpected end of input".into())),
+            }
+            let rhs = self.parse_set_operation(precedence + 1)?;
+            lhs = ast::Statement::SetOperation {
+                with: Vec::new(),
+                operator,
+                all,
+                left: Box::new(lhs),
+                right: Box::new(rhs),
+                order: Vec::new(),
+                offset: None,
+                limit: None,
+            };
+        }
+        Ok(lhs)
     }
 
     /// Parses an update statement
@@ -387,6 +666,14 @@ impl<'a> Parser<'a> {
 
     /// Parses a from clause item
     fn parse_clause_from_item(&mut self) -> Result<ast::FromItem> {
+        if self.next_if_token(Token::OpenParen).is_some() {
+            let query = Box::new(self.parse_subquery()?);
+            self.next_if_token(Keyword::As.into());
+            let alias = self.next_ident().map_err(|_| {
+                Error::Parse("Subqueries in the FROM clause must have an alias".into())
+            })?;
+            return Ok(ast::FromItem::Subquery { query, alias });
+        }
         self.parse_clause_from_table()
     }
 
@@ -421,6 +708,10 @@ impl<'a> Parser<'a> {
             self.next_if_token(Keyword::Outer.into());
             self.next_expect(Some(Keyword::Join.into()))?;
             Ok(Some(ast::JoinType::Right))
+        } else if self.next_if_token(Keyword::Full.into()).is_some() {
+            self.next_if_token(Keyword::Outer.into());
+            self.next_expect(Some(Keyword::Join.into()))?;
+            Ok(Some(ast::JoinType::Full))
         } else {
             Ok(None)
         }
@@ -475,12 +766,82 @@ impl<'a> Parser<'a> {
         Ok(orders)
     }
 
-    /// Parses a select clause
-    fn parse_clause_select(&mut self) -> Result<Vec<(ast::Expression, Option<String>)>> {
+    /// Parses a WITH clause, if any, which must be followed by a SELECT clause
+    fn parse_clause_with(&mut self) -> Result<Vec<ast::CommonTableExpression>> {
+        let mut ctes = Vec::new();
+        if self.next_if_token(Keyword::With.into()).is_none() {
+            return Ok(ctes);
+        }
+        let recursive_with = self.next_if_token(Keyword::Recursive.into()).is_some();
+        loop {
+            let name = self.next_ident()?;
+            let mut columns = Vec::new();
+            if self.next_if_token(Token::OpenParen).is_some() {
+                loop {
+                    columns.push(self.next_ident()?);
+                    if self.next_if_token(Token::Comma).is_none() {
+                        break;
+                    }
+                }
+                self.next_expect(Some(Token::CloseParen))?;
+            }
+            self.next_expect(Some(Keyword::As.into()))?;
+            self.next_expect(Some(Token::OpenParen))?;
+            // The recursive term of WITH RECURSIVE is the last query of a top-level UNION.
+            let (query, recursive) = match self.parse_statement_select()? {
+                ast::Statement::SetOperation {
+                    with,
+                    operator: ast::SetOperator::Union,
+                    all,
+                    left,
+                    right,
+                    order,
+                    offset: None,
+                    limit: None,
+                } if recursive_with && with.is_empty() && order.is_empty() => {
+                    (left, Some((right, all)))
+                }
+                query => (Box::new(query), None),
+            };
+            self.next_expect(Some(Token::CloseParen))?;
+            ctes.push(ast::CommonTableExpression { name, columns, query, recursive });
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        match self.peek()? {
+            Some(Token::Keyword(Keyword::Select)) => Ok(ctes),
+            Some(token) => Err(Error::Parse(format!("Unexpected token {}", token))),
+            None => Err(Error::Parse("Unexpected end of input".into())),
+        }
+    }
+
+    /// Parses a select clause, along with any DISTINCT or DISTINCT ON (exprs) qualifier
+    #[allow(clippy::type_complexity)]
+    fn parse_clause_select(
+        &mut self,
+    ) -> Result<(Option<Vec<ast::Expression>>, Vec<(ast::Expression, Option<String>)>)> {
         let mut select = Vec::new();
         if self.next_if_token(Keyword::Select.into()).is_none() {
-            return Ok(select);
+            return Ok((None, select));
         }
+        let distinct = if self.next_if_token(Keyword::Distinct.into()).is_some() {
+            let mut on = Vec::new();
+            if self.next_if_token(Keyword::On.into()).is_some() {
+                self.next_expect(Some(Token::OpenParen))?;
+                loop {
+                    on.push(self.parse_expression(0)?);
+                    if self.next_if_token(Token::Comma).is_none() {
+                        break;
+                    }
+                }
+                self.next_expect(Some(Token::CloseParen))?;
+            }
+            Some(on)
+        } else {
+            self.next_if_token(Keyword::All.into());
+            None
+        };
         loop {
             if self.next_if_token(Token::Asterisk).is_some() && select.is_empty() {
                 break;
@@ -499,7 +860,7 @@ impl<'a> Parser<'a> {
                 break;
             }
         }
-        Ok(select)
+        Ok((distinct, select))
     }
 
     /// Parses a WHERE clause
@@ -518,20 +879,149 @@ impl<'a> Parser<'a> {
         } else {
             self.parse_expression_atom()?
         };
-        while let Some(postfix) = self.next_if_operator::<PostfixOperator>(min_prec)? {
-            lhs = postfix.build(lhs)
+        loop {
+            if let Some(postfix) = self.next_if_operator::<PostfixOperator>(min_prec)? {
+                lhs = postfix.build(lhs)
+            } else if let Some(postfix) = self.next_if_not_in(min_prec)? {
+                lhs = postfix.build(lhs)
+            } else {
+                break;
+            }
         }
-        while let Some(infix) = self.next_if_operator::<InfixOperator>(min_prec)? {
-            lhs = infix.build(lhs, self.parse_expression(infix.prec() + infix.assoc())?)
+        loop {
+            if let Some(infix) = self.next_if_operator::<InfixOperator>(min_prec)? {
+                lhs = infix.build(lhs, self.parse_expression(infix.prec() + infix.assoc())?)
+            } else if let Some(infix) = self.next_if_not_like(min_prec)? {
+                lhs = infix.build(lhs, self.parse_expression(infix.prec() + infix.assoc())?)
+            } else {
+                break;
+            }
         }
         Ok(lhs)
     }
 
-    /// Parses an expression atom
+    /// Grabs a NOT LIKE or NOT ILIKE infix operator, if any. Like NOT IN, these consist of two
+    /// tokens, since NOT by itself is only a prefix operator.
+    fn next_if_not_like(&mut self, min_prec: u8) -> Result<Option<InfixOperator>> {
+        let operator = match self.peek_second()? {
+            Some(Token::Keyword(Keyword::Like)) => InfixOperator::NotLike,
+            Some(Token::Keyword(Keyword::ILike)) => InfixOperator::NotILike,
+            _ => return Ok(None),
+        };
+        if operator.prec() < min_prec || self.peek()? != Some(Keyword::Not.into()) {
+            return Ok(None);
+        }
+        self.next()?;
+        self.next()?;
+        Ok(Some(operator))
+    }
+
+    /// Grabs a NOT IN postfix operator, if any. NOT can't be a postfix operator by itself, since
+    /// it also follows expressions in e.g. DEFAULT 1 NOT NULL column constraints.
+    fn next_if_not_in(&mut self, min_prec: u8) -> Result<Option<PostfixOperator>> {
+        let operator = PostfixOperator::In { not: true, list: Vec::new() };
+        if operator.prec() < min_prec
+            || self.peek()? != Some(Keyword::Not.into())
+            || self.peek_second()? != Some(Keyword::In.into())
+        {
+            return Ok(None);
+        }
+        self.next()?;
+        self.next()?;
+        Ok(Some(operator.augment(self)?))
+    }
+
+    /// Parses a parenthesized SELECT query, whose opening parenthesis has already been consumed.
+    fn parse_subquery(&mut self) -> Result<ast::Statement> {
+        match self.peek()? {
+            Some(Token::Keyword(Keyword::Select)) | Some(Token::OpenParen) => {}
+            Some(token) => return Err(Error::Parse(format!("Unexpected token {}", token))),
+            None => return Err(Error::Parse("Unexpected end of input".into())),
+        }
+        let query = self.parse_statement_select()?;
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(query)
+    }
+
+    /// Parses the window of a window function call, following OVER: a parenthesized, optional
+    /// PARTITION BY clause, ORDER BY clause and frame.
+    fn parse_window(&mut self) -> Result<ast::Window> {
+        self.next_expect(Some(Token::OpenParen))?;
+        let mut partition = Vec::new();
+        if self.next_if_token(Keyword::Partition.into()).is_some() {
+            self.next_expect(Some(Keyword::By.into()))?;
+            loop {
+                partition.push(self.parse_expression(0)?);
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+        }
+        let order = self.parse_clause_order()?;
+        let units = match self.peek()? {
+            Some(Token::Keyword(Keyword::Rows)) => Some(ast::FrameUnits::Rows),
+            Some(Token::Keyword(Keyword::Range)) => Some(ast::FrameUnits::Range),
+            _ => None,
+        };
+        let frame = match units {
+            Some(units) => {
+                self.next()?;
+                // A frame with only a start bound ends at the current row.
+                if self.next_if_token(Keyword::Between.into()).is_some() {
+                    let start = self.parse_frame_bound()?;
+                    self.next_expect(Some(Keyword::And.into()))?;
+                    Some(ast::Frame { units, start, end: self.parse_frame_bound()? })
+                } else {
+                    let start = self.parse_frame_bound()?;
+                    Some(ast::Frame { units, start, end: ast::FrameBound::CurrentRow })
+                }
+            }
+            None => None,
+        };
+        self.next_expect(Some(Token::CloseParen))?;
+        Ok(ast::Window { partition, order, frame })
+    }
+
+    /// Parses a window frame bound
+    fn parse_frame_bound(&mut self) -> Result<ast::FrameBound> {
+        if self.next_if_token(Keyword::Unbounded.into()).is_some() {
+            return match self.next()? {
+                Token::Keyword(Keyword::Preceding) => Ok(ast::FrameBound::UnboundedPreceding),
+                Token::Keyword(Keyword::Following) => Ok(ast::FrameBound::UnboundedFollowing),
+                token => Err(Error::Parse(format!("Unexpected token {}", token))),
+            };
+        }
+        if self.next_if_token(Keyword::Current.into()).is_some() {
+            self.next_expect(Some(Keyword::Row.into()))?;
+            return Ok(ast::FrameBound::CurrentRow);
+        }
+        let offset = self.parse_expression(0)?;
+        match self.next()? {
+            Token::Keyword(Keyword::Preceding) => Ok(ast::FrameBound::Preceding(offset)),
+            Token::Keyword(Keyword::Following) => Ok(ast::FrameBound::Following(offset)),
+            token => Err(Error::Parse(format!("Unexpected token {}", token))),
+        }
+    }
+
+    /// Parses a typed literal such as DATE '2024-01-01', i.e. a string cast to the given type.
+    /// The type keyword has already been consumed.
+    fn parse_typed_literal(&mut self, datatype: DataType) -> Result<ast::Expression> {
+        match self.next()? {
+            Token::String(s) => {
+                Ok(ast::Expression::Cast(Box::new(ast::Literal::String(s).into()), datatype))
+            }
+            token => Err(Error::Parse(format!("Unexpected token {}, wanted string", token))),
+        }
+    }
+
     fn parse_expression_atom(&mut self) -> Result<ast::Expression> {
         Ok(match self.next()? {
-            Token::Ident(i) => {
+            Token::Ident(mut i) => {
                 if self.next_if_token(Token::OpenParen).is_some() {
+                    // COUNT(DISTINCT x) is represented as the function count_distinct(x)
+                    if i == "count" && self.next_if_token(Keyword::Distinct.into()).is_some() {
+                        i = "count_distinct".into();
+                    }
                     let mut args = Vec::new();
                     while self.next_if_token(Token::CloseParen).is_none() {
                         if !args.is_empty() {
@@ -544,7 +1034,11 @@ impl<'a> Parser<'a> {
                             args.push(self.parse_expression(0)?);
                         }
                     }
-                    ast::Expression::Function(i, args)
+                    if self.next_if_token(Keyword::Over.into()).is_some() {
+                        ast::Expression::WindowFunction(i, args, Box::new(self.parse_window()?))
+                    } else {
+                        ast::Expression::Function(i, args)
+                    }
                 } else {
                     let mut relation = None;
                     let mut field = i;
@@ -562,15 +1056,41 @@ impl<'a> Parser<'a> {
                     ast::Literal::Float(n.parse()?).into()
                 }
             }
+            Token::OpenParen if self.peek()? == Some(Keyword::Select.into()) => {
+                ast::Expression::Subquery(Box::new(self.parse_subquery()?))
+            }
             Token::OpenParen => {
                 let expr = self.parse_expression(0)?;
                 self.next_expect(Some(Token::CloseParen))?;
                 expr
             }
             Token::String(s) => ast::Literal::String(s).into(),
+            Token::HexString(h) => ast::Literal::Blob(
+                hex::decode(&h).map_err(|_| Error::Parse(format!("Invalid hex string {}", h)))?,
+            )
+            .into(),
+            Token::Keyword(Keyword::Date) => self.parse_typed_literal(DataType::Date)?,
+            Token::Keyword(Keyword::Decimal | Keyword::Numeric) => {
+                self.parse_typed_literal(DataType::Decimal)?
+            }
+            Token::Keyword(Keyword::Timestamp) => self.parse_typed_literal(DataType::Timestamp)?,
+            Token::Keyword(Keyword::Json) => self.parse_typed_literal(DataType::Json)?,
+            Token::Keyword(Keyword::Uuid) => self.parse_typed_literal(DataType::Uuid)?,
+            Token::Keyword(Keyword::Cast) => {
+                self.next_expect(Some(Token::OpenParen))?;
+                let expr = self.parse_expression(0)?;
+                self.next_expect(Some(Keyword::As.into()))?;
+                let datatype = self.parse_datatype()?;
+                self.next_expect(Some(Token::CloseParen))?;
+                ast::Expression::Cast(Box::new(expr), datatype)
+            }
+            Token::Keyword(Keyword::Exists) => {
+                self.next_expect(Some(Token::OpenParen))?;
+                ast::Expression::Exists(Box::new(self.parse_subquery()?))
+            }
             Token::Keyword(Keyword::False) => ast::Literal::Boolean(false).into(),
-            Token::Keyword(Keyword::Infinity) => ast::Literal::Float(std::f64::INFINITY).into(),
-            Token::Keyword(Keyword::NaN) => ast::Literal::Float(std::f64::NAN).into(),
+            Token::Keyword(Keyword::Infinity) => ast::Literal::Float(f64::INFINITY).into(),
+            Token::Keyword(Keyword::NaN) => ast::Literal::Float(f64::NAN).into(),
             Token::Keyword(Keyword::Null) => ast::Literal::Null.into(),
             Token::Keyword(Keyword::True) => ast::Literal::Boolean(true).into(),
             t => return Err(Error::Parse(format!("Expected expression atom, found {}", t))),
@@ -641,13 +1161,20 @@ enum InfixOperator {
     Exponentiate,
     GreaterThan,
     GreaterThanOrEqual,
+    ILike,
+    JsonGet,
+    JsonGetText,
     LessThan,
     LessThanOrEqual,
     Like,
     Modulo,
     Multiply,
     NotEqual,
+    NotILike,
+    NotLike,
+    NotRegex,
     Or,
+    Regex,
     Subtract,
 }
 
@@ -662,13 +1189,20 @@ impl InfixOperator {
             Self::Exponentiate => ast::Operation::Exponentiate(lhs, rhs),
             Self::GreaterThan => ast::Operation::GreaterThan(lhs, rhs),
             Self::GreaterThanOrEqual => ast::Operation::GreaterThanOrEqual(lhs, rhs),
+            Self::ILike => ast::Operation::ILike(lhs, rhs),
+            Self::JsonGet => ast::Operation::JsonGet(lhs, rhs),
+            Self::JsonGetText => ast::Operation::JsonGetText(lhs, rhs),
             Self::LessThan => ast::Operation::LessThan(lhs, rhs),
             Self::LessThanOrEqual => ast::Operation::LessThanOrEqual(lhs, rhs),
             Self::Like => ast::Operation::Like(lhs, rhs),
             Self::Modulo => ast::Operation::Modulo(lhs, rhs),
             Self::Multiply => ast::Operation::Multiply(lhs, rhs),
             Self::NotEqual => ast::Operation::NotEqual(lhs, rhs),
+            Self::NotILike => ast::Operation::Not(Box::new(ast::Operation::ILike(lhs, rhs).into())),
+            Self::NotLike => ast::Operation::Not(Box::new(ast::Operation::Like(lhs, rhs).into())),
+            Self::NotRegex => ast::Operation::Not(Box::new(ast::Operation::Regex(lhs, rhs).into())),
             Self::Or => ast::Operation::Or(lhs, rhs),
+            Self::Regex => ast::Operation::Regex(lhs, rhs),
             Self::Subtract => ast::Operation::Subtract(lhs, rhs),
         }
         .into()
@@ -678,12 +1212,15 @@ impl InfixOperator {
 impl Operator for InfixOperator {
     fn from(token: &Token) -> Option<Self> {
         Some(match token {
+            Token::Arrow => Self::JsonGet,
             Token::Asterisk => Self::Multiply,
             Token::Caret => Self::Exponentiate,
+            Token::DoubleArrow => Self::JsonGetText,
             Token::Equal => Self::Equal,
             Token::GreaterThan => Self::GreaterThan,
             Token::GreaterThanOrEqual => Self::GreaterThanOrEqual,
             Token::Keyword(Keyword::And) => Self::And,
+            Token::Keyword(Keyword::ILike) => Self::ILike,
             Token::Keyword(Keyword::Like) => Self::Like,
             Token::Keyword(Keyword::Or) => Self::Or,
             Token::LessOrGreaterThan => Self::NotEqual,
@@ -691,9 +1228,11 @@ impl Operator for InfixOperator {
             Token::LessThanOrEqual => Self::LessThanOrEqual,
             Token::Minus => Self::Subtract,
             Token::NotEqual => Self::NotEqual,
+            Token::NotTilde => Self::NotRegex,
             Token::Percent => Self::Modulo,
             Token::Plus => Self::Add,
             Token::Slash => Self::Divide,
+            Token::Tilde => Self::Regex,
             _ => return None,
         })
     }
@@ -713,7 +1252,14 @@ impl Operator for InfixOperator {
         match self {
             Self::Or => 1,
             Self::And => 2,
-            Self::Equal | Self::NotEqual | Self::Like => 3,
+            Self::Equal
+            | Self::NotEqual
+            | Self::Like
+            | Self::ILike
+            | Self::Regex
+            | Self::NotLike
+            | Self::NotILike
+            | Self::NotRegex => 3,
             Self::GreaterThan
             | Self::GreaterThanOrEqual
             | Self::LessThan
@@ -721,12 +1267,22 @@ impl Operator for InfixOperator {
             Self::Add | Self::Subtract => 5,
             Self::Multiply | Self::Divide | Self::Modulo => 6,
             Self::Exponentiate => 7,
+            Self::JsonGet | Self::JsonGetText => 8,
         }
     }
 }
 
 enum PostfixOperator {
     Factorial,
+    // x IN (a, b) is represented as x = a OR x = b
+    In {
+        not: bool,
+        list: Vec<ast::Expression>,
+    },
+    InSubquery {
+        not: bool,
+        query: Box<ast::Statement>,
+    },
     // FIXME Compiler bug? Why is this considered dead code?
     #[allow(dead_code)]
     IsNull {
@@ -743,6 +1299,27 @@ impl PostfixOperator {
                 false => ast::Operation::IsNull(lhs),
             },
             Self::Factorial => ast::Operation::Factorial(lhs),
+            Self::In { not, list } => {
+                let mut exprs = list
+                    .iter()
+                    .map(|e| ast::Operation::Equal(lhs.clone(), Box::new(e.clone())).into());
+                // The parser guarantees that the list is non-empty.
+                let first = exprs.next().unwrap();
+                let expr = exprs.fold(first, |acc: ast::Expression, e| {
+                    ast::Operation::Or(Box::new(acc), Box::new(e)).into()
+                });
+                match not {
+                    true => ast::Operation::Not(Box::new(expr)),
+                    false => return expr,
+                }
+            }
+            Self::InSubquery { not, query } => {
+                let expr = ast::Expression::InSubquery(lhs, query.clone());
+                match not {
+                    true => ast::Operation::Not(Box::new(expr)),
+                    false => return expr,
+                }
+            }
         }
         .into()
     }
@@ -752,21 +1329,35 @@ impl Operator for PostfixOperator {
     fn from(token: &Token) -> Option<Self> {
         match token {
             Token::Exclamation => Some(Self::Factorial),
+            Token::Keyword(Keyword::In) => Some(Self::In { not: false, list: Vec::new() }),
             Token::Keyword(Keyword::Is) => Some(Self::IsNull { not: false }),
             _ => None,
         }
     }
 
     fn augment(mut self, parser: &mut Parser) -> Result<Self> {
-        #[allow(clippy::single_match)]
         match &mut self {
+            Self::In { not, list } => {
+                parser.next_expect(Some(Token::OpenParen))?;
+                if parser.peek()? == Some(Keyword::Select.into()) {
+                    let query = Box::new(parser.parse_subquery()?);
+                    return Ok(Self::InSubquery { not: *not, query });
+                }
+                loop {
+                    list.push(parser.parse_expression(0)?);
+                    if parser.next_if_token(Token::Comma).is_none() {
+                        break;
+                    }
+                }
+                parser.next_expect(Some(Token::CloseParen))?;
+            }
             Self::IsNull { ref mut not } => {
                 if parser.next_if_token(Keyword::Not.into()).is_some() {
                     *not = true
                 };
                 parser.next_expect(Some(Keyword::Null.into()))?;
             }
-            _ => {}
+            Self::Factorial | Self::InSubquery { .. } => {}
         };
         Ok(self)
     }
@@ -1,11 +1,15 @@
+pub mod optimizer;
 pub mod planner;
 
+use self::optimizer::Optimizer;
 use self::planner::Planner;
 
-use super::{engine::Transaction, schema::{catalog::Catalog, table::Table}, types::expression::Expression, execution::{ResultSet, Executor}, parser::ast::Statement};
+use super::{engine::Transaction, schema::{catalog::Catalog, sequence::Sequence, table::{self, Table}, view::View}, types::{expression::Expression, format_key, Column, Row, Value}, execution::{ResultSet, Executor}, parser::ast::Statement};
 use crate::error::Result;
 
 use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeSet;
 use std::fmt::{self, Display};
 
 /// SQL Plan
@@ -19,6 +23,9 @@ impl Display for Plan {
 }
 
 impl Plan {
+    /// WITH RECURSIVE 查询默认的最大迭代次数
+    pub const DEFAULT_MAX_RECURSION: u64 = 1000;
+
     /// 从 AST 构造 Planner
     pub fn build<C: Catalog>(statement: Statement, catalog: &mut C) -> Result<Self> {
         Planner::new(catalog).build(statement)
@@ -29,50 +36,462 @@ impl Plan {
         <dyn Executor<T>>::build(self.0).execute(txn)
     }
 
-    /// 优化，例如谓词下推等等，目前暂时不优化
-    pub fn optimize<C: Catalog>(self, _catalog: &mut C) -> Result<Self> {
-        // let mut root = self.0;
-        Ok(Plan(self.0))
+    /// 以结果集的形式返回计划，每行对应一个节点
+    pub fn explain(self) -> ResultSet {
+        let lines: Vec<String> = self.to_string().lines().map(String::from).collect();
+        Self::lines_result(lines)
+    }
+
+    /// 执行计划，并以结果集的形式返回计划以及每个节点产生的行数和耗时
+    pub fn analyze<T: Transaction + 'static>(self, txn: &mut T) -> Result<ResultSet> {
+        let plan = self.to_string();
+        let (executor, stats) = <dyn Executor<T>>::build_analyzed(self.0);
+        if let ResultSet::Query { mut rows, .. } = executor.execute(txn)? {
+            while rows.next().transpose()?.is_some() {}
+        }
+        // The plan is formatted with one line per node, in the same order as the stats.
+        let lines = plan
+            .lines()
+            .zip(stats)
+            .map(|(line, stats)| {
+                format!(
+                    "{} (rows: {}, time: {:.3} ms)",
+                    line,
+                    stats.rows(),
+                    stats.time().as_secs_f64() * 1000.0
+                )
+            })
+            .collect();
+        Ok(Self::lines_result(lines))
+    }
+
+    /// 构造一个单列 plan 的结果集
+    fn lines_result(lines: Vec<String>) -> ResultSet {
+        ResultSet::Query {
+            columns: vec![Column { name: Some("plan".into()) }],
+            rows: Box::new(lines.into_iter().map(|line| Ok::<Row, _>(vec![Value::String(line)]))),
+        }
+    }
+
+    /// 设置计划（包括子查询）中所有 WITH RECURSIVE 查询的最大迭代次数
+    pub fn with_max_recursion(self, max_iterations: u64) -> Result<Self> {
+        fn set(node: Node, max_iterations: u64) -> Result<Node> {
+            node.transform(&Ok, &|n| match n {
+                Node::Recursive { name, anchor, recursive, all, .. } => {
+                    Ok(Node::Recursive { name, anchor, recursive, all, max_iterations })
+                }
+                n => n.transform_expressions(&Ok, &|e| {
+                    e.transform_subquery(&|node| set(node, max_iterations))
+                }),
+            })
+        }
+        set(self.0, max_iterations).map(Plan)
+    }
+
+    /// 使用默认的优化规则进行优化，例如谓词下推等等
+    pub fn optimize<C: Catalog>(self, catalog: &mut C) -> Result<Self> {
+        self.optimize_with(&optimizer::rules(catalog))
+    }
+
+    /// 依次使用给定的优化规则进行优化，子查询的计划也会被分别优化
+    pub fn optimize_with(self, optimizers: &[Box<dyn Optimizer + '_>]) -> Result<Self> {
+        let node = self.0.transform(&Ok, &|n| {
+            n.transform_expressions(&Ok, &|e| {
+                e.transform_subquery(&|node| Ok(Plan(node).optimize_with(optimizers)?.0))
+            })
+        })?;
+        optimizers.iter().try_fold(node, |node, optimizer| optimizer.optimize(node)).map(Plan)
+    }
+}
+
+/// An aggregate operation
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Aggregate {
+    Average,
+    Count,
+    CountDistinct,
+    Max,
+    Min,
+    Sum,
+}
+
+impl Aggregate {
+    /// Looks up an aggregate by function name, if any. COUNT(DISTINCT x) is parsed into the
+    /// function count_distinct.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "avg" => Some(Self::Average),
+            "count" => Some(Self::Count),
+            "count_distinct" => Some(Self::CountDistinct),
+            "max" => Some(Self::Max),
+            "min" => Some(Self::Min),
+            "sum" => Some(Self::Sum),
+            _ => None,
+        }
+    }
+}
+
+impl Display for Aggregate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Average => "average",
+            Self::Count => "count",
+            Self::CountDistinct => "count distinct",
+            Self::Max => "maximum",
+            Self::Min => "minimum",
+            Self::Sum => "sum",
+        })
+    }
+}
+
+/// A table schema change, made by ALTER TABLE
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Alteration {
+    AddColumn(table::Column, Vec<Expression>),
+    DropColumn(String),
+    RenameColumn { column: String, name: String },
+    RenameTable(String),
+}
+
+impl Display for Alteration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AddColumn(column, _) => write!(f, "add column {}", column.name),
+            Self::DropColumn(column) => write!(f, "drop column {}", column),
+            Self::RenameColumn { column, name } => {
+                write!(f, "rename column {} to {}", column, name)
+            }
+            Self::RenameTable(name) => write!(f, "rename to {}", name),
+        }
+    }
+}
+
+/// A sort direction
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Direction {
+    Ascending,
+    Descending,
+}
+
+impl Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ascending => "asc",
+            Self::Descending => "desc",
+        })
+    }
+}
+
+/// A join type. Right joins are planned as left joins with swapped inputs, and cross joins
+/// as inner joins without a predicate. Semi and anti joins emit only the left-hand row, if it has
+/// any respectively no matching right-hand rows, and are used for EXISTS and IN subqueries.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum JoinType {
+    Inner,
+    Left,
+    Full,
+    Semi,
+    Anti,
+}
+
+impl Display for JoinType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Inner => "inner",
+            Self::Left => "left outer",
+            Self::Full => "full outer",
+            Self::Semi => "semi",
+            Self::Anti => "anti",
+        })
+    }
+}
+
+/// A set operator, combining the rows of two queries. Rows are compared as a whole, with NULLs
+/// considered equal.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
+}
+
+impl Display for SetOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Union => "union",
+            Self::Intersect => "intersect",
+            Self::Except => "except",
+        })
+    }
+}
+
+/// A window function, computed for each row from the rows of its partition. Only FirstValue
+/// and Aggregate use the window frame, the others use the entire partition.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WindowFunction {
+    /// The number of the row within the partition, starting at 1.
+    RowNumber,
+    /// The row number of the first peer of the row, i.e. with gaps after ties.
+    Rank,
+    /// The number of distinct ORDER BY values up to the row, i.e. without gaps after ties.
+    DenseRank,
+    /// The value of the expression in the row the given number of rows before the current row,
+    /// or the default expression if there is none.
+    Lag(Expression, u64, Expression),
+    /// The value of the expression in the row the given number of rows after the current row,
+    /// or the default expression if there is none.
+    Lead(Expression, u64, Expression),
+    /// The value of the expression in the first row of the frame.
+    FirstValue(Expression),
+    /// An aggregate of the expression over the rows of the frame.
+    Aggregate(Aggregate, Expression),
+}
+
+impl WindowFunction {
+    /// Returns the argument expressions of the function.
+    pub fn expressions(&self) -> Vec<&Expression> {
+        match self {
+            Self::RowNumber | Self::Rank | Self::DenseRank => Vec::new(),
+            Self::Lag(expr, _, default) | Self::Lead(expr, _, default) => vec![expr, default],
+            Self::FirstValue(expr) | Self::Aggregate(_, expr) => vec![expr],
+        }
+    }
+
+    /// Whether the function is computed over the window frame.
+    pub fn uses_frame(&self) -> bool {
+        matches!(self, Self::FirstValue(_) | Self::Aggregate(..))
+    }
+
+    /// Transforms the argument expressions of the function.
+    fn transform_expressions<B, A>(self, before: &B, after: &A) -> Result<Self>
+    where
+        B: Fn(Expression) -> Result<Expression>,
+        A: Fn(Expression) -> Result<Expression>,
+    {
+        Ok(match self {
+            f @ (Self::RowNumber | Self::Rank | Self::DenseRank) => f,
+            Self::Lag(expr, offset, default) => Self::Lag(
+                expr.transform(before, after)?,
+                offset,
+                default.transform(before, after)?,
+            ),
+            Self::Lead(expr, offset, default) => Self::Lead(
+                expr.transform(before, after)?,
+                offset,
+                default.transform(before, after)?,
+            ),
+            Self::FirstValue(expr) => Self::FirstValue(expr.transform(before, after)?),
+            Self::Aggregate(aggregate, expr) => {
+                Self::Aggregate(aggregate, expr.transform(before, after)?)
+            }
+        })
+    }
+}
+
+impl Display for WindowFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::RowNumber => write!(f, "row_number()"),
+            Self::Rank => write!(f, "rank()"),
+            Self::DenseRank => write!(f, "dense_rank()"),
+            Self::Lag(expr, offset, default) => write!(f, "lag({}, {}, {})", expr, offset, default),
+            Self::Lead(expr, offset, default) => {
+                write!(f, "lead({}, {}, {})", expr, offset, default)
+            }
+            Self::FirstValue(expr) => write!(f, "first_value({})", expr),
+            Self::Aggregate(aggregate, expr) => write!(f, "{}({})", aggregate, expr),
+        }
+    }
+}
+
+/// A window frame, i.e. the rows around the current row that a window function is computed
+/// over. ROWS frames are offset by a number of rows, RANGE frames by the difference of the single
+/// ORDER BY value. The current row's peers, i.e. rows with equal ORDER BY values, are all part of
+/// a RANGE frame ending at the current row.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub rows: bool,
+    pub start: FrameBound,
+    pub end: FrameBound,
+}
+
+impl Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = if self.rows { "rows" } else { "range" };
+        write!(f, "{} between {} and {}", units, self.start, self.end)
+    }
+}
+
+/// A window frame bound. Offsets are non-negative integers for ROWS frames, and non-negative
+/// numbers for RANGE frames.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum FrameBound {
+    UnboundedPreceding,
+    Preceding(Value),
+    CurrentRow,
+    Following(Value),
+    UnboundedFollowing,
+}
+
+impl Display for FrameBound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnboundedPreceding => write!(f, "unbounded preceding"),
+            Self::Preceding(offset) => write!(f, "{} preceding", offset),
+            Self::CurrentRow => write!(f, "current row"),
+            Self::Following(offset) => write!(f, "{} following", offset),
+            Self::UnboundedFollowing => write!(f, "unbounded following"),
+        }
     }
 }
 
 /// A plan node
-#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Node {
+    Aggregation {
+        source: Box<Node>,
+        aggregates: Vec<Aggregate>,
+    },
+    AlterTable {
+        table: String,
+        alteration: Alteration,
+    },
+    CreateSequence {
+        schema: Sequence,
+    },
     CreateTable {
         schema: Table,
     },
+    CreateView {
+        schema: View,
+    },
     Delete {
         table: String,
         source: Box<Node>,
     },
+    /// Emits the first source row for each distinct value of the given expressions, or of the
+    /// entire row if there are none.
+    Distinct {
+        source: Box<Node>,
+        on: Vec<Expression>,
+    },
+    DropSequence {
+        sequence: String,
+    },
     DropTable {
         table: String,
     },
+    DropView {
+        view: String,
+    },
     Filter {
         source: Box<Node>,
         predicate: Expression,
     },
+    HashJoin {
+        left: Box<Node>,
+        left_field: (usize, Option<(Option<String>, String)>),
+        right: Box<Node>,
+        right_field: (usize, Option<(Option<String>, String)>),
+        r#type: JoinType,
+    },
+    IndexLookup {
+        table: String,
+        alias: Option<String>,
+        column: String,
+        values: Vec<Value>,
+    },
     Insert {
         table: String,
         columns: Vec<String>,
         expressions: Vec<Vec<Expression>>,
     },
+    KeyLookup {
+        table: String,
+        alias: Option<String>,
+        keys: Vec<Vec<Value>>,
+    },
+    Limit {
+        source: Box<Node>,
+        limit: u64,
+    },
+    NestedLoopJoin {
+        left: Box<Node>,
+        left_size: usize,
+        right: Box<Node>,
+        predicate: Option<Expression>,
+        r#type: JoinType,
+    },
+    Offset {
+        source: Box<Node>,
+        offset: u64,
+    },
+    Order {
+        source: Box<Node>,
+        orders: Vec<(Expression, Direction)>,
+    },
     Projection {
         source: Box<Node>,
         expressions: Vec<(Expression, Option<String>)>,
     },
+    /// A WITH RECURSIVE query. The anchor is executed once, and the recursive term is then
+    /// executed repeatedly with the rows of the previous iteration in its working table, until
+    /// it returns no (new) rows. Unless all is set, duplicate rows are removed.
+    Recursive {
+        name: String,
+        anchor: Box<Node>,
+        recursive: Box<Node>,
+        all: bool,
+        max_iterations: u64,
+    },
     Scan {
         table: String,
         alias: Option<String>,
         filter: Option<Expression>,
+        /// 父节点用到的列，None 表示全部列。其他列的行外存储的值不会被读取，返回 NULL
+        columns: Option<Vec<usize>>,
+    },
+    /// Combines the rows of two inputs with the same number of columns. Unless all is set,
+    /// duplicate rows are removed.
+    SetOperation {
+        left: Box<Node>,
+        right: Box<Node>,
+        operator: SetOperator,
+        all: bool,
+    },
+    TopN {
+        source: Box<Node>,
+        orders: Vec<(Expression, Direction)>,
+        limit: u64,
     },
     Update {
         table: String,
         source: Box<Node>,
         expressions: Vec<(usize, Option<String>, Expression)>,
     },
-    Nothing,
+    /// Emits the given rows, e.g. a single empty row as the source of a SELECT without FROM.
+    Values {
+        rows: Vec<Row>,
+    },
+    /// Computes window functions over the source rows, appending a column for each function.
+    /// Rows are emitted sorted by the partition expressions and then the orders.
+    Window {
+        source: Box<Node>,
+        partition: Vec<Expression>,
+        orders: Vec<(Expression, Direction)>,
+        functions: Vec<(WindowFunction, Frame)>,
+    },
+    /// The working table of a WITH RECURSIVE query, whose rows are filled in by the Recursive
+    /// executor before each iteration.
+    WorkingTable {
+        name: String,
+        columns: Vec<Column>,
+        rows: Vec<Row>,
+    },
+    /// Emits no rows, with the given columns. Replaces subtrees that can't emit any rows.
+    Nothing {
+        columns: Vec<Column>,
+    },
 }
 
 impl Node {
@@ -84,27 +503,150 @@ impl Node {
     {
         self = before(self)?;
         self = match self {
-            n @ Self::CreateTable { .. }
+            Self::Aggregation { source, aggregates } => {
+                Self::Aggregation { source: source.transform(before, after)?.into(), aggregates }
+            }
+            n @ Self::AlterTable { .. }
+            | n @ Self::CreateSequence { .. }
+            | n @ Self::CreateTable { .. }
+            | n @ Self::CreateView { .. }
+            | n @ Self::DropSequence { .. }
             | n @ Self::DropTable { .. }
+            | n @ Self::DropView { .. }
+            | n @ Self::IndexLookup { .. }
             | n @ Self::Insert { .. }
-            | n @ Self::Nothing
-            | n @ Self::Scan { .. } => n,
+            | n @ Self::KeyLookup { .. }
+            | n @ Self::Nothing { .. }
+            | n @ Self::Scan { .. }
+            | n @ Self::Values { .. }
+            | n @ Self::WorkingTable { .. } => n,
             Self::Delete { table, source } => {
                 Self::Delete { table, source: source.transform(before, after)?.into() }
             }
+            Self::Distinct { source, on } => {
+                Self::Distinct { source: source.transform(before, after)?.into(), on }
+            }
             Self::Filter { source, predicate } => {
                 Self::Filter { source: source.transform(before, after)?.into(), predicate }
             }
+            Self::HashJoin { left, left_field, right, right_field, r#type } => Self::HashJoin {
+                left: left.transform(before, after)?.into(),
+                left_field,
+                right: right.transform(before, after)?.into(),
+                right_field,
+                r#type,
+            },
+            Self::Limit { source, limit } => {
+                Self::Limit { source: source.transform(before, after)?.into(), limit }
+            }
+            Self::NestedLoopJoin { left, left_size, right, predicate, r#type } => {
+                Self::NestedLoopJoin {
+                    left: left.transform(before, after)?.into(),
+                    left_size,
+                    right: right.transform(before, after)?.into(),
+                    predicate,
+                    r#type,
+                }
+            }
+            Self::Offset { source, offset } => {
+                Self::Offset { source: source.transform(before, after)?.into(), offset }
+            }
+            Self::Order { source, orders } => {
+                Self::Order { source: source.transform(before, after)?.into(), orders }
+            }
             Self::Projection { source, expressions } => {
                 Self::Projection { source: source.transform(before, after)?.into(), expressions }
             }
+            Self::Recursive { name, anchor, recursive, all, max_iterations } => Self::Recursive {
+                name,
+                anchor: anchor.transform(before, after)?.into(),
+                recursive: recursive.transform(before, after)?.into(),
+                all,
+                max_iterations,
+            },
+            Self::SetOperation { left, right, operator, all } => Self::SetOperation {
+                left: left.transform(before, after)?.into(),
+                right: right.transform(before, after)?.into(),
+                operator,
+                all,
+            },
+            Self::TopN { source, orders, limit } => {
+                Self::TopN { source: source.transform(before, after)?.into(), orders, limit }
+            }
             Self::Update { table, source, expressions } => {
                 Self::Update { table, source: source.transform(before, after)?.into(), expressions }
             }
+            Self::Window { source, partition, orders, functions } => Self::Window {
+                source: source.transform(before, after)?.into(),
+                partition,
+                orders,
+                functions,
+            },
         };
         after(self)
     }
 
+    /// Returns the outer fields referenced by a subquery plan, i.e. the fields of the enclosing
+    /// query it is correlated with. These include the fields referenced by nested subqueries,
+    /// while the nested subqueries' references to this plan's fields are not included.
+    pub fn outer_fields(&self) -> BTreeSet<usize> {
+        let fields = RefCell::new(BTreeSet::new());
+        self.clone()
+            .transform_outer_fields(&|depth, i, label| {
+                if depth == 1 {
+                    fields.borrow_mut().insert(i);
+                }
+                Ok(Expression::OuterField(depth, i, label))
+            })
+            .expect("expression walk can't fail");
+        fields.into_inner()
+    }
+
+    /// Binds the outer fields of a correlated subquery plan to the values of the given row.
+    pub fn bind_outer(self, row: &Row) -> Result<Self> {
+        self.transform_outer_fields(&|depth, i, label| match depth {
+            1 => Ok(Expression::Constant(row.get(i).cloned().unwrap_or(Value::Null))),
+            depth => Ok(Expression::OuterField(depth, i, label)),
+        })
+    }
+
+    /// Transforms the outer fields of a subquery plan that reference queries enclosing it,
+    /// including those of nested subqueries, with the given closure. The closure is given the
+    /// depth relative to this plan, i.e. 1 for the directly enclosing query, and outer fields it
+    /// returns are taken as relative to this plan too.
+    pub fn transform_outer_fields<F>(self, f: &F) -> Result<Self>
+    where
+        F: Fn(usize, usize, Option<(Option<String>, String)>) -> Result<Expression>,
+    {
+        self.transform_nested_outer_fields(0, f)
+    }
+
+    /// Helper for transform_outer_fields(), for a plan nested the given number of subquery levels
+    /// below the plan being transformed.
+    fn transform_nested_outer_fields<F>(self, nesting: usize, f: &F) -> Result<Self>
+    where
+        F: Fn(usize, usize, Option<(Option<String>, String)>) -> Result<Expression>,
+    {
+        self.transform(&Ok, &|n| {
+            n.transform_expressions(
+                &|e| match e {
+                    Expression::OuterField(depth, i, label) if depth > nesting => {
+                        Ok(match f(depth - nesting, i, label)? {
+                            Expression::OuterField(depth, i, label) => {
+                                Expression::OuterField(depth + nesting, i, label)
+                            }
+                            e => e,
+                        })
+                    }
+                    e => e.transform_subquery(&|node| {
+                        node.transform_nested_outer_fields(nesting + 1, f)
+                    }),
+                },
+                &Ok,
+            )
+        })
+    }
+
     /// Transforms all expressions in a node by calling .transform() on them with the given closure.
     pub fn transform_expressions<B, A>(self, before: &B, after: &A) -> Result<Self>
     where
@@ -112,11 +654,27 @@ impl Node {
         A: Fn(Expression) -> Result<Expression>,
     {
         Ok(match self {
-            n @ Self::CreateTable { .. }
+            n @ Self::Aggregation { .. }
+            | n @ Self::AlterTable { .. }
+            | n @ Self::CreateSequence { .. }
+            | n @ Self::CreateTable { .. }
+            | n @ Self::CreateView { .. }
             | n @ Self::Delete { .. }
+            | n @ Self::DropSequence { .. }
             | n @ Self::DropTable { .. }
-            | n @ Self::Nothing
-            | n @ Self::Scan { filter: None, .. } => n,
+            | n @ Self::DropView { .. }
+            | n @ Self::HashJoin { .. }
+            | n @ Self::IndexLookup { .. }
+            | n @ Self::KeyLookup { .. }
+            | n @ Self::Limit { .. }
+            | n @ Self::NestedLoopJoin { predicate: None, .. }
+            | n @ Self::Nothing { .. }
+            | n @ Self::Offset { .. }
+            | n @ Self::Recursive { .. }
+            | n @ Self::Scan { filter: None, .. }
+            | n @ Self::SetOperation { .. }
+            | n @ Self::Values { .. }
+            | n @ Self::WorkingTable { .. } => n,
 
             Self::Filter { source, predicate } => {
                 Self::Filter { source, predicate: predicate.transform(before, after)? }
@@ -129,6 +687,26 @@ impl Node {
                     .map(|exprs| exprs.into_iter().map(|e| e.transform(before, after)).collect())
                     .collect::<Result<_>>()?,
             },
+            Self::NestedLoopJoin { left, left_size, right, predicate: Some(predicate), r#type } => {
+                Self::NestedLoopJoin {
+                    left,
+                    left_size,
+                    right,
+                    predicate: Some(predicate.transform(before, after)?),
+                    r#type,
+                }
+            }
+            Self::Distinct { source, on } => Self::Distinct {
+                source,
+                on: on.into_iter().map(|e| e.transform(before, after)).collect::<Result<_>>()?,
+            },
+            Self::Order { source, orders } => Self::Order {
+                source,
+                orders: orders
+                    .into_iter()
+                    .map(|(e, o)| e.transform(before, after).map(|e| (e, o)))
+                    .collect::<Result<_>>()?,
+            },
             Self::Projection { source, expressions } => Self::Projection {
                 source,
                 expressions: expressions
@@ -136,9 +714,20 @@ impl Node {
                     .map(|(e, l)| Ok((e.transform(before, after)?, l)))
                     .collect::<Result<_>>()?,
             },
-            Self::Scan { table, alias, filter: Some(filter) } => {
-                Self::Scan { table, alias, filter: Some(filter.transform(before, after)?) }
-            }
+            Self::Scan { table, alias, filter: Some(filter), columns } => Self::Scan {
+                table,
+                alias,
+                filter: Some(filter.transform(before, after)?),
+                columns,
+            },
+            Self::TopN { source, orders, limit } => Self::TopN {
+                source,
+                orders: orders
+                    .into_iter()
+                    .map(|(e, o)| e.transform(before, after).map(|e| (e, o)))
+                    .collect::<Result<_>>()?,
+                limit,
+            },
             Self::Update { table, source, expressions } => Self::Update {
                 table,
                 source,
@@ -147,6 +736,21 @@ impl Node {
                     .map(|(i, l, e)| e.transform(before, after).map(|e| (i, l, e)))
                     .collect::<Result<_>>()?,
             },
+            Self::Window { source, partition, orders, functions } => Self::Window {
+                source,
+                partition: partition
+                    .into_iter()
+                    .map(|e| e.transform(before, after))
+                    .collect::<Result<_>>()?,
+                orders: orders
+                    .into_iter()
+                    .map(|(e, o)| e.transform(before, after).map(|e| (e, o)))
+                    .collect::<Result<_>>()?,
+                functions: functions
+                    .into_iter()
+                    .map(|(f, frame)| Ok((f.transform_expressions(before, after)?, frame)))
+                    .collect::<Result<_>>()?,
+            },
         })
     }
 
@@ -161,23 +765,133 @@ impl Node {
             indent += "   ";
         }
         match self {
+            Self::Aggregation { source, aggregates } => {
+                s += &format!(
+                    "Aggregation: {}\n",
+                    aggregates.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ")
+                );
+                s += &source.format(indent, false, true);
+            }
+            Self::AlterTable { table, alteration } => {
+                s += &format!("AlterTable: {} {}\n", table, alteration);
+            }
+            Self::CreateSequence { schema } => {
+                s += &format!("CreateSequence: {}\n", schema.name);
+            }
             Self::CreateTable { schema } => {
                 s += &format!("CreateTable: {}\n", schema.name);
             }
+            Self::CreateView { schema } => {
+                s += &format!("CreateView: {}\n", schema.name);
+            }
             Self::Delete { source, table } => {
                 s += &format!("Delete: {}\n", table);
                 s += &source.format(indent, false, true);
             }
+            Self::Distinct { source, on } => {
+                s += "Distinct";
+                if !on.is_empty() {
+                    s += &format!(
+                        " on {}",
+                        on.iter().map(|e| e.to_string()).collect::<Vec<_>>().join(", ")
+                    );
+                }
+                s += "\n";
+                s += &source.format(indent, false, true);
+            }
+            Self::DropSequence { sequence } => {
+                s += &format!("DropSequence: {}\n", sequence);
+            }
             Self::DropTable { table } => {
                 s += &format!("DropTable: {}\n", table);
             }
+            Self::DropView { view } => {
+                s += &format!("DropView: {}\n", view);
+            }
             Self::Filter { source, predicate } => {
                 s += &format!("Filter: {}\n", predicate);
                 s += &source.format(indent, false, true);
             }
+            Self::IndexLookup { table, alias, column, values } => {
+                s += &format!("IndexLookup: {}", table);
+                if let Some(alias) = alias {
+                    s += &format!(" as {}", alias);
+                }
+                s += &format!(" column {}", column);
+                if !values.is_empty() && values.len() < 10 {
+                    s += &format!(
+                        " ({})",
+                        values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+                    );
+                } else {
+                    s += &format!(" ({} values)", values.len());
+                }
+                s += "\n";
+            }
             Self::Insert { table, columns: _, expressions } => {
                 s += &format!("Insert: {} ({} rows)\n", table, expressions.len());
             }
+            Self::KeyLookup { table, alias, keys } => {
+                s += &format!("KeyLookup: {}", table);
+                if let Some(alias) = alias {
+                    s += &format!(" as {}", alias);
+                }
+                if !keys.is_empty() && keys.len() < 10 {
+                    s += &format!(
+                        " ({})",
+                        keys.iter().map(|k| format_key(k)).collect::<Vec<_>>().join(", ")
+                    );
+                } else {
+                    s += &format!(" ({} keys)", keys.len());
+                }
+                s += "\n";
+            }
+            Self::HashJoin { left, left_field, right, right_field, r#type } => {
+                s += &format!(
+                    "HashJoin: {} on {} = {}\n",
+                    r#type,
+                    match left_field {
+                        (_, Some((Some(t), n))) => format!("{}.{}", t, n),
+                        (_, Some((None, n))) => n.clone(),
+                        (i, None) => format!("left #{}", i),
+                    },
+                    match right_field {
+                        (_, Some((Some(t), n))) => format!("{}.{}", t, n),
+                        (_, Some((None, n))) => n.clone(),
+                        (i, None) => format!("right #{}", i),
+                    },
+                );
+                s += &left.format(indent.clone(), false, false);
+                s += &right.format(indent, false, true);
+            }
+            Self::Limit { source, limit } => {
+                s += &format!("Limit: {}\n", limit);
+                s += &source.format(indent, false, true);
+            }
+            Self::NestedLoopJoin { left, left_size: _, right, predicate, r#type } => {
+                s += &format!("NestedLoopJoin: {}", r#type);
+                if let Some(expr) = predicate {
+                    s += &format!(" ({})", expr);
+                }
+                s += "\n";
+                s += &left.format(indent.clone(), false, false);
+                s += &right.format(indent, false, true);
+            }
+            Self::Offset { source, offset } => {
+                s += &format!("Offset: {}\n", offset);
+                s += &source.format(indent, false, true);
+            }
+            Self::Order { source, orders } => {
+                s += &format!(
+                    "Order: {}\n",
+                    orders
+                        .iter()
+                        .map(|(expr, dir)| format!("{} {}", expr, dir))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                s += &source.format(indent, false, true);
+            }
             Self::Projection { source, expressions } => {
                 s += &format!(
                     "Projection: {}\n",
@@ -189,7 +903,13 @@ impl Node {
                 );
                 s += &source.format(indent, false, true);
             }
-            Self::Scan { table, alias, filter } => {
+            Self::Recursive { name, anchor, recursive, all, max_iterations: _ } => {
+                let union = if *all { "union all" } else { "union" };
+                s += &format!("Recursive: {} ({})\n", name, union);
+                s += &anchor.format(indent.clone(), false, false);
+                s += &recursive.format(indent, false, true);
+            }
+            Self::Scan { table, alias, filter, columns: _ } => {
                 s += &format!("Scan: {}", table);
                 if let Some(alias) = alias {
                     s += &format!(" as {}", alias);
@@ -199,6 +919,23 @@ impl Node {
                 }
                 s += "\n";
             }
+            Self::SetOperation { left, right, operator, all } => {
+                s += &format!("SetOperation: {}{}\n", operator, if *all { " all" } else { "" });
+                s += &left.format(indent.clone(), false, false);
+                s += &right.format(indent, false, true);
+            }
+            Self::TopN { source, orders, limit } => {
+                s += &format!(
+                    "TopN: {} by {}\n",
+                    limit,
+                    orders
+                        .iter()
+                        .map(|(expr, dir)| format!("{} {}", expr, dir))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                );
+                s += &source.format(indent, false, true);
+            }
             Self::Update { source, table, expressions } => {
                 s += &format!(
                     "Update: {} ({})\n",
@@ -215,7 +952,35 @@ impl Node {
                 );
                 s += &source.format(indent, false, true);
             },
-            Self::Nothing {} => {
+            Self::Window { source, partition, orders, functions } => {
+                let functions = functions.iter().map(|(function, frame)| {
+                    if function.uses_frame() {
+                        format!("{} {}", function, frame)
+                    } else {
+                        function.to_string()
+                    }
+                });
+                s += &format!("Window: {}", functions.collect::<Vec<_>>().join(", "));
+                if !partition.is_empty() {
+                    let partition: Vec<_> = partition.iter().map(|e| e.to_string()).collect();
+                    s += &format!(" partition by {}", partition.join(", "));
+                }
+                if !orders.is_empty() {
+                    let orders: Vec<_> =
+                        orders.iter().map(|(e, d)| format!("{} {}", e, d)).collect();
+                    s += &format!(" order by {}", orders.join(", "));
+                }
+                s += "\n";
+                s += &source.format(indent, false, true);
+            }
+            Self::WorkingTable { name, columns: _, rows: _ } => {
+                s += &format!("WorkingTable: {}\n", name);
+            }
+            Self::Values { rows } => {
+                let plural = if rows.len() == 1 { "" } else { "s" };
+                s += &format!("Values: {} row{}\n", rows.len(), plural);
+            }
+            Self::Nothing { columns: _ } => {
                 s += "Nothing\n";
             }
         };
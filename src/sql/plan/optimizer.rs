@@ -0,0 +1,1392 @@
+use super::{JoinType, Node};
+use crate::error::Result;
+use crate::sql::schema::catalog::Catalog;
+use crate::sql::types::expression::{self, Expression};
+use crate::sql::types::{Column, DataType, Value};
+
+use std::collections::BTreeSet;
+
+/// A plan optimizer
+pub trait Optimizer {
+    fn optimize(&self, node: Node) -> Result<Node>;
+}
+
+/// Returns the default optimizer rules, in the order they are applied by Plan::optimize. Later
+/// rules depend on earlier ones, e.g. index lookups require filters to be pushed into scans.
+pub fn rules<C: Catalog>(catalog: &C) -> Vec<Box<dyn Optimizer + '_>> {
+    vec![
+        Box::new(ConstantFolder),
+        Box::new(FilterPushdown),
+        Box::new(NoopCleaner::new(catalog)),
+        Box::new(JoinReorder::new(catalog)),
+        Box::new(IndexLookup::new(catalog)),
+        Box::new(ColumnPruning),
+        Box::new(HashJoiner),
+        Box::new(LimitPushdown),
+    ]
+}
+
+/// Folds constant (sub)expressions by pre-evaluating them, and simplifies AND and OR
+/// expressions with a constant boolean operand. Expressions that fail to evaluate are left
+/// as-is, such that any error is only returned if they are actually evaluated during execution.
+/// Volatile expressions, e.g. NEXTVAL(), are never folded.
+pub struct ConstantFolder;
+
+impl Optimizer for ConstantFolder {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(&Ok, &|n| {
+            n.transform_expressions(
+                &|e| {
+                    if !e.contains(&|expr| matches!(expr, Expression::Field(_, _)))
+                        && !e.is_volatile()
+                    {
+                        if let Ok(value) = e.evaluate(None) {
+                            return Ok(Expression::Constant(value));
+                        }
+                    }
+                    Ok(e)
+                },
+                &|e| {
+                    use Expression::{And, Constant, Or};
+                    Ok(match e {
+                        And(lhs, rhs) => match (*lhs, *rhs) {
+                            (Constant(Value::Boolean(false)), _)
+                            | (_, Constant(Value::Boolean(false))) => {
+                                Constant(Value::Boolean(false))
+                            }
+                            (Constant(Value::Boolean(true)), e)
+                            | (e, Constant(Value::Boolean(true))) => e,
+                            (lhs, rhs) => And(lhs.into(), rhs.into()),
+                        },
+                        Or(lhs, rhs) => match (*lhs, *rhs) {
+                            (Constant(Value::Boolean(true)), _)
+                            | (_, Constant(Value::Boolean(true))) => Constant(Value::Boolean(true)),
+                            (Constant(Value::Boolean(false)), e)
+                            | (e, Constant(Value::Boolean(false))) => e,
+                            (lhs, rhs) => Or(lhs.into(), rhs.into()),
+                        },
+                        e => e,
+                    })
+                },
+            )
+        })
+    }
+}
+
+/// Pushes filter predicates down the plan tree, as close to the data source as possible: into
+/// scans, where they can be used for index lookups and are evaluated by the storage engine, and
+/// below joins into the join inputs. Join predicates are also pushed into the join inputs where
+/// possible. Predicates are only pushed where this doesn't change the result of outer joins.
+/// Predicates with subqueries are kept in place, since only filters can execute them.
+pub struct FilterPushdown;
+
+impl Optimizer for FilterPushdown {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(
+            &|n| match n {
+                Node::Filter { source, predicate } => Ok(Self::push_filter(*source, predicate)),
+                Node::NestedLoopJoin {
+                    left,
+                    left_size,
+                    right,
+                    predicate: Some(predicate),
+                    r#type,
+                } => Ok(Self::push_join(*left, left_size, *right, predicate, r#type)),
+                n => Ok(n),
+            },
+            &Ok,
+        )
+    }
+}
+
+impl FilterPushdown {
+    /// Pushes a filter predicate into the given source node, as far down as possible.
+    fn push_filter(source: Node, predicate: Expression) -> Node {
+        if predicate.has_subqueries() {
+            let (kept, pushed): (Vec<_>, Vec<_>) =
+                predicate.into_cnf_vec().into_iter().partition(|e| e.has_subqueries());
+            let source = match Expression::from_cnf_vec(pushed) {
+                Some(pushed) => Self::push_filter(source, pushed),
+                None => source,
+            };
+            let kept = Expression::from_cnf_vec(kept).expect("subquery predicate");
+            return Node::Filter { source: source.into(), predicate: kept };
+        }
+        match source {
+            Node::Filter { source, predicate: inner } => {
+                Self::push_filter(*source, Expression::And(inner.into(), predicate.into()))
+            }
+            Node::Scan { table, alias, filter, columns } => Node::Scan {
+                table,
+                alias,
+                filter: Some(match filter {
+                    Some(filter) => Expression::And(filter.into(), predicate.into()),
+                    None => predicate,
+                }),
+                columns,
+            },
+            // For inner joins, filters are equivalent to join predicates.
+            Node::NestedLoopJoin {
+                left,
+                left_size,
+                right,
+                predicate: join,
+                r#type: JoinType::Inner,
+            } => {
+                let predicate = match join {
+                    Some(join) => Expression::And(join.into(), predicate.into()),
+                    None => predicate,
+                };
+                Self::push_join(*left, left_size, *right, predicate, JoinType::Inner)
+            }
+            // For left joins, filters on the left-hand fields can be applied before joining,
+            // but filters on the right-hand fields must see the NULL-padded rows.
+            Node::NestedLoopJoin {
+                left,
+                left_size,
+                right,
+                predicate: join,
+                r#type: JoinType::Left,
+            } => {
+                let (pushed, kept): (Vec<_>, Vec<_>) = predicate
+                    .into_cnf_vec()
+                    .into_iter()
+                    .partition(|e| !Self::references_right(e, left_size));
+                let left = match Expression::from_cnf_vec(pushed) {
+                    Some(pushed) => Self::push_filter(*left, pushed),
+                    None => *left,
+                };
+                let node = Node::NestedLoopJoin {
+                    left: left.into(),
+                    left_size,
+                    right,
+                    predicate: join,
+                    r#type: JoinType::Left,
+                };
+                match Expression::from_cnf_vec(kept) {
+                    Some(kept) => Node::Filter { source: node.into(), predicate: kept },
+                    None => node,
+                }
+            }
+            // Semi and anti joins only emit left-hand rows, which can be filtered before joining.
+            Node::NestedLoopJoin {
+                left,
+                left_size,
+                right,
+                predicate: join,
+                r#type: r#type @ (JoinType::Semi | JoinType::Anti),
+            } => Node::NestedLoopJoin {
+                left: Self::push_filter(*left, predicate).into(),
+                left_size,
+                right,
+                predicate: join,
+                r#type,
+            },
+            source => Node::Filter { source: source.into(), predicate },
+        }
+    }
+
+    /// Pushes the parts of a join predicate that only reference one of the join inputs into that
+    /// input, returning the join node with the remaining predicate, if any.
+    fn push_join(
+        left: Node,
+        left_size: usize,
+        right: Node,
+        predicate: Expression,
+        r#type: JoinType,
+    ) -> Node {
+        let (mut left_exprs, mut right_exprs, mut join_exprs) =
+            (Vec::new(), Vec::new(), Vec::new());
+        for expr in predicate.into_cnf_vec() {
+            let (on_left, on_right) =
+                (Self::references_left(&expr, left_size), Self::references_right(&expr, left_size));
+            match r#type {
+                // The right-hand input of a left join can be filtered before joining, since
+                // unmatched left-hand rows are padded with NULLs either way. Constant predicates
+                // are therefore also pushed to the right.
+                JoinType::Left if !on_left => right_exprs.push(expr),
+                JoinType::Inner if !on_right => left_exprs.push(expr),
+                JoinType::Inner if !on_left => right_exprs.push(expr),
+                // Semi joins only check for a match, so both inputs can be filtered. Anti joins
+                // emit the unmatched left-hand rows, so only the right-hand input can be.
+                JoinType::Semi if !on_right => left_exprs.push(expr),
+                JoinType::Semi | JoinType::Anti if !on_left => right_exprs.push(expr),
+                _ => join_exprs.push(expr),
+            }
+        }
+        let left = match Expression::from_cnf_vec(left_exprs) {
+            Some(predicate) => Self::push_filter(left, predicate),
+            None => left,
+        };
+        let right = match Expression::from_cnf_vec(right_exprs) {
+            Some(predicate) => Self::push_filter(right, Self::shift_fields(predicate, left_size)),
+            None => right,
+        };
+        Node::NestedLoopJoin {
+            left: left.into(),
+            left_size,
+            right: right.into(),
+            predicate: Expression::from_cnf_vec(join_exprs),
+            r#type,
+        }
+    }
+
+    /// Checks whether an expression references any left-hand join fields.
+    fn references_left(expr: &Expression, left_size: usize) -> bool {
+        expr.contains(&|e| matches!(e, Expression::Field(i, _) if *i < left_size))
+    }
+
+    /// Checks whether an expression references any right-hand join fields.
+    fn references_right(expr: &Expression, left_size: usize) -> bool {
+        expr.contains(&|e| matches!(e, Expression::Field(i, _) if *i >= left_size))
+    }
+
+    /// Shifts right-hand join field references to index into the right-hand input.
+    fn shift_fields(expr: Expression, left_size: usize) -> Expression {
+        expr.transform(
+            &|e| match e {
+                Expression::Field(i, label) => Ok(Expression::Field(i - left_size, label)),
+                e => Ok(e),
+            },
+            &Ok,
+        )
+        .expect("field shifting can't fail")
+    }
+}
+
+/// Removes filters and join predicates that are always true, and replaces scans and filters that
+/// are always false or NULL with Node::Nothing, which emits no rows. Nothing retains the columns of
+/// the replaced subtree, which are needed e.g. for NULL-padding in outer joins and for the result
+/// column names, so filters are only replaced if the columns of their source are known.
+pub struct NoopCleaner<'a, C: Catalog> {
+    catalog: &'a C,
+}
+
+impl<'a, C: Catalog> NoopCleaner<'a, C> {
+    pub fn new(catalog: &'a C) -> Self {
+        Self { catalog }
+    }
+
+    /// Returns the columns emitted by a node, if known.
+    fn columns(&self, node: &Node) -> Result<Option<Vec<Column>>> {
+        Ok(match node {
+            Node::Scan { table, .. } => Some(
+                self.catalog
+                    .must_read_table(table)?
+                    .columns
+                    .into_iter()
+                    .map(|c| Column { name: Some(c.name) })
+                    .collect(),
+            ),
+            Node::Projection { source, expressions } => {
+                let Some(columns) = self.columns(source)? else { return Ok(None) };
+                Some(
+                    expressions
+                        .iter()
+                        .map(|(e, label)| match (e, label) {
+                            (_, Some(label)) => Column { name: Some(label.clone()) },
+                            (Expression::Field(i, _), None) => {
+                                columns.get(*i).cloned().unwrap_or(Column { name: None })
+                            }
+                            (_, None) => Column { name: None },
+                        })
+                        .collect(),
+                )
+            }
+            Node::NestedLoopJoin { left, r#type: JoinType::Semi | JoinType::Anti, .. } => {
+                self.columns(left)?
+            }
+            Node::NestedLoopJoin { left, right, .. } => {
+                match (self.columns(left)?, self.columns(right)?) {
+                    (Some(mut left), Some(right)) => {
+                        left.extend(right);
+                        Some(left)
+                    }
+                    _ => None,
+                }
+            }
+            Node::Distinct { source, .. }
+            | Node::Filter { source, .. }
+            | Node::Limit { source, .. }
+            | Node::Offset { source, .. }
+            | Node::Order { source, .. }
+            | Node::TopN { source, .. } => self.columns(source)?,
+            Node::Values { rows } => {
+                Some(vec![Column { name: None }; rows.first().map_or(0, |row| row.len())])
+            }
+            Node::Nothing { columns } => Some(columns.clone()),
+            _ => None,
+        })
+    }
+}
+
+impl<C: Catalog> Optimizer for NoopCleaner<'_, C> {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        use Expression::Constant;
+        node.transform(&Ok, &|n| match n {
+            Node::Filter { source, predicate: Constant(Value::Boolean(true)) } => Ok(*source),
+            Node::Scan { table, alias, filter: Some(Constant(Value::Boolean(true))), columns } => {
+                Ok(Node::Scan { table, alias, filter: None, columns })
+            }
+            Node::NestedLoopJoin {
+                left,
+                left_size,
+                right,
+                predicate: Some(Constant(Value::Boolean(true))),
+                r#type,
+            } => Ok(Node::NestedLoopJoin { left, left_size, right, predicate: None, r#type }),
+            n @ (Node::Filter {
+                predicate: Constant(Value::Boolean(false) | Value::Null), ..
+            }
+            | Node::Scan {
+                filter: Some(Constant(Value::Boolean(false) | Value::Null)), ..
+            }) => match self.columns(&n)? {
+                Some(columns) => Ok(Node::Nothing { columns }),
+                None => Ok(n),
+            },
+            n => Ok(n),
+        })
+    }
+}
+
+/// Reorders chains of three or more inner joins such that, where possible, each input is joined
+/// with a predicate connecting it to the inputs joined before it, instead of building a cross
+/// product, e.g. for FROM a, b, c WHERE a.id = c.id AND b.id = c.id. Starting with the first
+/// input, inputs are greedily picked in their original order, preferring connected ones. The
+/// original column order is restored by a projection. Chains where the width of an input can't
+/// be determined are left alone.
+pub struct JoinReorder<'a, C: Catalog> {
+    catalog: &'a C,
+}
+
+impl<'a, C: Catalog> JoinReorder<'a, C> {
+    pub fn new(catalog: &'a C) -> Self {
+        Self { catalog }
+    }
+
+    /// Flattens a left-deep chain of inner joins into its inputs and predicate conjuncts.
+    fn flatten(node: Node, inputs: &mut Vec<Node>, predicates: &mut Vec<Expression>) {
+        match node {
+            Node::NestedLoopJoin { left, right, predicate, r#type: JoinType::Inner, .. } => {
+                Self::flatten(*left, inputs, predicates);
+                inputs.push(*right);
+                predicates.extend(predicate.map(|p| p.into_cnf_vec()).unwrap_or_default());
+            }
+            node => inputs.push(node),
+        }
+    }
+
+    /// Returns the number of columns emitted by a node, if known.
+    fn width(&self, node: &Node) -> Result<Option<usize>> {
+        Ok(match node {
+            Node::Scan { table, .. }
+            | Node::KeyLookup { table, .. }
+            | Node::IndexLookup { table, .. } => {
+                Some(self.catalog.must_read_table(table)?.columns.len())
+            }
+            Node::Projection { expressions, .. } => Some(expressions.len()),
+            Node::NestedLoopJoin { left_size, r#type: JoinType::Semi | JoinType::Anti, .. } => {
+                Some(*left_size)
+            }
+            Node::NestedLoopJoin { left_size, right, .. } => {
+                self.width(right)?.map(|w| left_size + w)
+            }
+            Node::HashJoin { left, r#type: JoinType::Semi | JoinType::Anti, .. } => {
+                self.width(left)?
+            }
+            Node::HashJoin { left, right, .. } => match (self.width(left)?, self.width(right)?) {
+                (Some(l), Some(r)) => Some(l + r),
+                _ => None,
+            },
+            Node::Aggregation { source, .. }
+            | Node::Distinct { source, .. }
+            | Node::Filter { source, .. }
+            | Node::Limit { source, .. }
+            | Node::Offset { source, .. }
+            | Node::Order { source, .. }
+            | Node::TopN { source, .. } => self.width(source)?,
+            Node::Recursive { anchor, .. } | Node::SetOperation { left: anchor, .. } => {
+                self.width(anchor)?
+            }
+            Node::Window { source, functions, .. } => {
+                self.width(source)?.map(|width| width + functions.len())
+            }
+            Node::WorkingTable { columns, .. } => Some(columns.len()),
+            Node::Values { rows } => Some(rows.first().map_or(0, |row| row.len())),
+            Node::Nothing { columns } => Some(columns.len()),
+            _ => None,
+        })
+    }
+
+    /// Reorders an inner join chain, or returns it unchanged.
+    fn reorder(&self, node: Node) -> Result<Node> {
+        if !matches!(node, Node::NestedLoopJoin { r#type: JoinType::Inner, .. }) {
+            return Ok(node);
+        }
+        let original = node.clone();
+        let (mut inputs, mut predicates) = (Vec::new(), Vec::new());
+        Self::flatten(node, &mut inputs, &mut predicates);
+        if inputs.len() < 3 {
+            return Ok(original);
+        }
+
+        // Find the column range of each input in the original row layout.
+        let mut ranges = Vec::new();
+        for input in &inputs {
+            let start = ranges.last().map(|r: &std::ops::Range<usize>| r.end).unwrap_or(0);
+            match self.width(input)? {
+                Some(width) => ranges.push(start..start + width),
+                None => return Ok(original),
+            }
+        }
+        let width = ranges.last().map(|r| r.end).unwrap_or(0);
+
+        // Find the inputs referenced by each predicate conjunct.
+        let references: Vec<Vec<usize>> = predicates
+            .iter()
+            .map(|p| {
+                (0..inputs.len())
+                    .filter(|i| {
+                        p.contains(
+                            &|e| matches!(e, Expression::Field(f, _) if ranges[*i].contains(f)),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        // Greedily pick the next input, preferring ones connected to the already joined inputs.
+        let mut order = vec![0];
+        while order.len() < inputs.len() {
+            let remaining = (0..inputs.len()).filter(|i| !order.contains(i));
+            let connected = remaining.clone().find(|i| {
+                references.iter().any(|refs| {
+                    refs.contains(i)
+                        && refs.iter().any(|r| r != i && order.contains(r))
+                        && refs.iter().all(|r| r == i || order.contains(r))
+                })
+            });
+            let next = connected.or_else(|| remaining.min()).expect("no remaining inputs");
+            order.push(next);
+        }
+        if order.iter().enumerate().all(|(i, o)| i == *o) {
+            return Ok(original);
+        }
+
+        // Map fields from the original row layout to the reordered layout.
+        let mut mapping = vec![0; width];
+        let mut next = 0;
+        for i in &order {
+            for (offset, field) in ranges[*i].clone().enumerate() {
+                mapping[field] = next + offset;
+            }
+            next += ranges[*i].len();
+        }
+        let remap = |expr: Expression| {
+            expr.transform(
+                &|e| match e {
+                    Expression::Field(i, label) => Ok(Expression::Field(mapping[i], label)),
+                    e => Ok(e),
+                },
+                &Ok,
+            )
+        };
+
+        // Build a new left-deep join chain, applying each predicate conjunct at the first join
+        // where all of its referenced inputs are available.
+        let mut inputs: Vec<Option<Node>> = inputs.into_iter().map(Some).collect();
+        let mut predicates: Vec<Option<Expression>> = predicates.into_iter().map(Some).collect();
+        let mut node = inputs[order[0]].take().expect("input already used");
+        let mut left_size = ranges[order[0]].len();
+        for (k, i) in order.iter().enumerate().skip(1) {
+            let mut cnf = Vec::new();
+            for (p, refs) in references.iter().enumerate() {
+                if predicates[p].is_some() && refs.iter().all(|r| order[..=k].contains(r)) {
+                    cnf.push(remap(predicates[p].take().expect("predicate already used"))?);
+                }
+            }
+            node = Node::NestedLoopJoin {
+                left: node.into(),
+                left_size,
+                right: inputs[*i].take().expect("input already used").into(),
+                predicate: Expression::from_cnf_vec(cnf),
+                r#type: JoinType::Inner,
+            };
+            left_size += ranges[*i].len();
+        }
+
+        Ok(Node::Projection {
+            source: node.into(),
+            expressions: mapping.into_iter().map(|f| (Expression::Field(f, None), None)).collect(),
+        })
+    }
+}
+
+impl<C: Catalog> Optimizer for JoinReorder<'_, C> {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(&|n| self.reorder(n), &Ok)
+    }
+}
+
+/// The maximum number of primary keys to combine from lookups on composite primary key columns.
+const MAX_LOOKUP_KEYS: usize = 1000;
+
+/// Uses primary key lookups or secondary indexes for scans whose filter contains a lookup on the
+/// primary key or an indexed column, i.e. an equality or IS NULL check against constants, joined
+/// by AND with any other predicates. Primary key lookups are preferred.
+pub struct IndexLookup<'a, C: Catalog> {
+    catalog: &'a C,
+}
+
+impl<'a, C: Catalog> IndexLookup<'a, C> {
+    pub fn new(catalog: &'a C) -> Self {
+        Self { catalog }
+    }
+
+    /// Converts a lookup value to the given column datatype, if it's NULL or has the same
+    /// datatype, or if it's a number of a different numeric datatype with the exact same value,
+    /// e.g. 1 for a FLOAT or DECIMAL column. Returns None otherwise.
+    fn lookup_value(value: Value, datatype: &DataType) -> Option<Value> {
+        let numeric =
+            |t: &DataType| matches!(t, DataType::Integer | DataType::Float | DataType::Decimal);
+        match value.datatype() {
+            None => Some(value),
+            Some(t) if &t == datatype => Some(value),
+            Some(t) if numeric(&t) && numeric(datatype) => {
+                let converted = expression::cast(value.clone(), datatype).ok()?;
+                (converted.normalize() == value.normalize()).then_some(converted)
+            }
+            Some(_) => None,
+        }
+    }
+
+    /// Wraps a node in a filter for the given CNF vector, if any, otherwise returns the bare node.
+    fn wrap_cnf(&self, node: Node, cnf: Vec<Expression>) -> Node {
+        if let Some(predicate) = Expression::from_cnf_vec(cnf) {
+            Node::Filter { source: Box::new(node), predicate }
+        } else {
+            node
+        }
+    }
+}
+
+impl<C: Catalog> Optimizer for IndexLookup<'_, C> {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(&Ok, &|n| match n {
+            Node::Scan { table, alias, filter: Some(filter), columns: scan_columns } => {
+                let schema = self.catalog.must_read_table(&table)?;
+                let columns = schema.columns;
+                // Convert the filter into conjunctive normal form, and try to convert each
+                // sub-expression into a lookup. If a lookup is found, return a lookup node and
+                // apply the remaining conjunctions as a filter node, if any. Lookup values of a
+                // different datatype than the column would compare differently than in the
+                // filter, so they're converted to the column's datatype, and left to the scan
+                // if they can't be converted exactly.
+                let convert = |values: Vec<Value>, datatype: &DataType| -> Option<Vec<Value>> {
+                    values.into_iter().map(|v| Self::lookup_value(v, datatype)).collect()
+                };
+                let mut cnf = filter.clone().into_cnf_vec();
+                // Lookups on successive primary key columns, starting with the first one, are
+                // combined into (possibly partial) keys, as long as the number of keys is bounded.
+                let mut keys = vec![vec![]];
+                for (ci, column) in schema.primary_key.iter().map(|i| (*i, &columns[*i])) {
+                    let lookup = (0..cnf.len()).find_map(|i| {
+                        let values = cnf[i].as_lookup(ci)?;
+                        Some((i, convert(values, &column.datatype)?))
+                    });
+                    let Some((i, values)) = lookup else { break };
+                    if keys.len() * values.len() > MAX_LOOKUP_KEYS {
+                        break;
+                    }
+                    cnf.remove(i);
+                    keys = keys
+                        .iter()
+                        .flat_map(|key| {
+                            values.iter().map(move |v| key.iter().chain([v]).cloned().collect())
+                        })
+                        .collect();
+                }
+                if keys != [vec![]] {
+                    let node = Node::KeyLookup { table, alias, keys };
+                    return Ok(self.wrap_cnf(node, cnf));
+                }
+                for (ci, column) in columns.iter().enumerate().filter(|(_, c)| c.index) {
+                    for i in 0..cnf.len() {
+                        let lookup = cnf[i].as_lookup(ci);
+                        if let Some(values) = lookup.and_then(|v| convert(v, &column.datatype)) {
+                            cnf.remove(i);
+                            let column = column.name.clone();
+                            let node = Node::IndexLookup { table, alias, column, values };
+                            return Ok(self.wrap_cnf(node, cnf));
+                        }
+                    }
+                }
+                Ok(Node::Scan { table, alias, filter: Some(filter), columns: scan_columns })
+            }
+            n => Ok(n),
+        })
+    }
+}
+
+/// Pushes LIMIT (plus any OFFSET) down through row-preserving nodes into a directly following
+/// ORDER BY, replacing it with a TopN node that only retains the first rows instead of sorting
+/// the entire input.
+pub struct LimitPushdown;
+
+impl Optimizer for LimitPushdown {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(
+            &|n| match n {
+                Node::Limit { source, limit } => {
+                    Ok(Node::Limit { source: Box::new(Self::push(*source, limit)), limit })
+                }
+                n => Ok(n),
+            },
+            &Ok,
+        )
+    }
+}
+
+impl LimitPushdown {
+    /// Pushes a limit of the given number of rows into the node.
+    fn push(node: Node, limit: u64) -> Node {
+        match node {
+            Node::Offset { source, offset } => Node::Offset {
+                source: Box::new(Self::push(*source, limit.saturating_add(offset))),
+                offset,
+            },
+            Node::Projection { source, expressions } => {
+                Node::Projection { source: Box::new(Self::push(*source, limit)), expressions }
+            }
+            Node::Order { source, orders } => Node::TopN { source, orders, limit },
+            node => node,
+        }
+    }
+}
+
+/// Tells table scans which columns their parents use, such that large values stored out-of-row
+/// in other columns aren't read from storage. Columns are tracked down from projections, which
+/// are the only nodes that narrow rows. Nodes that need entire rows, e.g. updates, require all
+/// columns of their source. This must run before HashJoiner, since splitting hash join columns
+/// between the inputs would require the width of the left input.
+pub struct ColumnPruning;
+
+impl Optimizer for ColumnPruning {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        Ok(Self::prune(node, None))
+    }
+}
+
+impl ColumnPruning {
+    /// Prunes the columns of a node, given the columns required by its parent (None for all).
+    fn prune(node: Node, required: Option<BTreeSet<usize>>) -> Node {
+        // Adds the fields referenced by the given expressions to the required columns.
+        let with = |required: Option<BTreeSet<usize>>, exprs: &[&Expression]| {
+            required.map(|mut r| {
+                r.extend(exprs.iter().flat_map(|e| e.fields()));
+                r
+            })
+        };
+        match node {
+            Node::Projection { source, expressions } => {
+                let fields = expressions.iter().flat_map(|(e, _)| e.fields()).collect();
+                Node::Projection { source: Self::prune(*source, Some(fields)).into(), expressions }
+            }
+            Node::Filter { source, predicate } => {
+                let required = with(required, &[&predicate]);
+                Node::Filter { source: Self::prune(*source, required).into(), predicate }
+            }
+            // Rows are compared in full without DISTINCT ON expressions.
+            Node::Distinct { source, on } => {
+                let required = if on.is_empty() {
+                    None
+                } else {
+                    with(required, &on.iter().collect::<Vec<_>>())
+                };
+                Node::Distinct { source: Self::prune(*source, required).into(), on }
+            }
+            Node::Order { source, orders } => {
+                let required = with(required, &orders.iter().map(|(e, _)| e).collect::<Vec<_>>());
+                Node::Order { source: Self::prune(*source, required).into(), orders }
+            }
+            Node::TopN { source, orders, limit } => {
+                let required = with(required, &orders.iter().map(|(e, _)| e).collect::<Vec<_>>());
+                Node::TopN { source: Self::prune(*source, required).into(), orders, limit }
+            }
+            Node::Limit { source, limit } => {
+                Node::Limit { source: Self::prune(*source, required).into(), limit }
+            }
+            Node::Offset { source, offset } => {
+                Node::Offset { source: Self::prune(*source, required).into(), offset }
+            }
+            Node::NestedLoopJoin { left, left_size, right, predicate, r#type } => {
+                let required = with(required, &predicate.iter().collect::<Vec<_>>());
+                let (left_required, right_required) = match required {
+                    Some(r) => {
+                        let (left, right): (BTreeSet<_>, BTreeSet<_>) =
+                            r.into_iter().partition(|f| *f < left_size);
+                        (Some(left), Some(right.into_iter().map(|f| f - left_size).collect()))
+                    }
+                    None => (None, None),
+                };
+                Node::NestedLoopJoin {
+                    left: Self::prune(*left, left_required).into(),
+                    left_size,
+                    right: Self::prune(*right, right_required).into(),
+                    predicate,
+                    r#type,
+                }
+            }
+            Node::Scan { table, alias, filter, columns: _ } => Node::Scan {
+                table,
+                alias,
+                filter,
+                columns: required.map(|r| r.into_iter().collect()),
+            },
+            // The remaining nodes require all columns of their inputs.
+            Node::Aggregation { source, aggregates } => {
+                Node::Aggregation { source: Self::prune(*source, None).into(), aggregates }
+            }
+            Node::Delete { table, source } => {
+                Node::Delete { table, source: Self::prune(*source, None).into() }
+            }
+            Node::Update { table, source, expressions } => {
+                Node::Update { table, source: Self::prune(*source, None).into(), expressions }
+            }
+            Node::Window { source, partition, orders, functions } => Node::Window {
+                source: Self::prune(*source, None).into(),
+                partition,
+                orders,
+                functions,
+            },
+            Node::Recursive { name, anchor, recursive, all, max_iterations } => Node::Recursive {
+                name,
+                anchor: Self::prune(*anchor, None).into(),
+                recursive: Self::prune(*recursive, None).into(),
+                all,
+                max_iterations,
+            },
+            Node::SetOperation { left, right, operator, all } => Node::SetOperation {
+                left: Self::prune(*left, None).into(),
+                right: Self::prune(*right, None).into(),
+                operator,
+                all,
+            },
+            Node::HashJoin { left, left_field, right, right_field, r#type } => Node::HashJoin {
+                left: Self::prune(*left, None).into(),
+                left_field,
+                right: Self::prune(*right, None).into(),
+                right_field,
+                r#type,
+            },
+            node => node,
+        }
+    }
+}
+
+/// Replaces nested loop joins on a single equality between a left and right field with hash
+/// joins. The hash join executor decides at runtime which input to build the hash table from.
+pub struct HashJoiner;
+
+impl Optimizer for HashJoiner {
+    fn optimize(&self, node: Node) -> Result<Node> {
+        node.transform(
+            &|n| match n {
+                Node::NestedLoopJoin {
+                    left,
+                    left_size,
+                    right,
+                    predicate: Some(Expression::Equal(a, b)),
+                    r#type,
+                } => match (*a, *b) {
+                    (Expression::Field(a, a_label), Expression::Field(b, b_label))
+                        if (a < left_size) != (b < left_size) =>
+                    {
+                        let (left_field, right_field) = if a < left_size {
+                            ((a, a_label), (b - left_size, b_label))
+                        } else {
+                            ((b, b_label), (a - left_size, a_label))
+                        };
+                        Ok(Node::HashJoin { left, left_field, right, right_field, r#type })
+                    }
+                    (a, b) => Ok(Node::NestedLoopJoin {
+                        left,
+                        left_size,
+                        right,
+                        predicate: Some(Expression::Equal(a.into(), b.into())),
+                        r#type,
+                    }),
+                },
+                n => Ok(n),
+            },
+            &Ok,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        sql::{
+            engine::{bitcask::KV, Engine},
+            plan::Direction,
+            schema::table::{Column, ReferenceAction, Table},
+            types,
+        },
+        storage::engine::bitcask::Bitcask,
+    };
+
+    type Txn = <KV<Bitcask> as Engine>::Transaction;
+
+    /// Sets up a catalog with the tables a, b and c, each with an INT primary key id and an
+    /// indexed INT column v.
+    fn setup() -> Result<(tempdir::TempDir, Txn)> {
+        let dir = tempdir::TempDir::new("waterdb")?;
+        let mut txn = KV::new(Bitcask::new(dir.path().join("waterdb"))?).begin()?;
+        for name in ["a", "b", "c"] {
+            let column = |name: &str, primary_key| Column {
+                name: name.into(),
+                datatype: DataType::Integer,
+                max_length: None,
+                primary_key,
+                nullable: !primary_key,
+                default: if primary_key { None } else { Some(Value::Null) },
+                unique: primary_key,
+                references: None,
+                on_delete: ReferenceAction::Restrict,
+                on_update: ReferenceAction::Restrict,
+                index: !primary_key,
+                sequence: None,
+            };
+            txn.create_table(Table::new(
+                name.into(),
+                vec![column("id", true), column("v", false)],
+            )?)?;
+        }
+        Ok((dir, txn))
+    }
+
+    fn field(i: usize) -> Expression {
+        Expression::Field(i, None)
+    }
+
+    fn int(i: i64) -> Expression {
+        Expression::Constant(Value::Integer(i))
+    }
+
+    fn boolean(b: bool) -> Expression {
+        Expression::Constant(Value::Boolean(b))
+    }
+
+    fn eq(lhs: Expression, rhs: Expression) -> Expression {
+        Expression::Equal(lhs.into(), rhs.into())
+    }
+
+    fn and(lhs: Expression, rhs: Expression) -> Expression {
+        Expression::And(lhs.into(), rhs.into())
+    }
+
+    fn scan(table: &str, filter: Option<Expression>) -> Node {
+        Node::Scan { table: table.into(), alias: None, filter, columns: None }
+    }
+
+    fn filter(source: Node, predicate: Expression) -> Node {
+        Node::Filter { source: source.into(), predicate }
+    }
+
+    fn join(
+        left: Node,
+        left_size: usize,
+        right: Node,
+        predicate: Option<Expression>,
+        r#type: JoinType,
+    ) -> Node {
+        Node::NestedLoopJoin {
+            left: left.into(),
+            left_size,
+            right: right.into(),
+            predicate,
+            r#type,
+        }
+    }
+
+    #[test]
+    fn constant_folder() -> Result<()> {
+        let add = Expression::Add(int(1).into(), int(2).into());
+        assert_eq!(
+            ConstantFolder.optimize(filter(scan("a", None), eq(field(0), add)))?,
+            filter(scan("a", None), eq(field(0), int(3))),
+        );
+
+        // AND and OR with a constant operand are simplified.
+        let gt = Expression::GreaterThan(field(1).into(), int(1).into());
+        assert_eq!(
+            ConstantFolder.optimize(filter(scan("a", None), and(gt.clone(), boolean(false))))?,
+            filter(scan("a", None), boolean(false)),
+        );
+        assert_eq!(
+            ConstantFolder.optimize(filter(scan("a", None), and(boolean(true), gt.clone())))?,
+            filter(scan("a", None), gt.clone()),
+        );
+        let or = Expression::Or(gt.into(), boolean(true).into());
+        assert_eq!(
+            ConstantFolder.optimize(filter(scan("a", None), or))?,
+            filter(scan("a", None), boolean(true)),
+        );
+
+        // Expressions that fail to evaluate are left alone.
+        let divide = Expression::Divide(int(1).into(), int(0).into());
+        assert_eq!(
+            ConstantFolder.optimize(filter(scan("a", None), eq(field(0), divide.clone())))?,
+            filter(scan("a", None), eq(field(0), divide)),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn filter_pushdown() -> Result<()> {
+        // Filters are merged into scans.
+        assert_eq!(
+            FilterPushdown
+                .optimize(filter(scan("a", Some(eq(field(0), int(1)))), eq(field(1), int(2))))?,
+            scan("a", Some(and(eq(field(0), int(1)), eq(field(1), int(2))))),
+        );
+
+        // Filters and predicates of inner joins are pushed into the inputs where possible, with
+        // right-hand fields shifted.
+        let node = filter(
+            join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(eq(field(0), field(2))),
+                JoinType::Inner,
+            ),
+            and(eq(field(1), int(1)), eq(field(3), int(2))),
+        );
+        assert_eq!(
+            FilterPushdown.optimize(node)?,
+            join(
+                scan("a", Some(eq(field(1), int(1)))),
+                2,
+                scan("b", Some(eq(field(1), int(2)))),
+                Some(eq(field(0), field(2))),
+                JoinType::Inner,
+            ),
+        );
+
+        // For left joins, only left-hand filters and right-hand join predicates are pushed.
+        let node = filter(
+            join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(and(eq(field(0), field(2)), eq(field(3), int(3)))),
+                JoinType::Left,
+            ),
+            and(eq(field(1), int(1)), eq(field(3), int(2))),
+        );
+        assert_eq!(
+            FilterPushdown.optimize(node)?,
+            filter(
+                join(
+                    scan("a", Some(eq(field(1), int(1)))),
+                    2,
+                    scan("b", Some(eq(field(1), int(3)))),
+                    Some(eq(field(0), field(2))),
+                    JoinType::Left,
+                ),
+                eq(field(3), int(2)),
+            ),
+        );
+
+        // Nothing is pushed through full joins.
+        let node = filter(
+            join(scan("a", None), 2, scan("b", None), Some(eq(field(3), int(3))), JoinType::Full),
+            eq(field(1), int(1)),
+        );
+        assert_eq!(FilterPushdown.optimize(node.clone())?, node);
+
+        // Filters are pushed into the left input of anti joins, but left-hand join predicates
+        // are not, since unmatched left-hand rows are emitted.
+        let node = filter(
+            join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(and(eq(field(0), field(2)), eq(field(1), int(3)))),
+                JoinType::Anti,
+            ),
+            eq(field(1), int(1)),
+        );
+        assert_eq!(
+            FilterPushdown.optimize(node)?,
+            join(
+                scan("a", Some(eq(field(1), int(1)))),
+                2,
+                scan("b", None),
+                Some(and(eq(field(0), field(2)), eq(field(1), int(3)))),
+                JoinType::Anti,
+            ),
+        );
+
+        // Filter conjuncts with subqueries are kept in place.
+        let exists = Expression::Exists(scan("b", None).into());
+        let node = filter(scan("a", None), and(eq(field(0), int(1)), exists.clone()));
+        assert_eq!(
+            FilterPushdown.optimize(node)?,
+            filter(scan("a", Some(eq(field(0), int(1)))), exists),
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn noop_cleaner() -> Result<()> {
+        let (_dir, txn) = setup()?;
+        let cleaner = NoopCleaner::new(&txn);
+        assert_eq!(cleaner.optimize(filter(scan("a", None), boolean(true)))?, scan("a", None));
+        assert_eq!(cleaner.optimize(scan("a", Some(boolean(true))))?, scan("a", None));
+        assert_eq!(
+            cleaner.optimize(join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(boolean(true)),
+                JoinType::Inner
+            ))?,
+            join(scan("a", None), 2, scan("b", None), None, JoinType::Inner),
+        );
+
+        // Scans and filters that are always false or NULL are replaced by Nothing, keeping their
+        // columns, also for the NULL-padded side of an outer join.
+        let nothing = |names: &[&str]| Node::Nothing {
+            columns: names.iter().map(|n| types::Column { name: Some(n.to_string()) }).collect(),
+        };
+        assert_eq!(cleaner.optimize(scan("a", Some(boolean(false))))?, nothing(&["id", "v"]));
+        assert_eq!(
+            cleaner.optimize(scan("a", Some(Expression::Constant(Value::Null))))?,
+            nothing(&["id", "v"]),
+        );
+        assert_eq!(
+            cleaner.optimize(join(
+                scan("a", None),
+                2,
+                scan("b", Some(boolean(false))),
+                Some(eq(field(0), field(2))),
+                JoinType::Left
+            ))?,
+            join(
+                scan("a", None),
+                2,
+                nothing(&["id", "v"]),
+                Some(eq(field(0), field(2))),
+                JoinType::Left
+            ),
+        );
+        let node = filter(
+            Node::Projection {
+                source: join(scan("a", None), 2, scan("b", None), None, JoinType::Inner).into(),
+                expressions: vec![(field(3), None), (field(0), Some("x".into()))],
+            },
+            boolean(false),
+        );
+        assert_eq!(cleaner.optimize(node)?, nothing(&["v", "x"]));
+        assert_eq!(
+            cleaner.optimize(filter(Node::Values { rows: vec![vec![]] }, boolean(false)))?,
+            Node::Nothing { columns: vec![] },
+        );
+
+        // Filters whose source columns aren't known are left alone.
+        let node = filter(
+            Node::Aggregation { source: scan("a", None).into(), aggregates: vec![] },
+            boolean(false),
+        );
+        assert_eq!(cleaner.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn join_reorder() -> Result<()> {
+        let (_dir, txn) = setup()?;
+        let reorder = JoinReorder::new(&txn);
+
+        // a.id = c.id AND b.id = c.id joins c before b, to avoid the a × b cross product.
+        let node = join(
+            join(scan("a", None), 2, scan("b", None), None, JoinType::Inner),
+            4,
+            scan("c", None),
+            Some(and(eq(field(0), field(4)), eq(field(2), field(4)))),
+            JoinType::Inner,
+        );
+        assert_eq!(
+            reorder.optimize(node)?,
+            Node::Projection {
+                source: join(
+                    join(
+                        scan("a", None),
+                        2,
+                        scan("c", None),
+                        Some(eq(field(0), field(2))),
+                        JoinType::Inner
+                    ),
+                    4,
+                    scan("b", None),
+                    Some(eq(field(4), field(2))),
+                    JoinType::Inner,
+                )
+                .into(),
+                expressions: [0, 1, 4, 5, 2, 3].into_iter().map(|i| (field(i), None)).collect(),
+            },
+        );
+
+        // Chains that are already connected, and outer joins, are left alone.
+        let node = join(
+            join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(eq(field(0), field(2))),
+                JoinType::Inner,
+            ),
+            4,
+            scan("c", None),
+            Some(eq(field(2), field(4))),
+            JoinType::Inner,
+        );
+        assert_eq!(reorder.optimize(node.clone())?, node);
+        let node = join(
+            join(scan("a", None), 2, scan("b", None), None, JoinType::Left),
+            4,
+            scan("c", None),
+            Some(eq(field(0), field(4))),
+            JoinType::Left,
+        );
+        assert_eq!(reorder.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn index_lookup() -> Result<()> {
+        let (_dir, txn) = setup()?;
+        let lookup = IndexLookup::new(&txn);
+
+        assert_eq!(
+            lookup.optimize(scan("a", Some(and(eq(field(1), int(2)), eq(field(0), int(1))))))?,
+            filter(
+                Node::KeyLookup {
+                    table: "a".into(),
+                    alias: None,
+                    keys: vec![vec![Value::Integer(1)]]
+                },
+                eq(field(1), int(2)),
+            ),
+        );
+        assert_eq!(
+            lookup.optimize(scan("a", Some(eq(int(2), field(1)))))?,
+            Node::IndexLookup {
+                table: "a".into(),
+                alias: None,
+                column: "v".into(),
+                values: vec![Value::Integer(2)],
+            },
+        );
+        let node = scan("a", Some(Expression::GreaterThan(field(0).into(), int(1).into())));
+        assert_eq!(lookup.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn index_lookup_conversion() -> Result<()> {
+        let (_dir, mut txn) = setup()?;
+        let id = Column {
+            name: "id".into(),
+            datatype: DataType::Decimal,
+            max_length: None,
+            primary_key: true,
+            nullable: false,
+            default: None,
+            unique: true,
+            references: None,
+            on_delete: ReferenceAction::Restrict,
+            on_update: ReferenceAction::Restrict,
+            index: false,
+            sequence: None,
+        };
+        let f = Column {
+            name: "f".into(),
+            datatype: DataType::Float,
+            primary_key: false,
+            nullable: true,
+            default: Some(Value::Null),
+            unique: false,
+            index: true,
+            ..id.clone()
+        };
+        txn.create_table(Table::new("d".into(), vec![id, f])?)?;
+        let lookup = IndexLookup::new(&txn);
+        let float = |f: f64| Expression::Constant(Value::Float(f));
+
+        // Lookup values are converted to the column's numeric type when the value is exact.
+        assert_eq!(
+            lookup.optimize(scan("d", Some(eq(field(0), int(1)))))?,
+            Node::KeyLookup {
+                table: "d".into(),
+                alias: None,
+                keys: vec![vec![Value::Decimal(1.into())]],
+            },
+        );
+        assert_eq!(
+            lookup.optimize(scan("d", Some(eq(field(1), int(2)))))?,
+            Node::IndexLookup {
+                table: "d".into(),
+                alias: None,
+                column: "f".into(),
+                values: vec![Value::Float(2.0)],
+            },
+        );
+        assert_eq!(
+            lookup.optimize(scan("a", Some(eq(field(0), float(3.0)))))?,
+            Node::KeyLookup { table: "a".into(), alias: None, keys: vec![vec![Value::Integer(3)]] },
+        );
+
+        // Values that would change are left to the scan.
+        let node = scan("a", Some(eq(field(0), float(1.5))));
+        assert_eq!(lookup.optimize(node.clone())?, node);
+        let node = scan("d", Some(eq(field(1), int(i64::MAX))));
+        assert_eq!(lookup.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn column_pruning() -> Result<()> {
+        let pruned = |table: &str, columns: Vec<usize>| Node::Scan {
+            table: table.into(),
+            alias: None,
+            filter: None,
+            columns: Some(columns),
+        };
+
+        // Scans read the columns used by projections, filters and join predicates, with
+        // right-hand fields shifted.
+        let node = Node::Projection {
+            source: filter(
+                join(
+                    scan("a", None),
+                    2,
+                    scan("b", None),
+                    Some(eq(field(0), field(2))),
+                    JoinType::Inner,
+                ),
+                eq(field(3), int(1)),
+            )
+            .into(),
+            expressions: vec![(field(0), None)],
+        };
+        assert_eq!(
+            ColumnPruning.optimize(node)?,
+            Node::Projection {
+                source: filter(
+                    join(
+                        pruned("a", vec![0]),
+                        2,
+                        pruned("b", vec![0, 1]),
+                        Some(eq(field(0), field(2))),
+                        JoinType::Inner
+                    ),
+                    eq(field(3), int(1)),
+                )
+                .into(),
+                expressions: vec![(field(0), None)],
+            },
+        );
+
+        // Without a projection, or below updates, all columns are read.
+        assert_eq!(ColumnPruning.optimize(scan("a", None))?, scan("a", None));
+        let node = Node::Update {
+            table: "a".into(),
+            source: scan("a", None).into(),
+            expressions: vec![(1, None, int(1))],
+        };
+        assert_eq!(ColumnPruning.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn hash_joiner() -> Result<()> {
+        assert_eq!(
+            HashJoiner.optimize(join(
+                scan("a", None),
+                2,
+                scan("b", None),
+                Some(eq(field(3), field(1))),
+                JoinType::Left,
+            ))?,
+            Node::HashJoin {
+                left: scan("a", None).into(),
+                left_field: (1, None),
+                right: scan("b", None).into(),
+                right_field: (1, None),
+                r#type: JoinType::Left,
+            },
+        );
+        let node = join(
+            scan("a", None),
+            2,
+            scan("b", None),
+            Some(eq(field(0), field(1))),
+            JoinType::Inner,
+        );
+        assert_eq!(HashJoiner.optimize(node.clone())?, node);
+        Ok(())
+    }
+
+    #[test]
+    fn limit_pushdown() -> Result<()> {
+        let orders = vec![(field(0), Direction::Descending)];
+        let node = Node::Limit {
+            source: Node::Offset {
+                source: Node::Projection {
+                    source: Node::Order { source: scan("a", None).into(), orders: orders.clone() }
+                        .into(),
+                    expressions: vec![(field(1), None)],
+                }
+                .into(),
+                offset: 3,
+            }
+            .into(),
+            limit: 2,
+        };
+        assert_eq!(
+            LimitPushdown.optimize(node)?,
+            Node::Limit {
+                source: Node::Offset {
+                    source: Node::Projection {
+                        source: Node::TopN { source: scan("a", None).into(), orders, limit: 5 }
+                            .into(),
+                        expressions: vec![(field(1), None)],
+                    }
+                    .into(),
+                    offset: 3,
+                }
+                .into(),
+                limit: 2,
+            },
+        );
+
+        // Limits aren't pushed through filters.
+        let node = Node::Limit {
+            source: filter(
+                Node::Order {
+                    source: scan("a", None).into(),
+                    orders: vec![(field(0), Direction::Ascending)],
+                },
+                boolean(true),
+            )
+            .into(),
+            limit: 2,
+        };
+        assert_eq!(LimitPushdown.optimize(node.clone())?, node);
+        Ok(())
+    }
+}
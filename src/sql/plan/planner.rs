@@ -1,13 +1,19 @@
 use crate::error::{Error, Result};
-use crate::sql::parser::ast;
+use crate::sql::parser::{ast, Parser};
 use crate::sql::schema::catalog::Catalog;
+use crate::sql::schema::sequence::Sequence;
 use crate::sql::schema::table::{Table, Column};
-use crate::sql::types::Value;
-use crate::sql::types::expression::Expression;
+use crate::sql::schema::view::View;
+use crate::sql::types::expression::{Expression, Function};
+use crate::sql::types::{self, DataType, Value};
 
 use std::collections::{HashMap, HashSet};
+use std::mem::replace;
 
-use super::{Plan, Node};
+use super::{
+    Aggregate, Alteration, Direction, Frame, FrameBound, JoinType, Node, Plan, SetOperator,
+    WindowFunction,
+};
 
 /// A query plan builder.
 pub struct Planner<'a, C: Catalog> {
@@ -36,51 +42,95 @@ impl<'a, C: Catalog> Planner<'a, C> {
                 )))
             }
 
-            ast::Statement::Explain(_) => {
+            ast::Statement::Explain { .. } => {
                 return Err(Error::Internal("Unexpected explain statement".into()))
             }
 
             // DDL statements (schema changes).
-            ast::Statement::CreateTable { name, columns } => Node::CreateTable {
-                schema: Table::new(
-                    name,
-                    columns
-                        .into_iter()
-                        .map(|c| {
-                            let nullable = c.nullable.unwrap_or(!c.primary_key);
-                            let default = match c.default {
-                                Some(expr) => Some(self.evaluate_constant(expr)?),
-                                None if nullable => Some(Value::Null),
-                                None => None,
-                            };
-                            Ok(Column {
-                                name: c.name,
-                                datatype: c.datatype,
-                                primary_key: c.primary_key,
-                                nullable,
-                                default,
-                                index: c.index && !c.primary_key,
-                                unique: c.unique || c.primary_key,
-                                references: c.references,
-                            })
-                        })
-                        .collect::<Result<_>>()?,
-                )?,
-            },
+            ast::Statement::CreateTable { name, mut columns, primary_key, checks } => {
+                let column_keys = columns.iter().filter(|c| c.primary_key).count();
+                if column_keys > 1 || column_keys > 0 && !primary_key.is_empty() {
+                    return Err(Error::Value(format!("Multiple primary keys in table {}", name)));
+                }
+                // Composite primary key columns are only unique as a whole, and keep their
+                // declared order as the key order, e.g. for prefix lookups.
+                let mut key_order = Vec::new();
+                for key in &primary_key {
+                    let i = columns.iter().position(|c| &c.name == key).ok_or_else(|| {
+                        Error::Value(format!("Unknown primary key column {}", key))
+                    })?;
+                    if key_order.contains(&i) {
+                        return Err(Error::Value(format!("Duplicate primary key column {}", key)));
+                    }
+                    key_order.push(i);
+                    columns[i].primary_key = true;
+                }
+                let unique: Vec<bool> = columns.iter().map(|c| c.unique).collect();
+                // Column checks are stored as table checks, since they're evaluated per row.
+                let checks: Vec<_> = columns
+                    .iter_mut()
+                    .flat_map(|c| std::mem::take(&mut c.checks))
+                    .chain(checks)
+                    .collect();
+                let mut columns = columns
+                    .into_iter()
+                    .map(|c| self.build_column(&name, c))
+                    .collect::<Result<Vec<_>>>()?;
+                if primary_key.len() > 1 {
+                    for (column, unique) in columns.iter_mut().zip(unique) {
+                        column.unique = unique;
+                    }
+                }
+                let mut schema = Table::new(name, columns)?;
+                if !key_order.is_empty() {
+                    schema.primary_key = key_order;
+                }
+                schema.checks = self.build_checks(&schema, checks)?;
+                Node::CreateTable { schema }
+            }
 
             ast::Statement::DropTable(table) => Node::DropTable { table },
 
+            ast::Statement::CreateSequence { name, start, increment } => {
+                let mut schema = Sequence::new(name);
+                schema.start = start.unwrap_or(schema.start);
+                schema.increment = increment.unwrap_or(schema.increment);
+                Node::CreateSequence { schema }
+            }
+            ast::Statement::DropSequence(sequence) => Node::DropSequence { sequence },
+
+            ast::Statement::CreateView { name, query } => {
+                // Build the query once to validate it, but store it as SQL text such that it is
+                // planned against the current schema whenever the view is used.
+                self.build_query(&mut Scope::new(), Parser::new(&query).parse()?)?;
+                Node::CreateView { schema: View::new(name, query) }
+            }
+            ast::Statement::DropView(view) => Node::DropView { view },
+
+            ast::Statement::AddColumn { table, mut column } => {
+                let checks = std::mem::take(&mut column.checks);
+                let mut schema = self.catalog.must_read_table(&table)?;
+                schema.columns.push(self.build_column(&table, column)?);
+                let checks = self.build_checks(&schema, checks)?;
+                let column = schema.columns.pop().expect("column was just added");
+                Node::AlterTable { table, alteration: Alteration::AddColumn(column, checks) }
+            }
+            ast::Statement::DropColumn { table, column } => {
+                Node::AlterTable { table, alteration: Alteration::DropColumn(column) }
+            }
+            ast::Statement::RenameColumn { table, column, name } => {
+                Node::AlterTable { table, alteration: Alteration::RenameColumn { column, name } }
+            }
+            ast::Statement::RenameTable { table, name } => {
+                Node::AlterTable { table, alteration: Alteration::RenameTable(name) }
+            }
+
             // DML statements (mutations).
             ast::Statement::Delete { table, r#where } => {
                 let scope = &mut Scope::from_table(self.catalog.must_read_table(&table)?)?;
-                Node::Delete {
-                    table: table.clone(),
-                    source: Box::new(Node::Scan {
-                        table,
-                        alias: None,
-                        filter: r#where.map(|e| self.build_expression(scope, e)).transpose()?,
-                    }),
-                }
+                let scan =
+                    Node::Scan { table: table.clone(), alias: None, filter: None, columns: None };
+                Node::Delete { table, source: Box::new(self.build_where(scope, scan, r#where)?) }
             }
 
             ast::Statement::Insert { table, columns, values } => Node::Insert {
@@ -99,13 +149,11 @@ impl<'a, C: Catalog> Planner<'a, C> {
 
             ast::Statement::Update { table, set, r#where } => {
                 let scope = &mut Scope::from_table(self.catalog.must_read_table(&table)?)?;
+                let scan =
+                    Node::Scan { table: table.clone(), alias: None, filter: None, columns: None };
                 Node::Update {
-                    table: table.clone(),
-                    source: Box::new(Node::Scan {
-                        table,
-                        alias: None,
-                        filter: r#where.map(|e| self.build_expression(scope, e)).transpose()?,
-                    }),
+                    table,
+                    source: Box::new(self.build_where(scope, scan, r#where)?),
                     expressions: set
                         .into_iter()
                         .map(|(c, e)| {
@@ -120,92 +168,1007 @@ impl<'a, C: Catalog> Planner<'a, C> {
             }
 
             // Queries.
-            ast::Statement::Select {
-                mut select,
-                from,
-                r#where,
-                mut having,
-                mut order,
-                ..
-            } => {
-                let scope = &mut Scope::new();
-
-                // Build FROM clause.
-                let mut node = if !from.is_empty() {
-                    self.build_from_clause(scope, from)?
-                } else if select.is_empty() {
-                    return Err(Error::Value("Can't select * without a table".into()));
-                } else {
-                    Node::Nothing
-                };
+            query @ (ast::Statement::Select { .. } | ast::Statement::SetOperation { .. }) => {
+                self.build_query(&mut Scope::new(), query)?
+            }
+        })
+    }
 
-                // Build WHERE clause.
-                if let Some(expr) = r#where {
-                    node = Node::Filter {
-                        source: Box::new(node),
-                        predicate: self.build_expression(scope, expr)?,
-                    };
-                };
+    /// Builds a plan node for a query, i.e. a SELECT statement or a compound query. The scope
+    /// contains the output columns afterwards.
+    fn build_query(&self, scope: &mut Scope, statement: ast::Statement) -> Result<Node> {
+        match statement {
+            ast::Statement::SetOperation { .. } => self.build_set_operation(scope, statement),
+            statement => self.build_select(scope, statement),
+        }
+    }
 
-                // Build SELECT clause.
-                let mut hidden = 0;
-                if !select.is_empty() {
-                    // Inject hidden SELECT columns for fields and aggregates used in ORDER BY and
-                    // HAVING expressions but not present in existing SELECT output. These will be
-                    // removed again by a later projection.
-                    if let Some(ref mut expr) = having {
-                        hidden += self.inject_hidden(expr, &mut select)?;
-                    }
-                    for (expr, _) in order.iter_mut() {
-                        hidden += self.inject_hidden(expr, &mut select)?;
-                    }
+    /// Builds a plan node for a SELECT statement. The scope contains the output columns
+    /// afterwards.
+    fn build_select(&self, scope: &mut Scope, statement: ast::Statement) -> Result<Node> {
+        let ast::Statement::Select {
+            with,
+            mut distinct,
+            mut select,
+            from,
+            r#where,
+            group_by,
+            mut having,
+            mut order,
+            offset,
+            limit,
+        } = statement
+        else {
+            return Err(Error::Internal(format!("Expected SELECT statement, got {:?}", statement)));
+        };
 
-                    // Build the remaining non-aggregate projection.
-                    let expressions: Vec<(Expression, Option<String>)> = select
-                        .into_iter()
-                        .map(|(e, l)| Ok((self.build_expression(scope, e)?, l)))
-                        .collect::<Result<_>>()?;
-                    scope.project(&expressions)?;
-                    node = Node::Projection { source: Box::new(node), expressions };
-                };
+        // Add WITH clause common table expressions to the scope.
+        self.add_ctes(scope, with)?;
 
-                // Remove any hidden columns.
-                if hidden > 0 {
-                    node = Node::Projection {
-                        source: Box::new(node),
-                        expressions: (0..(scope.len() - hidden))
-                            .map(|i| (Expression::Field(i, None), None))
-                            .collect(),
-                    }
+        // Build FROM clause.
+        let mut node = if !from.is_empty() {
+            self.build_from_clause(scope, from)?
+        } else if select.is_empty() {
+            return Err(Error::Value("Can't select * without a table".into()));
+        } else {
+            Node::Values { rows: vec![Vec::new()] }
+        };
+
+        // Build WHERE clause.
+        node = self.build_where(scope, node, r#where)?;
+
+        // Build SELECT clause.
+        let mut hidden = 0;
+        if !select.is_empty() {
+            // Inject hidden SELECT columns for fields and aggregates used in ORDER BY and
+            // HAVING expressions but not present in existing SELECT output. These will be
+            // removed again by a later projection.
+            if let Some(ref mut expr) = having {
+                hidden += self.inject_hidden(expr, &mut select)?;
+            }
+            for (expr, _) in order.iter_mut() {
+                hidden += self.inject_hidden(expr, &mut select)?;
+            }
+            for expr in distinct.iter_mut().flatten() {
+                hidden += self.inject_hidden(expr, &mut select)?;
+            }
+
+            // Extract any aggregate functions and GROUP BY expressions, replacing them with
+            // Column placeholders. Aggregations are handled by evaluating group expressions
+            // and aggregate function arguments in a pre-projection, passing the results
+            // to an aggregation node, and then evaluating the final SELECT expressions
+            // in the post-projection. For example:
+            //
+            // SELECT (MAX(rating * 100) - MIN(rating * 100)) / 100
+            // FROM movies
+            // GROUP BY released - 2000
+            //
+            // Results in the following nodes:
+            //
+            // - Projection: rating * 100, rating * 100, released - 2000
+            // - Aggregation: max(#0), min(#1) group by #2
+            // - Projection: (#0 - #1) / 100
+            let aggregates = self.extract_aggregates(&mut select)?;
+            let groups = self.extract_groups(&mut select, group_by, aggregates.len())?;
+            if !aggregates.is_empty() || !groups.is_empty() {
+                node = self.build_aggregation(scope, node, groups, aggregates)?;
+            }
+
+            // Window functions are computed after aggregation, and replaced with Column
+            // placeholders for their results. They only see the rows that pass the HAVING
+            // clause, so it is applied first, in terms of the aggregation output.
+            if select.iter().any(|(e, _)| Self::has_window(e)) {
+                if let Some(mut expr) = having.take() {
+                    expr.transform_mut(&mut Ok, &mut |e| match e {
+                        ast::Expression::Column(i) => Ok(select[i].0.clone()),
+                        e => Ok(e),
+                    })?;
+                    let predicate = self.build_expression(scope, expr)?;
+                    node = Node::Filter { source: Box::new(node), predicate };
+                }
+                for calls in self.extract_windows(&mut select, scope.len())? {
+                    node = self.build_window(scope, node, calls)?;
                 }
+            }
 
-                node
+            // Build the remaining non-aggregate projection.
+            let expressions: Vec<(Expression, Option<String>)> = select
+                .into_iter()
+                .map(|(e, l)| Ok((self.build_expression(scope, e)?, l)))
+                .collect::<Result<_>>()?;
+            scope.project(&expressions)?;
+            node = Node::Projection { source: Box::new(node), expressions };
+        } else if !group_by.is_empty() || having.is_some() {
+            return Err(Error::Value("Can't use GROUP BY or HAVING with SELECT *".into()));
+        };
+
+        // Build HAVING clause.
+        if let Some(expr) = having {
+            node = Node::Filter {
+                source: Box::new(node),
+                predicate: self.build_expression(scope, expr)?,
+            };
+        };
+
+        // Build ORDER clause.
+        node = self.build_order(scope, node, order)?;
+
+        // Build DISTINCT clause. This comes after ORDER BY, such that DISTINCT ON keeps the first
+        // row of each group in sort order. Plain DISTINCT only compares the visible columns.
+        if let Some(on) = distinct {
+            let on = if on.is_empty() && hidden > 0 {
+                (0..(scope.len() - hidden)).map(|i| Expression::Field(i, None)).collect()
+            } else {
+                on.into_iter()
+                    .map(|e| self.build_expression(scope, e))
+                    .collect::<Result<Vec<_>>>()?
+            };
+            // The distinct executor can't execute subqueries.
+            if on.iter().any(|e| e.has_subqueries()) {
+                return Err(Error::Value("Subqueries are not supported in DISTINCT ON".into()));
             }
-        })
+            node = Node::Distinct { source: Box::new(node), on };
+        }
+
+        // Remove any hidden columns.
+        if hidden > 0 {
+            let expressions: Vec<_> =
+                (0..(scope.len() - hidden)).map(|i| (Expression::Field(i, None), None)).collect();
+            scope.project(&expressions)?;
+            node = Node::Projection { source: Box::new(node), expressions }
+        }
+
+        // Build OFFSET and LIMIT clauses.
+        self.build_offset_limit(node, offset, limit)
+    }
+
+    /// Builds a plan node for a compound query. Both operands are planned in separate scopes,
+    /// and must have the same number of columns with compatible types. The output columns take
+    /// their names from the left operand, and ORDER BY can only refer to these.
+    fn build_set_operation(&self, scope: &mut Scope, statement: ast::Statement) -> Result<Node> {
+        let ast::Statement::SetOperation {
+            with,
+            operator,
+            all,
+            left,
+            right,
+            order,
+            offset,
+            limit,
+        } = statement
+        else {
+            return Err(Error::Internal(format!("Expected set operation, got {:?}", statement)));
+        };
+        self.add_ctes(scope, with)?;
+
+        // The operands can reference the enclosing query of a subquery.
+        let mut left_scope = scope.derived();
+        left_scope.outer = scope.outer.clone();
+        let left = self.build_query(&mut left_scope, *left)?;
+        let mut right_scope = scope.derived();
+        right_scope.outer = scope.outer.clone();
+        let right = self.build_query(&mut right_scope, *right)?;
+
+        let operator = match operator {
+            ast::SetOperator::Union => SetOperator::Union,
+            ast::SetOperator::Intersect => SetOperator::Intersect,
+            ast::SetOperator::Except => SetOperator::Except,
+        };
+        let name = operator.to_string().to_uppercase();
+        if left_scope.len() != right_scope.len() {
+            return Err(Error::Value(format!(
+                "{} queries must have the same number of columns, found {} and {}",
+                name,
+                left_scope.len(),
+                right_scope.len()
+            )));
+        }
+        // Numeric columns of different types are cast to a common type, since e.g. 1 and 1.0
+        // are different rows. Types are only known for table columns, and for constants and
+        // some expressions in the operands' projections.
+        let numeric =
+            |t: &DataType| matches!(t, DataType::Integer | DataType::Float | DataType::Decimal);
+        let datatype = |scope: &Scope, datatypes: &[Option<DataType>], i: usize| {
+            scope.get_datatype(i).or_else(|| datatypes.get(i).cloned().flatten())
+        };
+        let (left_types, right_types) =
+            (Self::projected_datatypes(&left), Self::projected_datatypes(&right));
+        let (mut left_casts, mut right_casts) = (Vec::new(), Vec::new());
+        for i in 0..left_scope.len() {
+            let (Some(l), Some(r)) =
+                (datatype(&left_scope, &left_types, i), datatype(&right_scope, &right_types, i))
+            else {
+                continue;
+            };
+            if l == r {
+                continue;
+            } else if !numeric(&l) || !numeric(&r) {
+                return Err(Error::Value(format!(
+                    "{} column {} has mismatched types {} and {}",
+                    name,
+                    i + 1,
+                    l,
+                    r
+                )));
+            }
+            let common = if l == DataType::Float || r == DataType::Float {
+                DataType::Float
+            } else {
+                DataType::Decimal
+            };
+            if l != common {
+                left_casts.push((i, common.clone()));
+            }
+            if r != common {
+                right_casts.push((i, common));
+            }
+        }
+        let left = Self::build_casts(left, &left_scope, left_casts)?;
+        let right = Self::build_casts(right, &right_scope, right_casts)?;
+        for (_, label) in left_scope.columns {
+            scope.add_column(None, label);
+        }
+
+        let node =
+            Node::SetOperation { left: Box::new(left), right: Box::new(right), operator, all };
+        let node = self.build_order(scope, node, order)?;
+        self.build_offset_limit(node, offset, limit)
+    }
+
+    /// Returns the data types of a query's output columns that are given by its projection, where
+    /// known: constants, casts and boolean expressions. Other columns, and queries without a
+    /// projection, have unknown types.
+    fn projected_datatypes(node: &Node) -> Vec<Option<DataType>> {
+        match node {
+            Node::Projection { source, expressions } => {
+                let source = Self::projected_datatypes(source);
+                expressions
+                    .iter()
+                    .map(|(expr, _)| match expr {
+                        Expression::Constant(value) => value.datatype(),
+                        Expression::Field(i, _) => source.get(*i).cloned().flatten(),
+                        Expression::Function(Function::Cast(datatype), _) => Some(datatype.clone()),
+                        Expression::And(..)
+                        | Expression::Not(_)
+                        | Expression::Or(..)
+                        | Expression::Equal(..)
+                        | Expression::GreaterThan(..)
+                        | Expression::ILike(..)
+                        | Expression::IsNull(_)
+                        | Expression::LessThan(..)
+                        | Expression::Like(..)
+                        | Expression::Regex(..)
+                        | Expression::Exists(_)
+                        | Expression::InList(..)
+                        | Expression::InSubquery(..) => Some(DataType::Boolean),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            Node::Distinct { source, .. }
+            | Node::Filter { source, .. }
+            | Node::Limit { source, .. }
+            | Node::Offset { source, .. }
+            | Node::Order { source, .. }
+            | Node::TopN { source, .. } => Self::projected_datatypes(source),
+            Node::SetOperation { left, right, .. } => {
+                let (left, right) =
+                    (Self::projected_datatypes(left), Self::projected_datatypes(right));
+                (0..left.len().max(right.len()))
+                    .map(|i| {
+                        left.get(i).cloned().flatten().or_else(|| right.get(i).cloned().flatten())
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Casts the given columns of a query to the given data types, if any, with a projection.
+    fn build_casts(source: Node, scope: &Scope, casts: Vec<(usize, DataType)>) -> Result<Node> {
+        if casts.is_empty() {
+            return Ok(source);
+        }
+        let expressions = (0..scope.len())
+            .map(|i| {
+                let mut expr = Expression::Field(i, scope.get_label(i)?);
+                if let Some((_, datatype)) = casts.iter().find(|(c, _)| *c == i) {
+                    expr = Expression::Function(Function::Cast(datatype.clone()), vec![expr]);
+                }
+                Ok((expr, None))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Node::Projection { source: Box::new(source), expressions })
+    }
+
+    /// Adds the common table expressions of a WITH clause to the scope. Each can reference the
+    /// ones before it.
+    fn add_ctes(&self, scope: &mut Scope, with: Vec<ast::CommonTableExpression>) -> Result<()> {
+        let mut names = HashSet::new();
+        for cte in with {
+            if !names.insert(cte.name.clone()) {
+                return Err(Error::Value(format!("Duplicate common table expression {}", cte.name)));
+            }
+            let ctes = scope.ctes.clone();
+            scope.ctes.insert(cte.name.clone(), Cte::Query(cte, ctes));
+        }
+        Ok(())
+    }
+
+    /// Builds an ORDER BY clause on top of the given source node, if any.
+    fn build_order(
+        &self,
+        scope: &mut Scope,
+        source: Node,
+        order: Vec<(ast::Expression, ast::Order)>,
+    ) -> Result<Node> {
+        if order.is_empty() {
+            return Ok(source);
+        }
+        Ok(Node::Order { source: Box::new(source), orders: self.build_orders(scope, order)? })
+    }
+
+    /// Builds ORDER BY expressions and directions.
+    fn build_orders(
+        &self,
+        scope: &mut Scope,
+        order: Vec<(ast::Expression, ast::Order)>,
+    ) -> Result<Vec<(Expression, Direction)>> {
+        order
+            .into_iter()
+            .map(|(e, o)| {
+                let expr = self.build_expression(scope, e)?;
+                // The order executor can't execute subqueries.
+                if expr.has_subqueries() {
+                    return Err(Error::Value("Subqueries are not supported in ORDER BY".into()));
+                }
+                Ok((
+                    expr,
+                    match o {
+                        ast::Order::Ascending => Direction::Ascending,
+                        ast::Order::Descending => Direction::Descending,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Builds OFFSET and LIMIT clauses on top of the given source node, if any.
+    fn build_offset_limit(
+        &self,
+        mut node: Node,
+        offset: Option<ast::Expression>,
+        limit: Option<ast::Expression>,
+    ) -> Result<Node> {
+        if let Some(expr) = offset {
+            node = Node::Offset {
+                source: Box::new(node),
+                offset: match self.evaluate_constant(expr)? {
+                    Value::Integer(i) if i >= 0 => Ok(i as u64),
+                    v => Err(Error::Value(format!("Invalid offset {}", v))),
+                }?,
+            }
+        }
+        if let Some(expr) = limit {
+            node = Node::Limit {
+                source: Box::new(node),
+                limit: match self.evaluate_constant(expr)? {
+                    Value::Integer(i) if i >= 0 => Ok(i as u64),
+                    v => Err(Error::Value(format!("Invalid limit {}", v))),
+                }?,
+            }
+        }
+        Ok(node)
+    }
+
+    /// Builds a WHERE clause on top of the given source node. Top-level EXISTS, NOT EXISTS and IN
+    /// subqueries are decorrelated into semi and anti joins with the source where possible, and
+    /// the remaining predicate is evaluated by a filter.
+    fn build_where(
+        &self,
+        scope: &mut Scope,
+        source: Node,
+        r#where: Option<ast::Expression>,
+    ) -> Result<Node> {
+        let Some(expr) = r#where else { return Ok(source) };
+        let predicate = self.build_expression(scope, expr)?;
+        if !predicate.has_subqueries() {
+            return Ok(Node::Filter { source: Box::new(source), predicate });
+        }
+
+        let (mut node, mut remaining, mut decorrelated) = (source, Vec::new(), false);
+        let left_size = scope.len();
+        for expr in predicate.clone().into_cnf_vec() {
+            match Self::decorrelate(&expr, left_size) {
+                Some((r#type, right, predicate)) => {
+                    node = Node::NestedLoopJoin {
+                        left: Box::new(node),
+                        left_size,
+                        right: Box::new(right),
+                        predicate,
+                        r#type,
+                    };
+                    decorrelated = true;
+                }
+                None => remaining.push(expr),
+            }
+        }
+        if !decorrelated {
+            return Ok(Node::Filter { source: Box::new(node), predicate });
+        }
+        if let Some(predicate) = Expression::from_cnf_vec(remaining) {
+            node = Node::Filter { source: Box::new(node), predicate };
+        }
+        Ok(node)
+    }
+
+    /// Attempts to rewrite a WHERE conjunct as a semi or anti join with the subquery, returning
+    /// the join type, the right-hand node and the join predicate. The subquery must be a
+    /// projection (for IN) over an optional filter over a source, where only the filter can
+    /// reference the outer query. The filter's outer fields then become left-hand join fields,
+    /// and the subquery's fields are shifted past the left_size columns of the outer query.
+    /// Uncorrelated EXISTS and NOT IN are left for the executor, which only runs the subquery once
+    /// and handles NULLs respectively.
+    fn decorrelate(
+        expr: &Expression,
+        left_size: usize,
+    ) -> Option<(JoinType, Node, Option<Expression>)> {
+        let (r#type, lhs, node) = match expr {
+            Expression::Exists(node) => (JoinType::Semi, None, node),
+            Expression::Not(e) => match e.as_ref() {
+                Expression::Exists(node) => (JoinType::Anti, None, node),
+                _ => return None,
+            },
+            Expression::InSubquery(lhs, node) if !lhs.has_subqueries() => {
+                (JoinType::Semi, Some(lhs), node)
+            }
+            _ => return None,
+        };
+
+        // Peel off the projection and filter.
+        let (mut source, mut projection) = ((**node).clone(), None);
+        if let Node::Projection { source: inner, expressions } = source {
+            projection = Some(expressions);
+            source = *inner;
+        }
+        let mut filter = None;
+        if let Node::Filter { source: inner, predicate } = source {
+            filter = Some(predicate);
+            source = *inner;
+        }
+        if !source.outer_fields().is_empty() || filter.as_ref().is_some_and(|f| f.has_subqueries())
+        {
+            return None;
+        }
+        let correlated = filter
+            .as_ref()
+            .is_some_and(|f| f.contains(&|e| matches!(e, Expression::OuterField(1, ..))));
+
+        // Shift the subquery fields past the outer fields, and bind the outer fields to the
+        // left-hand join fields. Fields of queries further out are now one level closer.
+        let lift = |depth, i, label| match depth {
+            1 => Ok(Expression::Field(i, label)),
+            depth => Ok(Expression::OuterField(depth - 1, i, label)),
+        };
+        let source = source.transform_outer_fields(&lift).ok()?;
+        let shift = |expr: Expression| {
+            expr.transform(
+                &|e| match e {
+                    Expression::Field(i, label) => Ok(Expression::Field(left_size + i, label)),
+                    Expression::OuterField(depth, i, label) => lift(depth, i, label),
+                    e => Ok(e),
+                },
+                &Ok,
+            )
+            .ok()
+        };
+        let mut predicates = Vec::new();
+        match lhs {
+            Some(lhs) => {
+                let mut expressions = projection?;
+                if expressions.len() != 1 {
+                    return None;
+                }
+                let (value, _) = expressions.remove(0);
+                if value.has_subqueries()
+                    || value.contains(&|e| matches!(e, Expression::OuterField(..)))
+                {
+                    return None;
+                }
+                predicates.push(Expression::Equal(lhs.clone(), shift(value)?.into()));
+            }
+            None if !correlated => return None,
+            None => {}
+        }
+        if let Some(filter) = filter {
+            for expr in filter.into_cnf_vec() {
+                predicates.push(shift(expr)?);
+            }
+        }
+        Some((r#type, source, Expression::from_cnf_vec(predicates)))
     }
 
+    /// Builds a plan node for a FROM clause. Multiple FROM items are cross joined.
     fn build_from_clause(&self, scope: &mut Scope, from: Vec<ast::FromItem>) -> Result<Node> {
+        let base_scope = scope.clone();
         let mut items = from.into_iter();
-        let node = match items.next() {
+        let mut node = match items.next() {
             Some(item) => self.build_from_item(scope, item)?,
             None => return Err(Error::Value("No from items given".into())),
         };
+        for item in items {
+            let mut right_scope = base_scope.clone();
+            let right = self.build_from_item(&mut right_scope, item)?;
+            node = Node::NestedLoopJoin {
+                left: Box::new(node),
+                left_size: scope.len(),
+                right: Box::new(right),
+                predicate: None,
+                r#type: JoinType::Inner,
+            };
+            scope.merge(right_scope)?;
+        }
         Ok(node)
     }
 
     fn build_from_item(&self, scope: &mut Scope, item: ast::FromItem) -> Result<Node> {
         Ok(match item {
             ast::FromItem::Table { name, alias } => {
+                // Common table expressions shadow tables and views.
+                if let Some(cte) = scope.ctes.get(&name).cloned() {
+                    return self.build_cte(scope, cte, alias.unwrap_or(name));
+                }
+                // Views are expanded into the plan of their query, planned in a separate scope.
+                if let Some(view) = self.catalog.read_view(&name)? {
+                    let mut view_scope = Scope::new();
+                    let statement = Parser::new(&view.query).parse()?;
+                    let node = self.build_query(&mut view_scope, statement)?;
+                    scope.add_query(alias.unwrap_or(name), &view_scope)?;
+                    return Ok(node);
+                }
                 scope.add_table(
                     alias.clone().unwrap_or_else(|| name.clone()),
                     self.catalog.must_read_table(&name)?,
                 )?;
-                Node::Scan { table: name, alias, filter: None }
+                Node::Scan { table: name, alias, filter: None, columns: None }
+            }
+
+            ast::FromItem::Subquery { query, alias } => {
+                let mut query_scope = scope.derived();
+                let node = self.build_query(&mut query_scope, *query)?;
+                scope.add_query(alias, &query_scope)?;
+                node
+            }
+
+            ast::FromItem::Join { left, right, r#type, predicate } => {
+                // Right outer joins are built as a left outer join with an additional projection
+                // to swap the resulting columns back into their original order.
+                let (left, right) = match r#type {
+                    ast::JoinType::Right => (right, left),
+                    _ => (left, right),
+                };
+                let left = Box::new(self.build_from_item(scope, *left)?);
+                let left_size = scope.len();
+                let right = Box::new(self.build_from_item(scope, *right)?);
+                let predicate = predicate.map(|e| self.build_expression(scope, e)).transpose()?;
+                if predicate.as_ref().is_some_and(|p| p.has_subqueries()) {
+                    return Err(Error::Value(
+                        "Subqueries are not supported in join predicates".into(),
+                    ));
+                }
+                let swap = matches!(r#type, ast::JoinType::Right);
+                let r#type = match r#type {
+                    ast::JoinType::Cross | ast::JoinType::Inner => JoinType::Inner,
+                    ast::JoinType::Left | ast::JoinType::Right => JoinType::Left,
+                    ast::JoinType::Full => JoinType::Full,
+                };
+                let mut node = Node::NestedLoopJoin { left, left_size, right, predicate, r#type };
+                if swap {
+                    let expressions = (left_size..scope.len())
+                        .chain(0..left_size)
+                        .map(|i| Ok((Expression::Field(i, scope.get_label(i)?), None)))
+                        .collect::<Result<Vec<_>>>()?;
+                    scope.project(&expressions)?;
+                    node = Node::Projection { source: Box::new(node), expressions }
+                }
+                node
             }
-            _ => unimplemented!()
         })
     }
 
+    /// Builds a reference to a common table expression in a FROM clause, with the given label.
+    /// The query is planned at every reference, like a view, in a scope with the common table
+    /// expressions that were visible where it was defined.
+    fn build_cte(&self, scope: &mut Scope, cte: Cte, label: String) -> Result<Node> {
+        let (cte, ctes) = match cte {
+            Cte::Query(cte, ctes) => (cte, ctes),
+            Cte::WorkingTable(name, working_scope) => {
+                scope.add_query(label, &working_scope)?;
+                let columns = working_scope
+                    .columns
+                    .iter()
+                    .map(|(_, name)| types::Column { name: name.clone() })
+                    .collect();
+                return Ok(Node::WorkingTable { name, columns, rows: Vec::new() });
+            }
+        };
+        let mut query_scope = Scope::new();
+        query_scope.ctes = ctes.clone();
+        let mut node = self.build_query(&mut query_scope, *cte.query)?;
+
+        // Rename the columns, if column names are given.
+        if !cte.columns.is_empty() {
+            if cte.columns.len() != query_scope.len() {
+                return Err(Error::Value(format!(
+                    "Common table expression {} has {} columns, but {} column names were given",
+                    cte.name,
+                    query_scope.len(),
+                    cte.columns.len()
+                )));
+            }
+            let expressions: Vec<_> = cte
+                .columns
+                .into_iter()
+                .enumerate()
+                .map(|(i, name)| (Expression::Field(i, None), Some(name)))
+                .collect();
+            query_scope.project(&expressions)?;
+            node = Node::Projection { source: Box::new(node), expressions };
+        }
+
+        // The recursive term is planned with a working table in place of the expression itself,
+        // which has the columns of the anchor query.
+        if let Some((recursive, all)) = cte.recursive {
+            let mut recursive_scope = Scope::new();
+            recursive_scope.ctes = ctes;
+            recursive_scope
+                .ctes
+                .insert(cte.name.clone(), Cte::WorkingTable(cte.name.clone(), query_scope.clone()));
+            let recursive = self.build_query(&mut recursive_scope, *recursive)?;
+            if recursive_scope.len() != query_scope.len() {
+                return Err(Error::Value(format!(
+                    "Recursive term of {} must return {} columns, found {}",
+                    cte.name,
+                    query_scope.len(),
+                    recursive_scope.len()
+                )));
+            }
+            node = Node::Recursive {
+                name: cte.name,
+                anchor: Box::new(node),
+                recursive: Box::new(recursive),
+                all,
+                max_iterations: Plan::DEFAULT_MAX_RECURSION,
+            };
+        }
+        scope.add_query(label, &query_scope)?;
+        Ok(node)
+    }
+
+    /// Builds an aggregation node. All aggregate parameters and GROUP BY expressions are evaluated
+    /// in a pre-projection, whose results are fed into an Aggregation node. This node computes the
+    /// aggregates for the given groups, passing the group values through directly.
+    fn build_aggregation(
+        &self,
+        scope: &mut Scope,
+        source: Node,
+        groups: Vec<(ast::Expression, Option<String>)>,
+        aggregations: Vec<(Aggregate, ast::Expression)>,
+    ) -> Result<Node> {
+        let mut aggregates = Vec::new();
+        let mut expressions = Vec::new();
+        for (aggregate, expr) in aggregations {
+            aggregates.push(aggregate);
+            expressions.push((self.build_expression(scope, expr)?, None));
+        }
+        for (expr, label) in groups {
+            expressions.push((self.build_expression(scope, expr)?, label));
+        }
+        scope.project(
+            &expressions
+                .iter()
+                .cloned()
+                .enumerate()
+                .map(|(i, (e, l))| {
+                    if i < aggregates.len() {
+                        // We pass null values here since we don't want field references to hit
+                        // the fields in scope.
+                        (Expression::Constant(Value::Null), None)
+                    } else {
+                        (e, l)
+                    }
+                })
+                .collect::<Vec<_>>(),
+        )?;
+        Ok(Node::Aggregation {
+            source: Box::new(Node::Projection { source: Box::new(source), expressions }),
+            aggregates,
+        })
+    }
+
+    /// Extracts aggregate functions from an AST expression tree. This finds the aggregate
+    /// function calls, replaces them with ast::Expression::Column(i), maps the aggregate functions
+    /// to aggregates, and returns them along with their argument expressions.
+    fn extract_aggregates(
+        &self,
+        exprs: &mut [(ast::Expression, Option<String>)],
+    ) -> Result<Vec<(Aggregate, ast::Expression)>> {
+        let mut aggregates = Vec::new();
+        for (expr, _) in exprs {
+            expr.transform_mut(
+                &mut |mut e| match &mut e {
+                    ast::Expression::Function(f, args) => match Aggregate::from_name(f) {
+                        Some(aggregate) if args.len() == 1 => {
+                            aggregates.push((aggregate, args.remove(0)));
+                            Ok(ast::Expression::Column(aggregates.len() - 1))
+                        }
+                        Some(_) => Err(Error::Value(format!(
+                            "Aggregate function {} takes 1 argument, given {}",
+                            f,
+                            args.len()
+                        ))),
+                        None => Ok(e),
+                    },
+                    _ => Ok(e),
+                },
+                &mut Ok,
+            )?;
+        }
+        for (_, expr) in &aggregates {
+            if self.is_aggregate(expr) {
+                return Err(Error::Value("Aggregate functions can't be nested".into()));
+            }
+        }
+        Ok(aggregates)
+    }
+
+    /// Extracts group by expressions, and replaces them with column references with the given
+    /// offset. These can be either an arbitrary expression, a reference to a SELECT column, or the
+    /// same expression as a SELECT column. The following are all valid:
+    ///
+    /// SELECT released / 100 AS century, COUNT(*) FROM movies GROUP BY century
+    /// SELECT released / 100, COUNT(*) FROM movies GROUP BY released / 100
+    /// SELECT COUNT(*) FROM movies GROUP BY released / 100
+    fn extract_groups(
+        &self,
+        exprs: &mut [(ast::Expression, Option<String>)],
+        group_by: Vec<ast::Expression>,
+        offset: usize,
+    ) -> Result<Vec<(ast::Expression, Option<String>)>> {
+        let mut groups = Vec::new();
+        for g in group_by {
+            // Look for references to SELECT columns with AS labels
+            if let ast::Expression::Field(None, label) = &g {
+                if let Some(i) = exprs.iter().position(|(_, l)| l.as_deref() == Some(label)) {
+                    groups.push((
+                        replace(&mut exprs[i].0, ast::Expression::Column(offset + groups.len())),
+                        exprs[i].1.clone(),
+                    ));
+                    continue;
+                }
+            }
+            // Look for expressions exactly equal to the group expression
+            if let Some(i) = exprs.iter().position(|(e, _)| e == &g) {
+                groups.push((
+                    replace(&mut exprs[i].0, ast::Expression::Column(offset + groups.len())),
+                    exprs[i].1.clone(),
+                ));
+                continue;
+            }
+            // Otherwise, just use the group expression directly
+            groups.push((g, None))
+        }
+        // Make sure no group expressions contain aggregate functions, or Column references
+        // placed by extract_aggregates().
+        for (expr, _) in &groups {
+            if self.is_aggregate(expr)
+                || expr.contains(&|e| matches!(e, ast::Expression::Column(_)))
+            {
+                return Err(Error::Value("Group expression cannot contain aggregates".into()));
+            }
+        }
+        Ok(groups)
+    }
+
+    /// Checks whether a given expression is an aggregate expression.
+    fn is_aggregate(&self, expr: &ast::Expression) -> bool {
+        expr.contains(&|e| match e {
+            ast::Expression::Function(f, _) => Aggregate::from_name(f).is_some(),
+            _ => false,
+        })
+    }
+
+    /// Checks whether an AST expression contains a window function call.
+    fn has_window(expr: &ast::Expression) -> bool {
+        expr.contains(&|e| matches!(e, ast::Expression::WindowFunction(..)))
+    }
+
+    /// Extracts window function calls from SELECT expressions, replacing them with Column
+    /// references starting at the given offset. The calls are grouped by their partitioning and
+    /// ordering, such that each group can be computed by a single Window node, and identical
+    /// calls are only computed once. The placeholders are numbered in group order.
+    fn extract_windows(
+        &self,
+        exprs: &mut [(ast::Expression, Option<String>)],
+        offset: usize,
+    ) -> Result<Vec<Vec<ast::Expression>>> {
+        let mut groups: Vec<Vec<ast::Expression>> = Vec::new();
+        for (expr, _) in exprs.iter_mut() {
+            expr.transform_mut(
+                &mut |e| {
+                    let ast::Expression::WindowFunction(_, args, window) = &e else {
+                        return Ok(e);
+                    };
+                    let order = window.order.iter().map(|(e, _)| e);
+                    if args.iter().chain(&window.partition).chain(order).any(Self::has_window) {
+                        return Err(Error::Value("Window functions can't be nested".into()));
+                    }
+                    let same_window = |call: &ast::Expression| match call {
+                        ast::Expression::WindowFunction(_, _, w) => {
+                            w.partition == window.partition && w.order == window.order
+                        }
+                        _ => false,
+                    };
+                    match groups.iter_mut().find(|group| same_window(&group[0])) {
+                        Some(group) if group.contains(&e) => {}
+                        Some(group) => group.push(e.clone()),
+                        None => groups.push(vec![e.clone()]),
+                    }
+                    Ok(e)
+                },
+                &mut Ok,
+            )?;
+        }
+        let calls: Vec<_> = groups.iter().flatten().cloned().collect();
+        for (expr, _) in exprs.iter_mut() {
+            expr.transform_mut(
+                &mut |e| match calls.iter().position(|call| call == &e) {
+                    Some(i) => Ok(ast::Expression::Column(offset + i)),
+                    None => Ok(e),
+                },
+                &mut Ok,
+            )?;
+        }
+        Ok(groups)
+    }
+
+    /// Builds a Window node for window function calls with the same partitioning and ordering,
+    /// adding a column to the scope for each call.
+    fn build_window(
+        &self,
+        scope: &mut Scope,
+        source: Node,
+        calls: Vec<ast::Expression>,
+    ) -> Result<Node> {
+        let (mut partition, mut orders, mut functions) = (Vec::new(), Vec::new(), Vec::new());
+        for (i, call) in calls.into_iter().enumerate() {
+            let ast::Expression::WindowFunction(name, args, window) = call else {
+                return Err(Error::Internal(format!("Expected window function, got {:?}", call)));
+            };
+            let ast::Window { partition: p, order, frame } = *window;
+            if i == 0 {
+                partition =
+                    p.into_iter().map(|e| self.build_expression(scope, e)).collect::<Result<_>>()?;
+                orders = self.build_orders(scope, order)?;
+            }
+            let frame = self.build_frame(frame, orders.len())?;
+            functions.push((self.build_window_function(scope, &name, args)?, frame));
+        }
+        // The window executor can't execute subqueries.
+        if partition
+            .iter()
+            .chain(functions.iter().flat_map(|(f, _)| f.expressions()))
+            .any(|e| e.has_subqueries())
+        {
+            return Err(Error::Value("Subqueries are not supported in window functions".into()));
+        }
+        for _ in &functions {
+            scope.add_column(None, None);
+        }
+        Ok(Node::Window { source: Box::new(source), partition, orders, functions })
+    }
+
+    /// Builds a window function from its name and arguments. The LAG and LEAD offset must be a
+    /// constant, and aggregate functions can be used as window functions.
+    fn build_window_function(
+        &self,
+        scope: &mut Scope,
+        name: &str,
+        mut args: Vec<ast::Expression>,
+    ) -> Result<WindowFunction> {
+        Ok(match (name, args.len(), Aggregate::from_name(name)) {
+            ("row_number", 0, _) => WindowFunction::RowNumber,
+            ("rank", 0, _) => WindowFunction::Rank,
+            ("dense_rank", 0, _) => WindowFunction::DenseRank,
+            ("lag" | "lead", 1..=3, _) => {
+                let default = match args.len() {
+                    3 => self.build_expression(scope, args.remove(2))?,
+                    _ => Expression::Constant(Value::Null),
+                };
+                let offset = match args.len() {
+                    2 => match self.evaluate_constant(args.remove(1))? {
+                        Value::Integer(i) if i >= 0 => i as u64,
+                        v => return Err(Error::Value(format!("Invalid {} offset {}", name, v))),
+                    },
+                    _ => 1,
+                };
+                let expr = self.build_expression(scope, args.remove(0))?;
+                match name {
+                    "lag" => WindowFunction::Lag(expr, offset, default),
+                    _ => WindowFunction::Lead(expr, offset, default),
+                }
+            }
+            ("first_value", 1, _) => {
+                WindowFunction::FirstValue(self.build_expression(scope, args.remove(0))?)
+            }
+            (_, 1, Some(aggregate)) if aggregate != Aggregate::CountDistinct => {
+                WindowFunction::Aggregate(aggregate, self.build_expression(scope, args.remove(0))?)
+            }
+            (_, _, Some(Aggregate::CountDistinct)) => {
+                return Err(Error::Value("DISTINCT is not supported in window functions".into()))
+            }
+            ("row_number" | "rank" | "dense_rank" | "lag" | "lead" | "first_value", _, _)
+            | ("avg" | "count" | "max" | "min" | "sum", _, _) => {
+                return Err(Error::Value(format!(
+                    "Window function {} can't take {} arguments",
+                    name,
+                    args.len()
+                )))
+            }
+            (name, _, _) => {
+                return Err(Error::Value(format!("Unknown window function {}", name)))
+            }
+        })
+    }
+
+    /// Builds a window frame. Without a frame, the frame is the entire partition, or the rows up
+    /// to the current row and its peers with ORDER BY. RANGE frames with an offset require a
+    /// single ORDER BY expression to compare the offset with.
+    fn build_frame(&self, frame: Option<ast::Frame>, orders: usize) -> Result<Frame> {
+        let Some(frame) = frame else {
+            let end = match orders {
+                0 => FrameBound::UnboundedFollowing,
+                _ => FrameBound::CurrentRow,
+            };
+            return Ok(Frame { rows: false, start: FrameBound::UnboundedPreceding, end });
+        };
+        let rows = frame.units == ast::FrameUnits::Rows;
+        let offset = |expr: ast::Expression| -> Result<Value> {
+            match self.evaluate_constant(expr)? {
+                Value::Integer(i) if i >= 0 => Ok(Value::Integer(i)),
+                Value::Float(f) if !rows && f >= 0.0 => Ok(Value::Float(f)),
+                Value::Decimal(d) if !rows && d >= Default::default() => Ok(Value::Decimal(d)),
+                v => Err(Error::Value(format!("Invalid window frame offset {}", v))),
+            }
+        };
+        let bound = |bound: ast::FrameBound| -> Result<(FrameBound, u8)> {
+            Ok(match bound {
+                ast::FrameBound::UnboundedPreceding => (FrameBound::UnboundedPreceding, 0),
+                ast::FrameBound::Preceding(expr) => (FrameBound::Preceding(offset(expr)?), 1),
+                ast::FrameBound::CurrentRow => (FrameBound::CurrentRow, 2),
+                ast::FrameBound::Following(expr) => (FrameBound::Following(offset(expr)?), 3),
+                ast::FrameBound::UnboundedFollowing => (FrameBound::UnboundedFollowing, 4),
+            })
+        };
+        // Bounds are ranked by position, and the start can't come after the end.
+        let ((start, start_rank), (end, end_rank)) = (bound(frame.start)?, bound(frame.end)?);
+        let frame = Frame { rows, start, end };
+        if start_rank == 4 || end_rank == 0 || start_rank > end_rank {
+            return Err(Error::Value(format!("Invalid window frame {}", frame)));
+        }
+        if !rows && orders != 1 && [start_rank, end_rank].iter().any(|r| *r == 1 || *r == 3) {
+            return Err(Error::Value(
+                "RANGE frames with an offset require a single ORDER BY expression".into(),
+            ));
+        }
+        Ok(frame)
+    }
+
+    /// Injects hidden expressions into SELECT expressions. This is used for ORDER BY and HAVING, in
+    /// order to apply these to fields or aggregates that are not present in the SELECT output, e.g.
+    /// to order on a column that is not selected. This is done by replacing the relevant parts of
+    /// the given expression with Column references to either existing columns or new, hidden
+    /// columns in the select expressions. Returns the number of hidden columns added.
     fn inject_hidden(
         &self,
         expr: &mut ast::Expression,
@@ -234,7 +1197,18 @@ impl<'a, C: Catalog> Planner<'a, C> {
         let mut hidden = 0;
         expr.transform_mut(
             &mut |e| match &e {
-                ast::Expression::Function(_, _) => unimplemented!(),
+                ast::Expression::Function(f, args) if Aggregate::from_name(f).is_some() => {
+                    if let Some(ast::Expression::Column(c)) = args.first() {
+                        if self.is_aggregate(&select[*c].0) {
+                            return Err(Error::Value(
+                                "Aggregate function cannot reference aggregate".into(),
+                            ));
+                        }
+                    }
+                    select.push((e, None));
+                    hidden += 1;
+                    Ok(ast::Expression::Column(select.len() - 1))
+                }
                 ast::Expression::Field(_, _) => {
                     select.push((e, None));
                     hidden += 1;
@@ -247,6 +1221,60 @@ impl<'a, C: Catalog> Planner<'a, C> {
         Ok(hidden)
     }
     
+    /// Builds a column schema from an AST column specification. Auto-increment columns get a
+    /// sequence named after the table and column.
+    fn build_column(&self, table: &str, c: ast::Column) -> Result<Column> {
+        let nullable = c.nullable.unwrap_or(!c.primary_key && !c.auto_increment);
+        let sequence = c.auto_increment.then(|| format!("{}_{}_seq", table, c.name));
+        let default = match c.default {
+            Some(expr) => Some(self.evaluate_constant(expr)?.coerce(&c.datatype)?),
+            None if nullable => Some(Value::Null),
+            None => None,
+        };
+        Ok(Column {
+            name: c.name,
+            datatype: c.datatype,
+            max_length: c.max_length,
+            primary_key: c.primary_key,
+            nullable,
+            default,
+            index: (c.index || c.references.is_some()) && !c.primary_key,
+            unique: c.unique || c.primary_key,
+            references: c.references,
+            on_delete: c.on_delete.unwrap_or_default(),
+            on_update: c.on_update.unwrap_or_default(),
+            sequence,
+        })
+    }
+
+    /// Builds CHECK constraint expressions for a table schema. Column labels are unqualified,
+    /// since checks outlive the table name.
+    fn build_checks(&self, table: &Table, checks: Vec<ast::Expression>) -> Result<Vec<Expression>> {
+        let scope = &mut Scope::from_table(table.clone())?;
+        checks
+            .into_iter()
+            .map(|check| {
+                let check = self.build_expression(scope, check)?;
+                // Checks are evaluated per row by the executors, which can't execute subqueries.
+                if check.has_subqueries() {
+                    return Err(Error::Value(
+                        "Subqueries are not supported in CHECK constraints".into(),
+                    ));
+                }
+                // Checks must be deterministic, and sequences are only resolved by executors.
+                if check.contains(&|e| matches!(e, Expression::Function(Function::NextVal, _))) {
+                    return Err(Error::Value("NEXTVAL can't be used in CHECK constraints".into()));
+                }
+                check.transform(&Ok, &|e| match e {
+                    Expression::Field(i, Some((_, name))) => {
+                        Ok(Expression::Field(i, Some((None, name))))
+                    }
+                    e => Ok(e),
+                })
+            })
+            .collect()
+    }
+
     /// Builds an expression from an AST expression
     #[allow(clippy::only_used_in_recursion)]
     fn build_expression(&self, scope: &mut Scope, expr: ast::Expression) -> Result<Expression> {
@@ -258,13 +1286,42 @@ impl<'a, C: Catalog> Planner<'a, C> {
                 ast::Literal::Integer(i) => Value::Integer(i),
                 ast::Literal::Float(f) => Value::Float(f),
                 ast::Literal::String(s) => Value::String(s),
+                ast::Literal::Blob(b) => Value::Blob(b),
             }),
             ast::Expression::Column(i) => Field(i, scope.get_label(i)?),
-            ast::Expression::Field(table, name) => {
-                Field(scope.resolve(table.as_deref(), &name)?, Some((table, name)))
+            ast::Expression::Field(table, name) => match scope.resolve(table.as_deref(), &name) {
+                Ok(i) => Field(i, Some((table, name))),
+                Err(err) => match scope.resolve_outer(table.as_deref(), &name) {
+                    Some(field) => {
+                        let (depth, i) = field?;
+                        OuterField(depth, i, Some((table, name)))
+                    }
+                    None => return Err(err),
+                },
+            },
+            ast::Expression::Subquery(query) => {
+                Subquery(self.build_subquery(scope, *query, true)?.into())
             }
-            ast::Expression::Function(name, _) => {
-                return Err(Error::Value(format!("Unknown function {}", name,)))
+            ast::Expression::Exists(query) => {
+                Exists(self.build_subquery(scope, *query, false)?.into())
+            }
+            ast::Expression::InSubquery(lhs, query) => InSubquery(
+                self.build_expression(scope, *lhs)?.into(),
+                self.build_subquery(scope, *query, true)?.into(),
+            ),
+            ast::Expression::Function(name, args) => match self::Function::from_name(&name) {
+                Some(function) => self.build_function(scope, function, args)?,
+                None => return Err(Error::Value(format!("Unknown function {}", name,))),
+            },
+            ast::Expression::Cast(expr, datatype) => {
+                self.build_function(scope, self::Function::Cast(datatype), vec![*expr])?
+            }
+            // Window functions in the SELECT clause have been extracted already.
+            ast::Expression::WindowFunction(name, _, _) => {
+                return Err(Error::Value(format!(
+                    "Window function {} is only allowed in the SELECT clause",
+                    name
+                )))
             }
             ast::Expression::Operation(op) => match op {
                 // Logical operators
@@ -316,7 +1373,26 @@ impl<'a, C: Catalog> Planner<'a, C> {
                     )
                     .into(),
                 ),
-                ast::Operation::Like(_, _) => unimplemented!(),
+                ast::Operation::ILike(lhs, rhs) => ILike(
+                    self.build_expression(scope, *lhs)?.into(),
+                    self.build_expression(scope, *rhs)?.into(),
+                ),
+                ast::Operation::Like(lhs, rhs) => Like(
+                    self.build_expression(scope, *lhs)?.into(),
+                    self.build_expression(scope, *rhs)?.into(),
+                ),
+                ast::Operation::Regex(lhs, rhs) => Regex(
+                    self.build_expression(scope, *lhs)?.into(),
+                    self.build_expression(scope, *rhs)?.into(),
+                ),
+                ast::Operation::JsonGet(lhs, rhs) => JsonGet(
+                    self.build_expression(scope, *lhs)?.into(),
+                    self.build_expression(scope, *rhs)?.into(),
+                ),
+                ast::Operation::JsonGetText(lhs, rhs) => JsonGetText(
+                    self.build_expression(scope, *lhs)?.into(),
+                    self.build_expression(scope, *rhs)?.into(),
+                ),
                 ast::Operation::NotEqual(lhs, rhs) => Not(Equal(
                     self.build_expression(scope, *lhs)?.into(),
                     self.build_expression(scope, *rhs)?.into(),
@@ -357,6 +1433,52 @@ impl<'a, C: Catalog> Planner<'a, C> {
         })
     }
 
+    /// Builds the plan of a subquery in an expression. The subquery can reference the fields of
+    /// the given scope as outer fields, making it a correlated subquery.
+    fn build_subquery(&self, scope: &Scope, query: ast::Statement, single: bool) -> Result<Node> {
+        if scope.constant {
+            return Err(Error::Value("Expression must be constant, found subquery".into()));
+        }
+        let mut query_scope = scope.subquery();
+        let node = self.build_query(&mut query_scope, query)?;
+        if single && query_scope.len() != 1 {
+            return Err(Error::Value(format!(
+                "Subquery must return a single column, found {}",
+                query_scope.len()
+            )));
+        }
+        Ok(node)
+    }
+
+    /// Builds a scalar function call, checking its arguments against the function signature.
+    /// Argument types are known for constants and table columns.
+    fn build_function(
+        &self,
+        scope: &mut Scope,
+        function: Function,
+        args: Vec<ast::Expression>,
+    ) -> Result<Expression> {
+        let args = args
+            .into_iter()
+            .map(|arg| self.build_expression(scope, arg))
+            .collect::<Result<Vec<_>>>()?;
+        let datatypes: Vec<_> = args
+            .iter()
+            .map(|arg| match arg {
+                Expression::Constant(value) => value.datatype(),
+                Expression::Field(i, _) => scope.get_datatype(*i),
+                _ => None,
+            })
+            .collect();
+        function.check(&datatypes)?;
+        if function == Function::NextVal
+            && !matches!(args.as_slice(), [Expression::Constant(Value::String(_))])
+        {
+            return Err(Error::Value("NEXTVAL requires a constant sequence name".into()));
+        }
+        Ok(Expression::Function(function, args))
+    }
+
     /// Builds and evaluates a constant AST expression.
     fn evaluate_constant(&self, expr: ast::Expression) -> Result<Value> {
         self.build_expression(&mut Scope::constant(), expr)?.evaluate(None)
@@ -368,8 +1490,8 @@ impl<'a, C: Catalog> Planner<'a, C> {
 pub struct Scope {
     // If true, the scope is constant and cannot contain any variables.
     constant: bool,
-    // Currently visible tables, by query name (i.e. alias or actual name).
-    tables: HashMap<String, Table>,
+    // Currently visible tables, by query name (i.e. alias or actual name). Views have no table.
+    tables: HashMap<String, Option<Table>>,
     // Column labels, if any (qualified by table name when available)
     columns: Vec<(Option<String>, Option<String>)>,
     // Qualified names to column indexes.
@@ -378,6 +1500,19 @@ pub struct Scope {
     unqualified: HashMap<String, usize>,
     // Unqialified ambiguous names.
     ambiguous: HashSet<String>,
+    // The scope of the enclosing query, for subqueries.
+    outer: Option<Box<Scope>>,
+    // Visible common table expressions, by name.
+    ctes: HashMap<String, Cte>,
+}
+
+/// A common table expression visible in a scope.
+#[derive(Clone, Debug)]
+enum Cte {
+    /// A WITH query, along with the common table expressions visible where it was defined.
+    Query(ast::CommonTableExpression, HashMap<String, Cte>),
+    /// The working table of a recursive query being planned, with the anchor query's scope.
+    WorkingTable(String, Scope),
 }
 
 impl Scope {
@@ -390,6 +1525,8 @@ impl Scope {
             qualified: HashMap::new(),
             unqualified: HashMap::new(),
             ambiguous: HashSet::new(),
+            outer: None,
+            ctes: HashMap::new(),
         }
     }
 
@@ -400,6 +1537,21 @@ impl Scope {
         scope
     }
 
+    /// Creates a scope for a subquery, which can reference the columns of this scope.
+    fn subquery(&self) -> Self {
+        let mut scope = self.derived();
+        scope.outer = Some(Box::new(self.clone()));
+        scope
+    }
+
+    /// Creates a scope for a derived query, e.g. a subquery in the FROM clause, which can
+    /// reference the common table expressions of this scope but not its columns.
+    fn derived(&self) -> Self {
+        let mut scope = Self::new();
+        scope.ctes = self.ctes.clone();
+        scope
+    }
+
     /// Creates a scope from a table.
     fn from_table(table: Table) -> Result<Self> {
         let mut scope = Self::new();
@@ -437,7 +1589,22 @@ impl Scope {
         for column in &table.columns {
             self.add_column(Some(label.clone()), Some(column.name.clone()));
         }
-        self.tables.insert(label, table);
+        self.tables.insert(label, Some(table));
+        Ok(())
+    }
+
+    /// Adds a view or FROM subquery to the scope, with the output columns of its query scope.
+    fn add_query(&mut self, label: String, query: &Scope) -> Result<()> {
+        if self.constant {
+            return Err(Error::Internal("Can't modify constant scope".into()));
+        }
+        if self.tables.contains_key(&label) {
+            return Err(Error::Value(format!("Duplicate table name {}", label)));
+        }
+        for (_, name) in &query.columns {
+            self.add_column(Some(label.clone()), name.clone());
+        }
+        self.tables.insert(label, None);
         Ok(())
     }
 
@@ -455,6 +1622,16 @@ impl Scope {
             .ok_or_else(|| Error::Value(format!("Column index {} not found", index)))
     }
 
+    /// Fetches the data type of a column by index, if it is a known table column.
+    fn get_datatype(&self, index: usize) -> Option<DataType> {
+        match self.columns.get(index)? {
+            (Some(table), Some(name)) => {
+                self.tables.get(table)?.as_ref()?.get_column(name).ok().map(|c| c.datatype.clone())
+            }
+            _ => None,
+        }
+    }
+
     /// Fetches a column label by index, if any.
     fn get_label(&self, index: usize) -> Result<Option<(Option<String>, String)>> {
         Ok(match self.get_column(index)? {
@@ -506,6 +1683,25 @@ impl Scope {
         }
     }
 
+    /// Resolves a name in the enclosing queries of a subquery, innermost first, if it can't refer
+    /// to the subquery's own columns. Returns the number of query levels up and the column index,
+    /// or None if there is no enclosing query, or if the name is ambiguous or qualified by a
+    /// table in this scope.
+    fn resolve_outer(&self, table: Option<&str>, name: &str) -> Option<Result<(usize, usize)>> {
+        let outer = self.outer.as_ref()?;
+        match table {
+            Some(table) if self.tables.contains_key(table) => None,
+            None if self.ambiguous.contains(name) => None,
+            table => Some(match outer.resolve(table, name) {
+                Ok(i) => Ok((1, i)),
+                Err(err) => match outer.resolve_outer(table, name) {
+                    Some(field) => field.map(|(depth, i)| (depth + 1, i)),
+                    None => Err(err),
+                },
+            }),
+        }
+    }
+
     /// Number of columns in the current scope.
     fn len(&self) -> usize {
         self.columns.len()
@@ -519,6 +1715,8 @@ impl Scope {
         }
         let mut new = Self::new();
         new.tables = self.tables.clone();
+        new.outer = self.outer.take();
+        new.ctes = std::mem::take(&mut self.ctes);
         for (expr, label) in projection {
             match (expr, label) {
                 (_, Some(label)) => new.add_column(None, Some(label.clone())),
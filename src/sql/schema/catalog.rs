@@ -1,6 +1,9 @@
 use crate::error::{Error, Result};
+use crate::sql::types::expression::Expression;
 
-use super::table::{Table, Tables};
+use super::sequence::Sequence;
+use super::table::{Column, Table, Tables};
+use super::view::View;
 
 /// db 的接口
 pub trait Catalog {
@@ -12,6 +15,47 @@ pub trait Catalog {
 
     fn scan_tables(&self) -> Result<Tables>;
 
+    /// Adds a column to a table along with any CHECK constraints on it, filling in its default
+    /// value for existing rows
+    fn add_column(&mut self, table: &str, column: Column, checks: Vec<Expression>) -> Result<()>;
+
+    /// Drops a column from a table, removing its values from existing rows along with any CHECK
+    /// constraints that use it
+    fn drop_column(&mut self, table: &str, column: &str) -> Result<()>;
+
+    /// Renames a table column
+    fn rename_column(&mut self, table: &str, column: &str, name: &str) -> Result<()>;
+
+    /// Renames a table, updating any references to it
+    fn rename_table(&mut self, table: &str, name: &str) -> Result<()>;
+
+    /// Creates a sequence, resetting its state
+    fn create_sequence(&mut self, sequence: Sequence) -> Result<()>;
+
+    /// Deletes a sequence, unless it generates values for a table column
+    fn delete_sequence(&mut self, sequence: &str) -> Result<()>;
+
+    fn read_sequence(&self, sequence: &str) -> Result<Option<Sequence>>;
+
+    fn must_read_sequence(&self, sequence: &str) -> Result<Sequence> {
+        self.read_sequence(sequence)?
+            .ok_or_else(|| Error::Value(format!("Sequence {} does not exist", sequence)))
+    }
+
+    /// Creates a view, unless a table or view with the same name exists
+    fn create_view(&mut self, view: View) -> Result<()>;
+
+    fn delete_view(&mut self, view: &str) -> Result<()>;
+
+    fn read_view(&self, view: &str) -> Result<Option<View>>;
+
+    /// Returns all views, ordered by name
+    fn scan_views(&self) -> Result<Vec<View>>;
+
+    fn must_read_view(&self, view: &str) -> Result<View> {
+        self.read_view(view)?.ok_or_else(|| Error::Value(format!("View {} does not exist", view)))
+    }
+
     fn must_read_table(&self, table: &str) -> Result<Table> {
         self.read_table(table)?
             .ok_or_else(|| Error::Value(format!("Table {} does not exist", table)))
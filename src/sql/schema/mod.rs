@@ -1,3 +1,7 @@
 pub mod table;
 
-pub mod catalog;
\ No newline at end of file
+pub mod catalog;
+
+pub mod sequence;
+
+pub mod view;
@@ -0,0 +1,42 @@
+use std::fmt::Display;
+
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// A sequence schema. Sequences generate integers for NEXTVAL() and auto-increment columns. The
+/// schema is transactional, but the sequence state is not: values are never reused, even when
+/// the transaction that fetched them is rolled back.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Sequence {
+    pub name: String,
+    /// The first value of the sequence
+    pub start: i64,
+    /// The difference between successive values, which can be negative
+    pub increment: i64,
+}
+
+impl Sequence {
+    /// Creates a new sequence schema, counting up from 1
+    pub fn new(name: String) -> Self {
+        Self { name, start: 1, increment: 1 }
+    }
+
+    /// Validates the sequence schema
+    pub fn validate(&self) -> Result<()> {
+        if self.increment == 0 {
+            return Err(Error::Value(format!("Sequence {} can't have increment 0", self.name)));
+        }
+        Ok(())
+    }
+}
+
+impl Display for Sequence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "CREATE SEQUENCE {} START WITH {} INCREMENT BY {}",
+            self.name, self.start, self.increment
+        )
+    }
+}
@@ -1,8 +1,9 @@
 use std::fmt::Display;
+use std::slice;
 
 use serde_derive::{Deserialize, Serialize};
 
-use crate::{error::{Error, Result}, sql::{types::{Value, DataType}, engine::Transaction}};
+use crate::{error::{Error, Result}, sql::{types::{Row, Value, DataType, expression::Expression}, engine::Transaction}};
 
 
 /// A table scan iterator
@@ -13,12 +14,19 @@ pub type Tables = Box<dyn DoubleEndedIterator<Item = Table> + Send>;
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
+    /// Indexes of the primary key columns, in key order. Composite keys may be declared in a
+    /// different order than the table columns.
+    pub primary_key: Vec<usize>,
+    /// CHECK constraints, evaluated against every inserted or updated row
+    pub checks: Vec<Expression>,
 }
 
 impl Table {
-    /// Creates a new table schema
+    /// Creates a new table schema, with the primary key columns in table column order
     pub fn new(name: String, columns: Vec<Column>) -> Result<Self> {
-        let table = Self { name, columns };
+        let primary_key = columns.iter().enumerate().filter(|(_, c)| c.primary_key);
+        let primary_key = primary_key.map(|(i, _)| i).collect();
+        let table = Self { name, columns, primary_key, checks: Vec::new() };
         Ok(table)
     }
 
@@ -36,24 +44,32 @@ impl Table {
         })
     }
 
-    /// Returns the primary key column of the table
+    /// Returns the primary key column of the table. Errors for composite primary keys.
     pub fn get_primary_key(&self) -> Result<&Column> {
-        self.columns
-            .iter()
-            .find(|c| c.primary_key)
-            .ok_or_else(|| Error::Value(format!("Primary key not found in table {}", self.name)))
+        match self.get_primary_key_columns()[..] {
+            [column] => Ok(column),
+            [] => Err(Error::Value(format!("Primary key not found in table {}", self.name))),
+            _ => Err(Error::Value(format!("Table {} has a composite primary key", self.name))),
+        }
     }
 
-    /// Returns the primary key value of a row
-    pub fn get_row_key(&self, row: &[Value]) -> Result<Value> {
-        row.get(
-            self.columns
-                .iter()
-                .position(|c| c.primary_key)
-                .ok_or_else(|| Error::Value("Primary key not found".into()))?,
-        )
-        .cloned()
-        .ok_or_else(|| Error::Value("Primary key value not found for row".into()))
+    /// Returns the primary key columns of the table, in key order
+    pub fn get_primary_key_columns(&self) -> Vec<&Column> {
+        self.primary_key.iter().map(|i| &self.columns[*i]).collect()
+    }
+
+    /// Returns the primary key of a row, with one value per primary key column
+    pub fn get_row_key(&self, row: &[Value]) -> Result<Vec<Value>> {
+        let key: Vec<Value> = self
+            .primary_key
+            .iter()
+            .map(|i| row.get(*i).cloned())
+            .collect::<Option<_>>()
+            .ok_or_else(|| Error::Value("Primary key value not found for row".into()))?;
+        if key.is_empty() {
+            return Err(Error::Value("Primary key not found".into()));
+        }
+        Ok(key)
     }
 
     /// Validates the table schema
@@ -61,19 +77,32 @@ impl Table {
         if self.columns.is_empty() {
             return Err(Error::Value(format!("Table {} has no columns", self.name)));
         }
-        match self.columns.iter().filter(|c| c.primary_key).count() {
-            1 => {}
-            0 => return Err(Error::Value(format!("No primary key in table {}", self.name))),
-            _ => return Err(Error::Value(format!("Multiple primary keys in table {}", self.name))),
-        };
+        if !self.columns.iter().any(|c| c.primary_key) {
+            return Err(Error::Value(format!("No primary key in table {}", self.name)));
+        }
+        // The key order must cover each primary key column exactly once.
+        let mut primary_key = self.primary_key.clone();
+        primary_key.sort_unstable();
+        let columns = (0..self.columns.len()).filter(|i| self.columns[*i].primary_key);
+        if !primary_key.into_iter().eq(columns) {
+            return Err(Error::Value(format!("Invalid primary key in table {}", self.name)));
+        }
         for column in &self.columns {
             column.validate(self, txn)?;
         }
+        for check in &self.checks {
+            if !check.walk(&|e| !matches!(e, Expression::Field(i, _) if *i >= self.columns.len())) {
+                return Err(Error::Value(format!(
+                    "Check constraint {} refers to an unknown column in table {}",
+                    check, self.name
+                )));
+            }
+        }
         Ok(())
     }
 
     /// Validates a row
-    pub fn validate_row(&self, row: &[Value], txn: &mut dyn Transaction) -> Result<()> {
+    pub fn validate_row(&self, row: &Row, txn: &mut dyn Transaction) -> Result<()> {
         if row.len() != self.columns.len() {
             return Err(Error::Value(format!("Invalid row size for table {}", self.name)));
         }
@@ -81,18 +110,45 @@ impl Table {
         for (column, value) in self.columns.iter().zip(row.iter()) {
             column.validate_value(self, &pk, value, txn)?;
         }
+        for check in &self.checks {
+            match check.evaluate(Some(row))? {
+                Value::Boolean(true) | Value::Null => {}
+                Value::Boolean(false) => {
+                    return Err(Error::Value(format!(
+                        "Check constraint {} violated for table {}",
+                        check, self.name
+                    )))
+                }
+                v => {
+                    return Err(Error::Value(format!(
+                        "Check constraint {} returned {}, expected boolean",
+                        check, v
+                    )))
+                }
+            }
+        }
         Ok(())
     }
 }
 
 impl Display for Table {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "CREATE TABLE {} (\n{}\n)",
-            &self.name,
-            self.columns.iter().map(|c| format!("  {}", c)).collect::<Vec<String>>().join(",\n")
-        )
+        let mut lines: Vec<String> = self.columns.iter().map(|c| format!("  {}", c)).collect();
+        let primary_key = self.get_primary_key_columns();
+        if primary_key.len() > 1 {
+            // Composite primary keys are given as a table constraint rather than per column
+            for (line, column) in lines.iter_mut().zip(&self.columns) {
+                if column.primary_key {
+                    *line = line.replacen(" PRIMARY KEY", "", 1);
+                }
+            }
+            lines.push(format!(
+                "  PRIMARY KEY ({})",
+                primary_key.iter().map(|c| c.name.as_str()).collect::<Vec<_>>().join(", ")
+            ));
+        }
+        lines.extend(self.checks.iter().map(|c| format!("  CHECK ({})", c)));
+        write!(f, "CREATE TABLE {} (\n{}\n)", &self.name, lines.join(",\n"))
     }
 }
 
@@ -103,6 +159,8 @@ pub struct Column {
     pub name: String,
     /// Column datatype
     pub datatype: DataType,
+    /// The maximum number of characters in string values, for VARCHAR(n) columns
+    pub max_length: Option<usize>,
     /// Whether the column is a primary key
     pub primary_key: bool,
     /// Whether the column allows null values
@@ -113,8 +171,15 @@ pub struct Column {
     pub unique: bool,
     /// The table which is referenced by this foreign key
     pub references: Option<String>,
+    /// The action to take when a referenced row is deleted
+    pub on_delete: ReferenceAction,
+    /// The action to take when the primary key of a referenced row is changed
+    pub on_update: ReferenceAction,
     /// Whether the column should be indexed
     pub index: bool,
+    /// The sequence generating values for the column when none is given, for auto-increment
+    /// columns
+    pub sequence: Option<String>,
 }
 
 impl Column {
@@ -124,10 +189,42 @@ impl Column {
         if self.primary_key && self.nullable {
             return Err(Error::Value(format!("Primary key {} cannot be nullable", self.name)));
         }
-        if self.primary_key && !self.unique {
+        if self.primary_key && !self.unique && table.get_primary_key_columns().len() == 1 {
             return Err(Error::Value(format!("Primary key {} must be unique", self.name)));
         }
 
+        // Validate length limit
+        if let Some(max_length) = self.max_length {
+            if self.datatype != DataType::String {
+                return Err(Error::Value(format!(
+                    "Column {} has a length limit but datatype {}",
+                    self.name, self.datatype
+                )));
+            }
+            if max_length == 0 {
+                return Err(Error::Value(format!(
+                    "Length limit of column {} must be positive",
+                    self.name
+                )));
+            }
+        }
+
+        // Validate auto-increment
+        if self.sequence.is_some() {
+            if self.datatype != DataType::Integer {
+                return Err(Error::Value(format!(
+                    "Auto-increment column {} must be an integer, found {}",
+                    self.name, self.datatype
+                )));
+            }
+            if self.default.is_some() || self.nullable {
+                return Err(Error::Value(format!(
+                    "Auto-increment column {} can't be nullable or have a default value",
+                    self.name
+                )));
+            }
+        }
+
         // Validate default value
         if let Some(default) = &self.default {
             if let Some(datatype) = default.datatype() {
@@ -151,6 +248,32 @@ impl Column {
         }
 
         // Validate references
+        if self.references.is_none()
+            && (self.on_delete != ReferenceAction::Restrict
+                || self.on_update != ReferenceAction::Restrict)
+        {
+            return Err(Error::Value(format!(
+                "Column {} has a referential action but no reference",
+                self.name
+            )));
+        }
+        for action in [&self.on_delete, &self.on_update] {
+            match action {
+                ReferenceAction::SetNull if !self.nullable => {
+                    return Err(Error::Value(format!(
+                        "Can't use SET NULL for non-nullable column {}",
+                        self.name
+                    )))
+                }
+                ReferenceAction::SetDefault if self.default.is_none() => {
+                    return Err(Error::Value(format!(
+                        "Can't use SET DEFAULT for column {} without a default value",
+                        self.name
+                    )))
+                }
+                _ => {}
+            }
+        }
         if let Some(reference) = &self.references {
             let target = if reference == &table.name {
                 table.clone()
@@ -162,6 +285,12 @@ impl Column {
                     reference, self.name
                 )));
             };
+            if target.get_primary_key_columns().len() > 1 {
+                return Err(Error::Value(format!(
+                    "Can't reference table {} with composite primary key from column {}",
+                    target.name, self.name
+                )));
+            }
             if self.datatype != target.get_primary_key()?.datatype {
                 return Err(Error::Value(format!(
                     "Can't reference {} primary key of table {} from {} column {}",
@@ -180,7 +309,7 @@ impl Column {
     pub fn validate_value(
         &self,
         table: &Table,
-        pk: &Value,
+        pk: &[Value],
         value: &Value,
         txn: &mut dyn Transaction,
     ) -> Result<()> {
@@ -196,10 +325,11 @@ impl Column {
         }?;
 
         // Validate value
-        match value {
-            Value::String(s) if s.len() > 1024 => {
-                Err(Error::Value("Strings cannot be more than 1024 bytes".into()))
-            }
+        match (value, self.max_length) {
+            (Value::String(s), Some(max)) if s.chars().count() > max => Err(Error::Value(format!(
+                "Value for column {} is longer than {} characters",
+                self.name, max
+            ))),
             _ => Ok(()),
         }?;
 
@@ -208,8 +338,8 @@ impl Column {
             match value {
                 Value::Null => Ok(()),
                 Value::Float(f) if f.is_nan() => Ok(()),
-                v if target == &table.name && v == pk => Ok(()),
-                v if txn.read(target, v)?.is_none() => Err(Error::Value(format!(
+                v if target == &table.name && pk == slice::from_ref(v) => Ok(()),
+                v if txn.read(target, slice::from_ref(v))?.is_none() => Err(Error::Value(format!(
                     "Referenced primary key {} in table {} does not exist",
                     v, target,
                 ))),
@@ -219,18 +349,13 @@ impl Column {
 
         // Validate uniqueness constraints
         if self.unique && !self.primary_key && value != &Value::Null {
-            let index = table.get_column_index(&self.name)?;
-            let mut scan = txn.scan(&table.name, None)?;
-            while let Some(row) = scan.next().transpose()? {
-                if row.get(index).unwrap_or(&Value::Null) == value
-                    && &table.get_row_key(&row)? != pk
-                {
-                    return Err(Error::Value(format!(
-                        "Unique value {} already exists for column {}",
-                        value, self.name
-                    )));
-                }
-            }
+            match txn.read_unique(&table.name, &self.name, value)? {
+                Some(id) if id != pk => Err(Error::Value(format!(
+                    "Unique value {} already exists for column {}",
+                    value, self.name
+                ))),
+                _ => Ok(()),
+            }?;
         }
 
         Ok(())
@@ -240,7 +365,13 @@ impl Column {
 impl Display for Column {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut sql = self.name.clone();
-        sql += &format!(" {}", self.datatype);
+        match self.max_length {
+            Some(max_length) => sql += &format!(" VARCHAR({})", max_length),
+            None => sql += &format!(" {}", self.datatype),
+        }
+        if self.sequence.is_some() {
+            sql += " AUTO_INCREMENT";
+        }
         if self.primary_key {
             sql += " PRIMARY KEY";
         }
@@ -255,6 +386,12 @@ impl Display for Column {
         }
         if let Some(reference) = &self.references {
             sql += &format!(" REFERENCES {}", reference);
+            if self.on_delete != ReferenceAction::Restrict {
+                sql += &format!(" ON DELETE {}", self.on_delete);
+            }
+            if self.on_update != ReferenceAction::Restrict {
+                sql += &format!(" ON UPDATE {}", self.on_update);
+            }
         }
         if self.index {
             sql += " INDEX";
@@ -262,3 +399,28 @@ impl Display for Column {
         write!(f, "{}", sql)
     }
 }
+
+/// A referential action, taken on rows referencing a deleted or updated primary key
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub enum ReferenceAction {
+    /// Refuse to delete or update the referenced row
+    #[default]
+    Restrict,
+    /// Delete the referencing rows, or update their references to the new primary key
+    Cascade,
+    /// Set the referencing column to NULL
+    SetNull,
+    /// Set the referencing column to its default value
+    SetDefault,
+}
+
+impl Display for ReferenceAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Restrict => "RESTRICT",
+            Self::Cascade => "CASCADE",
+            Self::SetNull => "SET NULL",
+            Self::SetDefault => "SET DEFAULT",
+        })
+    }
+}
@@ -0,0 +1,25 @@
+use std::fmt::Display;
+
+use serde_derive::{Deserialize, Serialize};
+
+/// A view schema. Views are stored as the SQL text of their SELECT query, which is planned
+/// whenever the view is queried, such that it follows changes to the underlying tables.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct View {
+    pub name: String,
+    /// The SQL text of the view query
+    pub query: String,
+}
+
+impl View {
+    /// Creates a new view schema
+    pub fn new(name: String, query: String) -> Self {
+        Self { name, query }
+    }
+}
+
+impl Display for View {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "CREATE VIEW {} AS {}", self.name, self.query)
+    }
+}
@@ -9,6 +9,8 @@ use super::{engine::Engine, execution::ResultSet};
 pub struct Session<E: Engine> {
     pub engine: E,
     pub txn: Option<E::Transaction>,
+    /// The maximum number of iterations of WITH RECURSIVE queries.
+    pub max_recursion: u64,
 }
 
 impl<E: Engine + 'static> Session<E> {
@@ -53,22 +55,47 @@ impl<E: Engine + 'static> Session<E> {
                 txn.rollback()?;
                 Ok(ResultSet::Rollback { version })
             }
-            ast::Statement::Explain(_) => self.read_with_txn(|_txn| {
-                unimplemented!()
-            }),
-            statement if self.txn.is_some() => Plan::build(statement, self.txn.as_mut().unwrap())?
-                .optimize(self.txn.as_mut().unwrap())?
-                .execute(self.txn.as_mut().unwrap()),
-            statement @ ast::Statement::Select { .. } => {
+            ast::Statement::Explain { statement, analyze: false } => {
+                self.read_with_txn(|txn| Ok(Plan::build(*statement, txn)?.optimize(txn)?.explain()))
+            }
+            ast::Statement::Explain { statement, analyze: true } => {
+                self.execute_plan(*statement, |plan, txn| plan.analyze(txn))
+            }
+            statement => self.execute_plan(statement, |plan, txn| plan.execute(txn)),
+        }
+    }
+
+    /// Builds and optimizes the plan for a statement, and runs it with the given closure. Runs in
+    /// the session's transaction if any, otherwise in a read-only transaction for SELECT and an
+    /// implicit read-write transaction for other statements.
+    fn execute_plan<F>(&mut self, statement: ast::Statement, f: F) -> Result<ResultSet>
+    where
+        F: FnOnce(Plan, &mut E::Transaction) -> Result<ResultSet>,
+    {
+        let max_recursion = self.max_recursion;
+        match statement {
+            statement if self.txn.is_some() => {
+                let txn = self.txn.as_mut().unwrap();
+                let plan =
+                    Plan::build(statement, txn)?.with_max_recursion(max_recursion)?.optimize(txn)?;
+                f(plan, txn)
+            }
+            statement @ (ast::Statement::Select { .. } | ast::Statement::SetOperation { .. }) => {
                 let mut txn = self.engine.begin_read_only()?;
-                let result =
-                    Plan::build(statement, &mut txn)?.optimize(&mut txn)?.execute(&mut txn);
+                let result = Plan::build(statement, &mut txn)
+                    .and_then(|plan| plan.with_max_recursion(max_recursion))
+                    .and_then(|plan| plan.optimize(&mut txn))
+                    .and_then(|plan| f(plan, &mut txn));
                 txn.rollback()?;
                 result
             }
             statement => {
                 let mut txn = self.engine.begin()?;
-                match Plan::build(statement, &mut txn)?.optimize(&mut txn)?.execute(&mut txn) {
+                match Plan::build(statement, &mut txn)
+                    .and_then(|plan| plan.with_max_recursion(max_recursion))
+                    .and_then(|plan| plan.optimize(&mut txn))
+                    .and_then(|plan| f(plan, &mut txn))
+                {
                     Ok(result) => {
                         txn.commit()?;
                         Ok(result)
@@ -98,8 +125,53 @@ impl<E: Engine + 'static> Session<E> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{error::Result, storage::engine::bitcask::Bitcask, sql::engine::{bitcask::KV, Engine}};
+    use super::Session;
+    use crate::{
+        error::{Error, Result},
+        sql::{
+            engine::{bitcask::KV, Engine, Transaction},
+            execution::ResultSet,
+            parser::Parser,
+            plan::Plan,
+            schema::catalog::Catalog,
+            types::{Column, Row, Value},
+        },
+        storage::engine::bitcask::Bitcask,
+    };
 
+    /// Sets up a session on a temporary Bitcask engine, running the given setup queries.
+    fn setup(queries: &[&str]) -> Result<(tempdir::TempDir, Session<KV<Bitcask>>)> {
+        let dir = tempdir::TempDir::new("waterdb")?;
+        let engine = KV::new(Bitcask::new(dir.path().join("waterdb"))?);
+        let mut session = engine.session()?;
+        for query in queries {
+            session.execute(query)?;
+        }
+        Ok((dir, session))
+    }
+
+    /// Runs a query and collects the resulting rows.
+    fn query(session: &mut Session<KV<Bitcask>>, query: &str) -> Result<Vec<Row>> {
+        match session.execute(query)? {
+            ResultSet::Query { rows, .. } => rows.collect(),
+            r => panic!("Unexpected result {:?}", r),
+        }
+    }
+
+    /// Runs a query and collects the resulting rows in sorted order.
+    fn query_sorted(session: &mut Session<KV<Bitcask>>, query: &str) -> Result<Vec<Row>> {
+        let mut rows = self::query(session, query)?;
+        rows.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        Ok(rows)
+    }
+
+    /// Builds and optimizes the plan for a query, without executing it.
+    fn plan(session: &mut Session<KV<Bitcask>>, query: &str) -> Result<String> {
+        let mut txn = session.engine.begin()?;
+        let plan = Plan::build(Parser::new(query).parse()?, &mut txn)?.optimize(&mut txn)?;
+        txn.rollback()?;
+        Ok(plan.to_string())
+    }
 
     #[test]
     fn test() -> Result<()> {
@@ -114,7 +186,7 @@ mod tests {
             "INSERT INTO t VALUES (1, 'haha')",
             "INSERT INTO t VALUES (2, 'nana')",
             "INSERT INTO t VALUES (3, 'gaga')",
-            "UPDATE t SET id=3 WHERE id=1",
+            "UPDATE t SET id=4 WHERE id=1",
             "SELECT * FROM t;"
         ];
         for query in queries {
@@ -124,4 +196,2113 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn join() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE genre (id INT PRIMARY KEY, name STRING)",
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, genre_id INT REFERENCES genre)",
+            "INSERT INTO genre VALUES (1, 'drama'), (2, 'comedy'), (3, 'horror')",
+            "INSERT INTO movie VALUES (1, 'a', 1), (2, 'b', 2), (3, 'c', 1), (4, 'd', NULL)",
+        ])?;
+        let (i, s, n) = (Value::Integer, |s: &str| Value::String(s.into()), Value::Null);
+
+        assert_eq!(query(&mut session, "SELECT m.id FROM movie m CROSS JOIN genre g")?.len(), 12);
+        assert_eq!(query(&mut session, "SELECT m.id, g.id FROM movie m, genre g")?.len(), 12);
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT m.title, g.name FROM movie m JOIN genre g ON m.genre_id = g.id"
+            )?,
+            vec![vec![s("a"), s("drama")], vec![s("b"), s("comedy")], vec![s("c"), s("drama")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT m.id, g.id FROM movie m LEFT JOIN genre g ON m.genre_id = g.id"
+            )?,
+            vec![vec![i(1), i(1)], vec![i(2), i(2)], vec![i(3), i(1)], vec![i(4), n.clone()]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT * FROM movie m RIGHT JOIN genre g ON m.genre_id = g.id"
+            )?,
+            vec![
+                vec![n.clone(), n.clone(), n, i(3), s("horror")],
+                vec![i(1), s("a"), i(1), i(1), s("drama")],
+                vec![i(2), s("b"), i(2), i(2), s("comedy")],
+                vec![i(3), s("c"), i(1), i(1), s("drama")],
+            ],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT title FROM movie, genre WHERE genre_id = genre.id AND name = 'comedy'"
+            )?,
+            vec![vec![s("b")]],
+        );
+        assert!(session.execute("SELECT id FROM movie, genre").is_err());
+        assert!(session.execute("SELECT * FROM movie JOIN movie ON TRUE").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn hash_join() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE a (id INT PRIMARY KEY, v INT)",
+            "CREATE TABLE b (id INT PRIMARY KEY, v INT)",
+            "INSERT INTO a VALUES (1, 10), (2, 20), (3, NULL)",
+            "INSERT INTO b VALUES (1, 10), (2, 10), (3, 30), (4, NULL), (5, 40)",
+        ])?;
+        let (i, n) = (Value::Integer, Value::Null);
+
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, b.id FROM a JOIN b ON a.v = b.v")?,
+            vec![vec![i(1), i(1)], vec![i(1), i(2)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, b.id FROM a LEFT JOIN b ON b.v = a.v")?,
+            vec![vec![i(1), i(1)], vec![i(1), i(2)], vec![i(2), n.clone()], vec![i(3), n.clone()]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, b.id FROM a RIGHT JOIN b ON a.v = b.v")?,
+            vec![
+                vec![n.clone(), i(3)],
+                vec![n.clone(), i(4)],
+                vec![n.clone(), i(5)],
+                vec![i(1), i(1)],
+                vec![i(1), i(2)],
+            ],
+        );
+        let full = vec![
+            vec![n.clone(), i(3)],
+            vec![n.clone(), i(4)],
+            vec![n.clone(), i(5)],
+            vec![i(1), i(1)],
+            vec![i(1), i(2)],
+            vec![i(2), n.clone()],
+            vec![i(3), n.clone()],
+        ];
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, b.id FROM a FULL JOIN b ON a.v = b.v")?,
+            full,
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, b.id FROM b FULL OUTER JOIN a ON a.v = b.v")?,
+            full,
+        );
+        // A non-equijoin full outer join is executed as a nested loop join.
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT a.id, b.id FROM a FULL JOIN b ON a.v = b.v AND a.v IS NOT NULL"
+            )?,
+            full,
+        );
+
+        // Keys of different numeric types match when they're numerically equal, like they do
+        // in a nested loop join.
+        session.execute("CREATE TABLE f (id INT PRIMARY KEY, v FLOAT, d DECIMAL)")?;
+        session.execute("INSERT INTO f VALUES (1, 10.0, 10), (2, -0.0, 20.5), (3, 20.5, 0)")?;
+        session.execute("INSERT INTO a VALUES (4, 0)")?;
+        let sql = "SELECT a.id, f.id FROM a JOIN f ON a.v = f.v";
+        assert!(plan(&mut session, sql)?.contains("HashJoin"));
+        assert_eq!(query_sorted(&mut session, sql)?, vec![vec![i(1), i(1)], vec![i(4), i(2)]]);
+        assert_eq!(
+            query_sorted(&mut session, "SELECT a.id, f.id FROM a JOIN f ON a.v = f.d")?,
+            vec![vec![i(1), i(1)], vec![i(4), i(3)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT f.id, g.id FROM f JOIN f g ON f.v = g.d")?,
+            vec![vec![i(1), i(1)], vec![i(2), i(3)], vec![i(3), i(2)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM a WHERE v IN (SELECT v FROM f)")?,
+            vec![vec![i(1)], vec![i(4)]],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn aggregate() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, genre STRING, rating FLOAT, year INT)",
+            "INSERT INTO movie VALUES (1, 'drama', 8.0, 2001), (2, 'drama', 6.0, 2005), \
+                (3, 'comedy', 7.0, 2001), (4, 'comedy', NULL, 2010), (5, NULL, 5.0, 2005)",
+        ])?;
+        let (i, f, n) = (Value::Integer, Value::Float, Value::Null);
+        let s = |s: &str| Value::String(s.into());
+
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT COUNT(*), COUNT(rating), COUNT(DISTINCT year), SUM(year), AVG(rating), \
+                    MIN(rating), MAX(genre) FROM movie"
+            )?,
+            vec![vec![i(5), i(4), i(3), i(10022), f(6.5), f(5.0), s("drama")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT COUNT(*), SUM(rating), MAX(id) FROM movie WHERE FALSE")?,
+            vec![vec![i(0), n.clone(), n.clone()]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT genre, COUNT(*), AVG(rating) AS avg FROM movie GROUP BY genre"
+            )?,
+            vec![
+                vec![n.clone(), i(1), f(5.0)],
+                vec![s("comedy"), i(2), f(7.0)],
+                vec![s("drama"), i(2), f(7.0)],
+            ],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT year / 10 AS decade, MAX(rating) - MIN(rating) FROM movie GROUP BY decade"
+            )?,
+            vec![vec![i(200), f(3.0)], vec![i(201), n.clone()]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT genre FROM movie GROUP BY genre HAVING COUNT(rating) > 1"
+            )?,
+            vec![vec![s("drama")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT COUNT(*) FROM movie GROUP BY year HAVING year = 2005")?,
+            vec![vec![i(2)]],
+        );
+
+        // NULL group keys hash into a single group, also as part of a composite key.
+        session.execute("INSERT INTO movie VALUES (6, NULL, NULL, 2005), (7, NULL, 9.0, NULL)")?;
+        assert_eq!(
+            query(&mut session, "SELECT genre, COUNT(*) FROM movie WHERE id > 4 GROUP BY genre")?,
+            vec![vec![n.clone(), i(3)]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT genre, year, COUNT(*), COUNT(DISTINCT rating) FROM movie WHERE id > 4 \
+                    GROUP BY genre, year"
+            )?,
+            vec![vec![n.clone(), n.clone(), i(1), i(1)], vec![n.clone(), i(2005), i(2), i(1)]],
+        );
+        assert!(session.execute("SELECT genre, COUNT(*) FROM movie").is_err());
+        assert!(session.execute("SELECT MAX(COUNT(*)) FROM movie").is_err());
+        assert!(session.execute("SELECT SUM(genre) FROM movie").is_err());
+        assert!(session.execute("SELECT * FROM movie GROUP BY genre").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn order_limit() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, rating FLOAT, year INT)",
+            "INSERT INTO movie VALUES (1, 'a', 8.0, 2001), (2, 'b', 6.0, 2005), \
+                (3, 'c', 7.0, 2001), (4, 'd', NULL, 2010), (5, 'e', 5.0, 2005)",
+        ])?;
+        let i = Value::Integer;
+
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY rating")?,
+            vec![vec![i(4)], vec![i(5)], vec![i(2)], vec![i(3)], vec![i(1)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY rating DESC")?,
+            vec![vec![i(1)], vec![i(3)], vec![i(2)], vec![i(5)], vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY year DESC, title ASC")?,
+            vec![vec![i(4)], vec![i(2)], vec![i(5)], vec![i(1)], vec![i(3)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY year, id DESC LIMIT 3")?,
+            vec![vec![i(3)], vec![i(1)], vec![i(5)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY rating DESC LIMIT 2 OFFSET 1")?,
+            vec![vec![i(3)], vec![i(2)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT title FROM movie ORDER BY id DESC LIMIT 1 OFFSET 5")?,
+            Vec::<Vec<Value>>::new(),
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie ORDER BY id LIMIT 0")?,
+            Vec::<Vec<Value>>::new(),
+        );
+        assert_eq!(query(&mut session, "SELECT * FROM movie LIMIT 2")?.len(), 2);
+        assert_eq!(query(&mut session, "SELECT * FROM movie OFFSET 4")?.len(), 1);
+        assert!(session.execute("SELECT * FROM movie LIMIT -1").is_err());
+        assert!(session.execute("SELECT * FROM movie OFFSET 'a'").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn like() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING)",
+            "INSERT INTO movie VALUES (1, 'Star Wars'), (2, 'Stardust'), (3, 'star 100%'), \
+                (4, 'Heat'), (5, NULL)",
+        ])?;
+        let (i, b) = (Value::Integer, Value::Boolean);
+
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title LIKE 'Star%'")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title ILIKE 'star%'")?,
+            vec![vec![i(1)], vec![i(2)], vec![i(3)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title LIKE 'H_at'")?,
+            vec![vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title LIKE '%\\%'")?,
+            vec![vec![i(3)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title ~ '^[SH].*[st]$'")?,
+            vec![vec![i(1)], vec![i(2)], vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE NOT (title LIKE '%St%')")?,
+            vec![vec![i(3)], vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT 'a.c' LIKE 'a_', 'a.c' ~ 'a.c', NULL LIKE 'a', 'a' ~ NULL")?,
+            vec![vec![b(false), b(true), Value::Null, Value::Null]],
+        );
+        assert_eq!(query(&mut session, "SELECT 'ab' ~ 'a.'")?, vec![vec![b(true)]]);
+
+        // NOT LIKE, NOT ILIKE and !~ negate the match, and are NULL for NULL operands. NULL
+        // titles therefore match neither the pattern nor its negation.
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title NOT LIKE 'Star%'")?,
+            vec![vec![i(3)], vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title NOT ILIKE 'star%'")?,
+            vec![vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title !~ '^[SH].*[st]$'")?,
+            vec![vec![i(3)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id FROM movie WHERE id > 3 AND title NOT LIKE 'x' OR id = 1"
+            )?,
+            vec![vec![i(1)], vec![i(4)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT 'ab' NOT LIKE 'a_', 'AB' NOT ILIKE 'a%', 'ab' !~ 'b$', 'a' !~ 'b'"
+            )?,
+            vec![vec![b(false), b(false), b(false), b(true)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT NULL NOT LIKE 'a', 'a' NOT ILIKE NULL, NULL !~ 'a', 'a' !~ NULL"
+            )?,
+            vec![vec![Value::Null, Value::Null, Value::Null, Value::Null]],
+        );
+        assert!(query(&mut session, "SELECT 'a' NOT 'a'").is_err());
+        assert!(query(&mut session, "SELECT 1 LIKE 'a'").is_err());
+        assert!(query(&mut session, "SELECT 'a' LIKE 'a\\'").is_err());
+        assert!(query(&mut session, "SELECT 'a' ~ '('").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn functions() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, rating FLOAT)",
+            "INSERT INTO movie VALUES (1, ' Heat ', 8.3), (2, 'Alien', NULL)",
+        ])?;
+        let (i, f, s, b) = (Value::Integer, Value::Float, Value::String, Value::Boolean);
+        let s = |v: &str| s(v.to_string());
+
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT UPPER(title), LOWER(title), LENGTH(title), TRIM(title), \
+                    SUBSTR(title, 2, 3), REPLACE(title, 'e', 'E') FROM movie WHERE id = 1"
+            )?,
+            vec![vec![s(" HEAT "), s(" heat "), i(6), s("Heat"), s("Hea"), s(" HEat ")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT CONCAT(title, '/', rating), COALESCE(rating, 0.0), IFNULL(rating, -1), \
+                    NULLIF(id, 2) FROM movie ORDER BY id"
+            )?,
+            vec![
+                vec![s(" Heat /8.3"), f(8.3), f(8.3), i(1)],
+                vec![s("Alien/"), f(0.0), i(-1), Value::Null],
+            ],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT ABS(-3), ROUND(2.567, 2), ROUND(1250, -2), FLOOR(-1.5), CEIL(1.2), \
+                    SQRT(16), SUBSTR('abc', 0, 2), UPPER(NULL)"
+            )?,
+            vec![vec![i(3), f(2.57), i(1300), f(-2.0), f(2.0), f(4.0), s("a"), Value::Null]],
+        );
+
+        // Rounding floats to very many or very few digits doesn't overflow.
+        let rounded = query(
+            &mut session,
+            "SELECT ROUND(1.5, 9223372036854775807), ROUND(1e300, -400), ROUND(-1e300, -400), \
+                ROUND(1e300, 2), ROUND(0.125, 400), ROUND(1.5e-320, 321)",
+        )?;
+        assert_eq!(rounded, vec![vec![f(1.5), f(0.0), f(-0.0), f(1e300), f(0.125), f(1.5e-320)]]);
+        assert!(matches!(rounded[0][2], Value::Float(z) if z.is_sign_negative()));
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT CAST('42' AS INT), CAST(2.6 AS INTEGER), CAST(1 AS BOOLEAN), \
+                    CAST(rating AS STRING), CAST(NULL AS FLOAT) FROM movie WHERE id = 1"
+            )?,
+            vec![vec![i(42), i(3), b(true), s("8.3"), Value::Null]],
+        );
+
+        // Unknown functions, arities and known argument types are rejected at plan time.
+        assert!(session.execute("SELECT FOO(1)").is_err());
+        assert!(session.execute("SELECT UPPER('a', 'b')").is_err());
+        assert!(session.execute("SELECT COALESCE()").is_err());
+        assert!(session.execute("SELECT LENGTH(1)").is_err());
+        assert!(session.execute("SELECT ABS(title) FROM movie").is_err());
+        assert!(query(&mut session, "SELECT CAST('a' AS INT)").is_err());
+        assert!(query(&mut session, "SELECT SQRT(-1)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn index() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, genre STRING INDEX, year INT INDEX)",
+            "INSERT INTO movie VALUES (1, 'drama', 2001), (2, 'drama', 2005), \
+                (3, 'comedy', 2001), (4, NULL, 2010)",
+        ])?;
+        let i = Value::Integer;
+
+        assert!(plan(&mut session, "SELECT id FROM movie WHERE genre = 'drama'")?
+            .contains("IndexLookup: movie column genre (drama)"));
+        assert!(plan(&mut session, "SELECT id FROM movie WHERE year IN (2001, 2010) AND id > 1")?
+            .contains("IndexLookup: movie column year (2001, 2010)"));
+        assert!(plan(&mut session, "SELECT id FROM movie WHERE year > 2001")?.contains("Scan:"));
+
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE genre = 'drama'")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT id FROM movie WHERE year IN (2001, 2010) AND id > 1"
+            )?,
+            vec![vec![i(3)], vec![i(4)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE year NOT IN (2001, 2010)")?,
+            vec![vec![i(2)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE genre IS NULL")?,
+            vec![vec![i(4)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE genre = NULL")?,
+            Vec::<Row>::new(),
+        );
+
+        // Indexes are maintained by updates and deletes, including primary key changes.
+        session.execute("UPDATE movie SET genre = 'comedy' WHERE id = 1")?;
+        session.execute("UPDATE movie SET id = 5 WHERE id = 2")?;
+        session.execute("DELETE FROM movie WHERE id = 3")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE genre = 'comedy'")?,
+            vec![vec![i(1)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE genre = 'drama'")?,
+            vec![vec![i(5)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE year = 2001")?,
+            vec![vec![i(1)]],
+        );
+        session.execute("DELETE FROM movie WHERE year = 2005")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie")?,
+            vec![vec![i(1)], vec![i(4)]],
+        );
+
+        // Float index lookups find the same rows as a scan: -0.0 equals 0.0, NaN equals nothing.
+        session.execute("CREATE TABLE f (id INT PRIMARY KEY, v FLOAT INDEX)")?;
+        session.execute("INSERT INTO f VALUES (1, 0.0), (2, -0.0), (3, NAN), (4, 1.0)")?;
+        for (lookup, scan) in [
+            ("v = 0.0", "v >= 0.0 AND v <= 0.0"),
+            ("v = -0.0", "v >= -0.0 AND v <= -0.0"),
+            ("v = NAN", "v >= NAN AND v <= NAN"),
+            ("v IN (NAN, 1.0)", "v >= 1.0"),
+        ] {
+            let lookup = format!("SELECT id FROM f WHERE {}", lookup);
+            let scan = format!("SELECT id FROM f WHERE {}", scan);
+            assert!(plan(&mut session, &lookup)?.contains("IndexLookup"));
+            assert!(plan(&mut session, &scan)?.contains("Scan"));
+            assert_eq!(query_sorted(&mut session, &lookup)?, query_sorted(&mut session, &scan)?);
+        }
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM f WHERE v = 0.0")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        session.execute("DELETE FROM f WHERE v = 0.0")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM f")?,
+            vec![vec![i(3)], vec![i(4)]],
+        );
+
+        // Changes in an uncommitted transaction are not visible to others.
+        session.execute("BEGIN")?;
+        session.execute("INSERT INTO movie VALUES (6, 'drama', 2020)")?;
+        let mut other = session.engine.session()?;
+        assert_eq!(
+            query(&mut other, "SELECT id FROM movie WHERE genre = 'drama'")?,
+            Vec::<Row>::new(),
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE genre = 'drama'")?,
+            vec![vec![i(6)]],
+        );
+        session.execute("ROLLBACK")?;
+        Ok(())
+    }
+
+    #[test]
+    fn key_lookup() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, year INT INDEX)",
+            "INSERT INTO movie VALUES (1, 'a', 2001), (2, 'b', 2005), (3, 'c', 2001)",
+        ])?;
+        let (i, s) = (Value::Integer, |s: &str| Value::String(s.into()));
+
+        assert!(plan(&mut session, "SELECT * FROM movie WHERE id = 2")?
+            .contains("KeyLookup: movie (2)"));
+        assert!(plan(&mut session, "SELECT * FROM movie WHERE year = 2001 AND id = 2")?
+            .contains("KeyLookup: movie (2)"));
+        assert!(plan(&mut session, "UPDATE movie SET title = 'x' WHERE id = 1")?
+            .contains("KeyLookup: movie (1)"));
+        assert!(plan(&mut session, "DELETE FROM movie WHERE id IN (1, 3)")?
+            .contains("KeyLookup: movie (1, 3)"));
+        assert!(plan(&mut session, "SELECT * FROM movie WHERE id = 1.0")?
+            .contains("KeyLookup: movie (1)"));
+        assert!(plan(&mut session, "SELECT * FROM movie WHERE id = 1.5")?.contains("Scan:"));
+
+        assert_eq!(
+            query(&mut session, "SELECT title FROM movie WHERE id = 2")?,
+            vec![vec![s("b")]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE id IN (3, 1, 3, 7)")?,
+            vec![vec![i(1)], vec![i(3)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE id = 1 AND year = 2005")?,
+            Vec::<Row>::new(),
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE id = 1.0")?,
+            vec![vec![i(1)]],
+        );
+
+        session.execute("UPDATE movie SET title = 'x' WHERE id = 1")?;
+        session.execute("DELETE FROM movie WHERE id = 2")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM movie")?,
+            vec![vec![i(1), s("x"), i(2001)], vec![i(3), s("c"), i(2001)]],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn composite_key() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE account (tenant_id INT, id INT, name STRING, \
+                PRIMARY KEY (tenant_id, id))",
+            "INSERT INTO account VALUES (1, 1, 'a'), (1, 2, 'b'), (2, 1, 'c'), (2, 2, 'd')",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        assert!(session.execute("INSERT INTO account VALUES (1, 2, 'x')").is_err());
+        assert!(session.execute("INSERT INTO account VALUES (NULL, 3, 'x')").is_err());
+        session.execute("INSERT INTO account VALUES (3, 2, 'e')")?;
+
+        // Lookups on leading key columns use key (prefix) lookups.
+        let q = "SELECT name FROM account WHERE tenant_id = 2";
+        assert!(plan(&mut session, q)?.contains("KeyLookup: account (2)"));
+        assert_eq!(query(&mut session, q)?, vec![vec![s("c")], vec![s("d")]]);
+        let q = "SELECT name FROM account WHERE id = 2 AND tenant_id IN (1, 3)";
+        assert!(plan(&mut session, q)?.contains("KeyLookup: account ((1, 2), (3, 2))"));
+        assert_eq!(query_sorted(&mut session, q)?, vec![vec![s("b")], vec![s("e")]]);
+        assert!(plan(&mut session, "SELECT * FROM account WHERE id = 1")?.contains("Scan:"));
+
+        // Updates and deletes address rows by their full key.
+        session.execute("UPDATE account SET id = 3 WHERE tenant_id = 1 AND id = 2")?;
+        session.execute("DELETE FROM account WHERE tenant_id = 2 AND id = 1")?;
+        assert_eq!(
+            query(&mut session, "SELECT tenant_id, id FROM account")?,
+            vec![vec![i(1), i(1)], vec![i(1), i(3)], vec![i(2), i(2)], vec![i(3), i(2)]],
+        );
+
+        // The declared key column order is the key order, and can differ from the column order.
+        session
+            .execute("CREATE TABLE event (seq INT, tenant_id INT, PRIMARY KEY (tenant_id, seq))")?;
+        session.execute("INSERT INTO event VALUES (1, 2), (2, 1), (3, 1), (1, 1)")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM event")?,
+            vec![vec![i(1), i(1)], vec![i(2), i(1)], vec![i(3), i(1)], vec![i(1), i(2)]],
+        );
+        let q = "SELECT seq FROM event WHERE tenant_id = 1";
+        assert!(plan(&mut session, q)?.contains("KeyLookup: event (1)"));
+        assert_eq!(query(&mut session, q)?, vec![vec![i(1)], vec![i(2)], vec![i(3)]]);
+        let q = "SELECT seq FROM event WHERE seq = 3 AND tenant_id = 1";
+        assert!(plan(&mut session, q)?.contains("KeyLookup: event ((1, 3))"));
+        assert_eq!(query(&mut session, q)?, vec![vec![i(3)]]);
+        assert!(session.execute("INSERT INTO event VALUES (3, 1)").is_err());
+        session.execute("UPDATE event SET seq = 4 WHERE tenant_id = 2")?;
+        session.execute("ALTER TABLE event ADD COLUMN note STRING")?;
+        session.execute("ALTER TABLE event DROP COLUMN note")?;
+        session.execute("DELETE FROM event WHERE tenant_id = 1 AND seq = 2")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM event")?,
+            vec![vec![i(1), i(1)], vec![i(3), i(1)], vec![i(4), i(2)]],
+        );
+        let txn = session.engine.begin()?;
+        assert!(txn.must_read_table("event")?.to_string().contains("PRIMARY KEY (tenant_id, seq)"));
+        txn.rollback()?;
+
+        assert!(session
+            .execute("CREATE TABLE a (x INT, y INT PRIMARY KEY, PRIMARY KEY (x, y))")
+            .is_err());
+        assert!(session.execute("CREATE TABLE a (x INT, y INT, PRIMARY KEY (x, x))").is_err());
+        assert!(session.execute("CREATE TABLE a (x INT, y INT, PRIMARY KEY (x, z))").is_err());
+        assert!(session.execute("CREATE TABLE a (x INT PRIMARY KEY, y INT PRIMARY KEY)").is_err());
+        assert!(session
+            .execute("CREATE TABLE a (x INT PRIMARY KEY, y INT REFERENCES account)")
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn optimize() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE a (id INT PRIMARY KEY, v INT)",
+            "CREATE TABLE b (id INT PRIMARY KEY, v INT)",
+            "CREATE TABLE c (id INT PRIMARY KEY, v INT)",
+            "INSERT INTO a VALUES (1, 10), (2, 20)",
+            "INSERT INTO b VALUES (1, 100), (3, 300)",
+            "INSERT INTO c VALUES (1, 1000), (2, 2000), (3, 3000)",
+        ])?;
+        let (i, n) = (Value::Integer, Value::Null);
+
+        // Joins are reordered to avoid cross products, keeping the original column order.
+        let q = "SELECT * FROM a, b, c WHERE a.id = c.id AND b.id = c.id AND c.v > 1 + 1";
+        assert!(plan(&mut session, q)?.contains("Scan: c (c.v > 2)"));
+        assert_eq!(query(&mut session, q)?, vec![vec![i(1), i(10), i(1), i(100), i(1), i(1000)]]);
+
+        // Always-false filters don't read any rows, but keep the result columns.
+        assert!(plan(&mut session, "SELECT * FROM a WHERE 1 = 2")?.contains("Nothing"));
+        assert!(plan(&mut session, "SELECT 1 WHERE NULL")?.contains("Nothing"));
+        match session.execute("SELECT * FROM a WHERE 1 = 2")? {
+            ResultSet::Query { columns, rows } => {
+                assert_eq!(columns.len(), 2);
+                assert_eq!(columns[1].name.as_deref(), Some("v"));
+                assert_eq!(rows.count(), 0);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+        assert_eq!(
+            query_sorted(&mut session, "SELECT * FROM a LEFT JOIN b ON FALSE")?,
+            vec![vec![i(1), i(10), n.clone(), n.clone()], vec![i(2), i(20), n.clone(), n]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT a.id FROM a WHERE TRUE AND a.v = 20")?,
+            vec![vec![i(2)]],
+        );
+        assert!(query(&mut session, "SELECT 1 WHERE FALSE")?.is_empty());
+        assert_eq!(query(&mut session, "SELECT COUNT(*) FROM a WHERE 1 = 2")?, vec![vec![i(0)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn explain() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, year INT)",
+            "INSERT INTO movie VALUES (1, 'a', 2001), (2, 'b', 2005), (3, 'c', 2001)",
+        ])?;
+        let s = |s: &str| Value::String(s.into());
+
+        let q = "SELECT title FROM movie WHERE year = 2001 ORDER BY title";
+        match session.execute(&format!("EXPLAIN {}", q))? {
+            ResultSet::Query { columns, rows } => {
+                assert_eq!(columns, vec![Column { name: Some("plan".into()) }]);
+                let rows = rows.collect::<Result<Vec<_>>>()?;
+                let expect = plan(&mut session, q)?;
+                assert_eq!(rows, expect.lines().map(|l| vec![s(l)]).collect::<Vec<_>>());
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        // EXPLAIN ANALYZE annotates each node with the number of rows it produced.
+        let lines: Vec<String> = query(&mut session, &format!("EXPLAIN ANALYZE {}", q))?
+            .into_iter()
+            .map(|row| row[0].to_string())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Order: movie.title asc (rows: 2, time: "), "{}", lines[0]);
+        assert!(lines[1].starts_with("└─ Projection: title (rows: 2, time: "), "{}", lines[1]);
+        assert!(lines[2].starts_with("   └─ Scan: movie (year = 2001) (rows: 2, time: "));
+
+        // EXPLAIN ANALYZE executes mutations, EXPLAIN doesn't.
+        query(&mut session, "EXPLAIN DELETE FROM movie WHERE year = 2001")?;
+        assert_eq!(query(&mut session, "SELECT * FROM movie")?.len(), 3);
+        let lines = query(&mut session, "EXPLAIN ANALYZE DELETE FROM movie WHERE year = 2001")?;
+        assert!(lines[0][0].to_string().starts_with("Delete: movie (rows: 2, time: "));
+        assert_eq!(query(&mut session, "SELECT * FROM movie")?.len(), 1);
+
+        assert!(session.execute("EXPLAIN EXPLAIN SELECT * FROM movie").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn alter_table() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE genre (id INT PRIMARY KEY, name STRING)",
+            "CREATE TABLE movie (id INT PRIMARY KEY, title STRING, genre_id INT REFERENCES genre)",
+            "INSERT INTO genre VALUES (1, 'drama'), (2, 'comedy')",
+            "INSERT INTO movie VALUES (1, 'a', 1), (2, 'b', 2)",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // Added columns take their default value, and can be indexed.
+        session.execute("ALTER TABLE movie ADD COLUMN year INT NOT NULL DEFAULT 2000 INDEX")?;
+        session.execute("ALTER TABLE movie ADD rating FLOAT")?;
+        session.execute("INSERT INTO movie VALUES (3, 'c', 1, 2010, 7.5)")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM movie WHERE id = 1")?,
+            vec![vec![i(1), s("a"), i(1), i(2000), Value::Null]],
+        );
+        assert!(plan(&mut session, "SELECT id FROM movie WHERE year = 2000")?
+            .contains("IndexLookup: movie column year (2000)"));
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM movie WHERE year = 2000")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        assert!(session.execute("ALTER TABLE movie ADD COLUMN score INT NOT NULL").is_err());
+        assert!(session.execute("ALTER TABLE movie ADD COLUMN title STRING").is_err());
+        assert!(session.execute("ALTER TABLE movie ADD COLUMN g INT DEFAULT 9 REFERENCES genre")
+            .is_err());
+
+        // Renamed columns keep their values and index.
+        session.execute("ALTER TABLE movie RENAME COLUMN year TO released")?;
+        assert!(session.execute("SELECT year FROM movie").is_err());
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE released = 2010")?,
+            vec![vec![i(3)]],
+        );
+        assert!(session.execute("ALTER TABLE movie RENAME COLUMN title TO id").is_err());
+
+        // Dropped columns are removed from existing rows.
+        session.execute("ALTER TABLE movie DROP COLUMN rating")?;
+        session.execute("ALTER TABLE movie DROP title")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM movie WHERE id = 3")?,
+            vec![vec![i(3), i(1), i(2010)]],
+        );
+        assert!(session.execute("ALTER TABLE movie DROP COLUMN id").is_err());
+
+        // Renamed tables move their rows, indexes and incoming references.
+        session.execute("ALTER TABLE genre RENAME TO category")?;
+        assert!(session.execute("SELECT * FROM genre").is_err());
+        assert_eq!(
+            query(&mut session, "SELECT name FROM category WHERE id = 2")?,
+            vec![vec![s("comedy")]],
+        );
+        assert!(session.execute("DELETE FROM category WHERE id = 1").is_err());
+        assert!(session.execute("INSERT INTO movie VALUES (4, 3, 2000)").is_err());
+        session.execute("ALTER TABLE movie RENAME TO film")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM film WHERE released = 2000")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        assert!(session.execute("ALTER TABLE film RENAME TO category").is_err());
+
+        // Schema changes are transactional.
+        session.execute("BEGIN")?;
+        session.execute("ALTER TABLE film DROP COLUMN released")?;
+        session.execute("ROLLBACK")?;
+        assert_eq!(
+            query(&mut session, "SELECT released FROM film WHERE id = 3")?,
+            vec![vec![i(2010)]],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn unique() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE user (id INT PRIMARY KEY, email STRING UNIQUE, score FLOAT UNIQUE)",
+            "INSERT INTO user VALUES (1, 'a@x', 0.0), (2, 'b@x', NULL), (3, NULL, NULL)",
+        ])?;
+        let i = Value::Integer;
+        let duplicate = |v: &str, c: &str| {
+            Error::Value(format!("Unique value {} already exists for column {}", v, c))
+        };
+
+        // Duplicate values are rejected, but NULLs and NaNs never conflict.
+        assert_eq!(
+            session.execute("INSERT INTO user VALUES (4, 'a@x', 2.0)").err(),
+            Some(duplicate("a@x", "email"))
+        );
+        assert_eq!(
+            session.execute("INSERT INTO user VALUES (4, 'c@x', -0.0)").err(),
+            Some(duplicate("-0", "score"))
+        );
+        session.execute("INSERT INTO user VALUES (4, NULL, NAN), (5, 'c@x', NAN)")?;
+        assert_eq!(
+            session.execute("INSERT INTO user VALUES (6, 'd@x', 3.0), (7, 'd@x', 4.0)").err(),
+            Some(duplicate("d@x", "email"))
+        );
+
+        // Updates may keep their own value, but not take another row's.
+        session.execute("UPDATE user SET email = 'a@x', score = 5.0 WHERE id = 1")?;
+        assert_eq!(
+            session.execute("UPDATE user SET email = 'b@x' WHERE id = 1").err(),
+            Some(duplicate("b@x", "email"))
+        );
+        session.execute("UPDATE user SET email = 'e@x' WHERE id = 1")?;
+        session.execute("INSERT INTO user VALUES (6, 'a@x', 1.0)")?;
+        session.execute("UPDATE user SET id = 7 WHERE id = 6")?;
+
+        // Deleted values can be reused.
+        session.execute("DELETE FROM user WHERE id = 2")?;
+        session.execute("INSERT INTO user VALUES (2, 'b@x', NULL)")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT id FROM user WHERE email IS NOT NULL")?,
+            vec![vec![i(1)], vec![i(2)], vec![i(5)], vec![i(7)]],
+        );
+
+        // Constraints follow schema changes.
+        session.execute("ALTER TABLE user RENAME COLUMN email TO mail")?;
+        assert_eq!(
+            session.execute("INSERT INTO user VALUES (8, 'b@x', NULL)").err(),
+            Some(duplicate("b@x", "mail"))
+        );
+        session.execute("ALTER TABLE user RENAME TO account")?;
+        assert_eq!(
+            session.execute("UPDATE account SET mail = 'c@x' WHERE id = 2").err(),
+            Some(duplicate("c@x", "mail"))
+        );
+        assert_eq!(
+            session.execute("ALTER TABLE account ADD COLUMN code INT DEFAULT 1 UNIQUE").err(),
+            Some(duplicate("1", "code"))
+        );
+        session.execute("ALTER TABLE account DROP COLUMN mail")?;
+        session.execute("ALTER TABLE account ADD COLUMN mail STRING DEFAULT NULL UNIQUE")?;
+        session.execute("UPDATE account SET mail = 'b@x' WHERE id = 1")?;
+        Ok(())
+    }
+
+    #[test]
+    fn check() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE product (
+                id INT PRIMARY KEY,
+                price FLOAT NOT NULL CHECK (price > 0.0),
+                discount FLOAT CHECK (discount >= 0.0) CHECK (discount < 100.0),
+                CHECK (discount IS NULL OR discount < price)
+            )",
+            "INSERT INTO product VALUES (1, 10.0, NULL), (2, 20.0, 5.0)",
+        ])?;
+        let (i, f) = (Value::Integer, Value::Float);
+        let violated = |check: &str| {
+            Some(Error::Value(format!("Check constraint {} violated for table product", check)))
+        };
+
+        // Column and table checks are evaluated on inserts and updates.
+        assert_eq!(
+            session.execute("INSERT INTO product VALUES (3, 0.0, NULL)").err(),
+            violated("price > 0")
+        );
+        assert_eq!(
+            session.execute("INSERT INTO product VALUES (3, 1.0, 2.0)").err(),
+            violated("discount IS NULL OR discount < price")
+        );
+        assert_eq!(
+            session.execute("UPDATE product SET discount = 100.0 WHERE id = 1").err(),
+            violated("discount < 100")
+        );
+        session.execute("UPDATE product SET discount = 9.0 WHERE id = 1")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT * FROM product")?,
+            vec![vec![i(1), f(10.0), f(9.0)], vec![i(2), f(20.0), f(5.0)]],
+        );
+
+        // Checks must be boolean and can only refer to the table's own columns.
+        assert!(session.execute("CREATE TABLE a (id INT PRIMARY KEY CHECK (x > 0))").is_err());
+        assert!(session
+            .execute("CREATE TABLE a (id INT PRIMARY KEY CHECK (COUNT(id) > 0))")
+            .is_err());
+        assert_eq!(
+            session.execute("CREATE TABLE a (id INT PRIMARY KEY CHECK (id IN (SELECT 1)))").err(),
+            Some(Error::Value("Subqueries are not supported in CHECK constraints".into()))
+        );
+        session.execute("CREATE TABLE a (id INT PRIMARY KEY, CHECK (id + 1))")?;
+        assert_eq!(
+            session.execute("INSERT INTO a VALUES (1)").err(),
+            Some(Error::Value("Check constraint id + 1 returned 2, expected boolean".into()))
+        );
+
+        // Checks follow schema changes, and are dropped along with their columns.
+        session.execute("ALTER TABLE product ADD COLUMN stock INT DEFAULT 0 CHECK (stock >= 0)")?;
+        assert_eq!(
+            session.execute("UPDATE product SET stock = -1 WHERE id = 1").err(),
+            violated("stock > 0 OR stock = 0")
+        );
+        assert!(session.execute("ALTER TABLE product ADD COLUMN x INT DEFAULT 0 CHECK (x > 0)")
+            .is_err());
+        assert!(session
+            .execute("ALTER TABLE product ADD COLUMN x INT CHECK (EXISTS (SELECT 1 WHERE x > 0))")
+            .is_err());
+        session.execute("ALTER TABLE product RENAME COLUMN price TO cost")?;
+        assert_eq!(
+            session.execute("INSERT INTO product VALUES (3, -1.0, NULL, 0)").err(),
+            violated("cost > 0")
+        );
+        session.execute("ALTER TABLE product DROP COLUMN discount")?;
+        assert_eq!(
+            session.execute("INSERT INTO product VALUES (3, 1.0, -5)").err(),
+            violated("stock > 0 OR stock = 0")
+        );
+        session.execute("INSERT INTO product VALUES (3, 1.0, 5)")?;
+        Ok(())
+    }
+
+    #[test]
+    fn reference_actions() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE genre (id INT PRIMARY KEY, name STRING)",
+            "CREATE TABLE movie (
+                id INT PRIMARY KEY,
+                genre_id INT REFERENCES genre ON DELETE CASCADE ON UPDATE CASCADE,
+                sequel_of INT DEFAULT 0 REFERENCES movie ON DELETE SET DEFAULT ON UPDATE SET NULL
+            )",
+            "CREATE TABLE review (
+                id INT PRIMARY KEY,
+                movie_id INT REFERENCES movie ON DELETE SET NULL ON UPDATE NO ACTION
+            )",
+            "INSERT INTO genre VALUES (1, 'drama'), (2, 'comedy'), (3, 'horror')",
+            "INSERT INTO movie VALUES (0, 3, NULL), (1, 1, NULL), (2, 1, 1), (3, 2, 2)",
+            "INSERT INTO review VALUES (1, 1), (2, 3)",
+        ])?;
+        let i = Value::Integer;
+
+        // Deletes cascade through references, setting defaults and NULLs along the way.
+        session.execute("DELETE FROM genre WHERE id = 1")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT * FROM movie")?,
+            vec![vec![i(0), i(3), Value::Null], vec![i(3), i(2), i(0)]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT * FROM review")?,
+            vec![vec![i(1), Value::Null], vec![i(2), i(3)]],
+        );
+
+        // Primary key updates cascade, set NULL or are restricted.
+        session.execute("UPDATE genre SET id = 4 WHERE id = 2")?;
+        assert_eq!(
+            query(&mut session, "SELECT genre_id FROM movie WHERE id = 3")?,
+            vec![vec![i(4)]],
+        );
+        assert_eq!(
+            session.execute("UPDATE movie SET id = 5 WHERE id = 3").err(),
+            Some(Error::Value("Primary key 3 is referenced by table review column movie_id".into()))
+        );
+        session.execute("DELETE FROM review WHERE id = 2")?;
+        session.execute("UPDATE movie SET id = 5 WHERE id = 0")?;
+        assert_eq!(
+            query(&mut session, "SELECT sequel_of FROM movie WHERE id = 3")?,
+            vec![vec![Value::Null]],
+        );
+
+        // Foreign key columns are indexed, such that references are looked up in the index
+        // rather than by scanning the referencing table. Primary key columns aren't indexed, and
+        // are scanned instead.
+        assert!(plan(&mut session, "SELECT id FROM movie WHERE genre_id = 4")?
+            .contains("IndexLookup: movie column genre_id (4)"));
+        session.execute("CREATE TABLE detail (id INT PRIMARY KEY REFERENCES movie, notes STRING)")?;
+        session.execute("INSERT INTO detail VALUES (5, 'x')")?;
+        assert_eq!(
+            session.execute("DELETE FROM movie WHERE id = 5").err(),
+            Some(Error::Value("Primary key 5 is referenced by table detail column id".into()))
+        );
+        session.execute("DELETE FROM genre WHERE id = 4")?;
+        assert_eq!(query(&mut session, "SELECT id FROM movie")?, vec![vec![i(5)]]);
+
+        // Actions must be compatible with the column.
+        for query in [
+            "CREATE TABLE a (id INT PRIMARY KEY, g INT NOT NULL REFERENCES genre \
+             ON DELETE SET NULL)",
+            "CREATE TABLE a (id INT PRIMARY KEY, g INT NOT NULL REFERENCES genre \
+             ON UPDATE SET DEFAULT)",
+            "CREATE TABLE a (id INT PRIMARY KEY, g INT REFERENCES genre ON DELETE NO ACTION \
+             ON DELETE CASCADE)",
+        ] {
+            assert!(session.execute(query).is_err(), "{}", query);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sequences() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE movie (id SERIAL PRIMARY KEY, title STRING NOT NULL)",
+            "CREATE TABLE genre (id INT AUTO_INCREMENT PRIMARY KEY, name STRING NOT NULL)",
+            "CREATE SEQUENCE countdown START WITH 10 INCREMENT BY -3",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // Auto-increment columns are filled in when no value is given.
+        session.execute("INSERT INTO movie (title) VALUES ('a'), ('b')")?;
+        session.execute("INSERT INTO movie VALUES (10, 'c')")?;
+        session.execute("INSERT INTO genre (name) VALUES ('drama')")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT * FROM movie")?,
+            vec![vec![i(1), s("a")], vec![i(2), s("b")], vec![i(10), s("c")]],
+        );
+        assert_eq!(query(&mut session, "SELECT id FROM genre")?, vec![vec![i(1)]]);
+
+        // Sequence values are never reused, even across rollbacks.
+        session.execute("BEGIN")?;
+        session.execute("INSERT INTO movie (title) VALUES ('d')")?;
+        session.execute("ROLLBACK")?;
+        session.execute("INSERT INTO movie (title) VALUES ('e')")?;
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title = 'e'")?,
+            vec![vec![i(4)]]
+        );
+
+        // NEXTVAL() yields a new value per row, and is never folded into a constant.
+        assert_eq!(
+            query(&mut session, "SELECT NEXTVAL('countdown'), NEXTVAL('countdown') * 2")?,
+            vec![vec![i(10), i(14)]],
+        );
+        assert!(plan(&mut session, "SELECT NEXTVAL('countdown')")?.contains("NEXTVAL(countdown)"));
+        session.execute("UPDATE genre SET id = NEXTVAL('countdown')")?;
+        assert_eq!(query(&mut session, "SELECT id FROM genre")?, vec![vec![i(4)]]);
+        assert!(session.execute("INSERT INTO movie VALUES (NEXTVAL('countdown'), 'f')").is_err());
+        session.execute("INSERT INTO movie VALUES (NEXTVAL('countdown'), 'g')")?;
+        assert_eq!(
+            query(&mut session, "SELECT id FROM movie WHERE title = 'g'")?,
+            vec![vec![i(-2)]]
+        );
+        assert!(query(&mut session, "SELECT id FROM movie WHERE NEXTVAL('countdown') > 0")
+            .is_err());
+        assert!(session.execute("SELECT NEXTVAL(title) FROM movie").is_err());
+        assert!(session.execute("SELECT NEXTVAL('missing')").is_err());
+
+        // Sequences can be recreated, but not while used by a column.
+        session.execute("DROP SEQUENCE countdown")?;
+        session.execute("CREATE SEQUENCE countdown")?;
+        assert_eq!(query(&mut session, "SELECT NEXTVAL('countdown')")?, vec![vec![i(1)]]);
+        assert!(session.execute("CREATE SEQUENCE countdown").is_err());
+        assert!(session.execute("CREATE SEQUENCE zero INCREMENT BY 0").is_err());
+        assert!(session.execute("DROP SEQUENCE movie_id_seq").is_err());
+
+        // Added auto-increment columns number existing rows, and dropping them drops the sequence.
+        session.execute("ALTER TABLE genre ADD COLUMN rank SERIAL")?;
+        session.execute("INSERT INTO genre (name) VALUES ('comedy')")?;
+        assert_eq!(
+            query_sorted(&mut session, "SELECT name, rank FROM genre")?,
+            vec![vec![s("comedy"), i(2)], vec![s("drama"), i(1)]],
+        );
+        session.execute("ALTER TABLE genre DROP COLUMN rank")?;
+        session.execute("DROP TABLE movie")?;
+        session.execute("CREATE SEQUENCE genre_rank_seq")?;
+        session.execute("CREATE SEQUENCE movie_id_seq")?;
+        assert_eq!(
+            session.execute("CREATE TABLE a (id INT CHECK (NEXTVAL('countdown') > 0))").err(),
+            Some(Error::Value("NEXTVAL can't be used in CHECK constraints".into()))
+        );
+        assert!(session
+            .execute("ALTER TABLE genre ADD COLUMN x INT CHECK (x < NEXTVAL('genre_rank_seq'))")
+            .is_err());
+        assert!(session.execute("CREATE TABLE a (id SERIAL PRIMARY KEY DEFAULT 1)").is_err());
+        assert!(session.execute("CREATE TABLE a (id STRING AUTO_INCREMENT PRIMARY KEY)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn datatypes() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE event (
+                id UUID PRIMARY KEY,
+                day DATE NOT NULL,
+                at TIMESTAMP,
+                price DECIMAL NOT NULL UNIQUE,
+                data BLOB
+            )",
+            "CREATE TABLE daily (day DATE PRIMARY KEY)",
+            "CREATE TABLE price (amount NUMERIC PRIMARY KEY)",
+        ])?;
+        let strings = |session: &mut Session<KV<Bitcask>>, q: &str| -> Result<Vec<Vec<String>>> {
+            Ok(query(session, q)?
+                .into_iter()
+                .map(|row| row.into_iter().map(|v| v.to_string()).collect())
+                .collect())
+        };
+
+        // Strings are coerced to the column type on assignment, and values print as literals.
+        session.execute(
+            "INSERT INTO event VALUES
+                ('123E4567-E89B-12D3-A456-426614174000', '2024-02-29', '2024-02-29 12:30:00.5',
+                 '19.99', X'00ff'),
+                (UUID '00000000000000000000000000000001', DATE '1969-12-31', NULL, 5, NULL)",
+        )?;
+        assert_eq!(
+            strings(&mut session, "SELECT * FROM event ORDER BY day")?,
+            vec![
+                vec!["00000000-0000-0000-0000-000000000001", "1969-12-31", "NULL", "5", "NULL"],
+                vec![
+                    "123e4567-e89b-12d3-a456-426614174000",
+                    "2024-02-29",
+                    "2024-02-29 12:30:00.5",
+                    "19.99",
+                    "\\x00ff",
+                ],
+            ],
+        );
+        let insert = "INSERT INTO event (id, day, price) VALUES";
+        assert!(session.execute(&format!("{} (UUID '0', '2024-01-01', 1)", insert)).is_err());
+        assert!(session.execute(&format!("{} (UUID '02', '2023-02-29', 1)", insert)).is_err());
+        assert!(session.execute(&format!("{} (UUID '03', 'x', 1)", insert)).is_err());
+        assert!(session.execute(&format!("{} (X'00', '2024-01-01', 1)", insert)).is_err());
+        assert!(session.execute("SELECT X'0'").is_err());
+
+        // Decimals are exact, and compare with other numbers and strings.
+        session.execute("UPDATE event SET price = price * 3 WHERE day > '2000-01-01'")?;
+        assert_eq!(
+            strings(
+                &mut session,
+                "SELECT price, price / 7, -price % 2, ROUND(price, 1), FLOOR(-price), SUM(price)
+                 FROM event WHERE price > 5.5 AND price = '59.970' GROUP BY price",
+            )?,
+            vec![vec!["59.97", "8.567142857142857143", "-1.97", "60", "-60", "59.97"]],
+        );
+        assert_eq!(
+            strings(
+                &mut session,
+                "SELECT DECIMAL '0.1' + DECIMAL '0.2' = DECIMAL '0.3', 1 / DECIMAL '3',
+                    CAST(DECIMAL '2.5' AS INTEGER), CAST(0.1 AS DECIMAL), DECIMAL '1.5' + 0.5",
+            )?,
+            vec![vec!["TRUE", "0.333333333333333333", "3", "0.1", "2"]],
+        );
+        assert!(session.execute(&format!("{} (UUID '04', '2024-01-01', 5.00)", insert)).is_err());
+        assert!(query(&mut session, "SELECT DECIMAL '1' / 0").is_err());
+        assert!(query(&mut session, "SELECT DECIMAL '100000000000000000000' * 10").is_err());
+
+        // Float literals in decimal arithmetic are taken as exact decimals where possible.
+        session.execute("INSERT INTO price VALUES (0.1)")?;
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT amount + 0.2, 0.2 + amount, amount * 1.5, amount - 0.05, amount / 0.5,
+                    amount % 0.03, amount + 1e-30, amount + 0.2 = 0.3 FROM price",
+            )?,
+            vec![vec![
+                Value::Decimal("0.3".parse()?),
+                Value::Decimal("0.3".parse()?),
+                Value::Decimal("0.15".parse()?),
+                Value::Decimal("0.05".parse()?),
+                Value::Decimal("0.2".parse()?),
+                Value::Decimal("0.01".parse()?),
+                Value::Float(0.1),
+                Value::Boolean(true),
+            ]],
+        );
+        session.execute("DELETE FROM price")?;
+
+        // Dates and timestamps support arithmetic in days and microseconds respectively.
+        assert_eq!(
+            strings(
+                &mut session,
+                "SELECT DATE '2024-02-28' + 1, DATE '2024-03-01' - DATE '2024-02-01',
+                    TIMESTAMP '2024-01-01T00:00:00Z' - 1, CAST(at AS DATE), at > day,
+                    at - CAST(day AS TIMESTAMP)
+                 FROM event WHERE at IS NOT NULL",
+            )?,
+            vec![vec![
+                "2024-02-29",
+                "29",
+                "2023-12-31 23:59:59.999999",
+                "2024-02-29",
+                "TRUE",
+                "45000500000",
+            ]],
+        );
+        assert!(query(&mut session, "SELECT day FROM event WHERE day > 1").is_err());
+
+        // Dates and decimals sort correctly as primary keys, also when negative.
+        session.execute(
+            "INSERT INTO daily VALUES
+                ('2024-01-02'), ('1900-06-01'), ('1970-01-01'), ('1969-12-31')",
+        )?;
+        assert_eq!(
+            strings(&mut session, "SELECT * FROM daily")?,
+            vec![vec!["1900-06-01"], vec!["1969-12-31"], vec!["1970-01-01"], vec!["2024-01-02"]],
+        );
+        session.execute("INSERT INTO price VALUES (10), ('-1.5'), ('0.25'), ('-0.75'), (-2)")?;
+        assert_eq!(
+            strings(&mut session, "SELECT * FROM price")?,
+            vec![vec!["-2"], vec!["-1.5"], vec!["-0.75"], vec!["0.25"], vec!["10"]],
+        );
+        assert!(session.execute("INSERT INTO price VALUES ('0.250')").is_err());
+        let q = "SELECT * FROM price WHERE amount = 10 OR amount = -1.5";
+        assert!(plan(&mut session, q)?.contains("KeyLookup: price (10, -1.5)"));
+        assert_eq!(strings(&mut session, q)?, vec![vec!["10"], vec!["-1.5"]]);
+
+        // Blobs and UUIDs cast to and from strings.
+        assert_eq!(
+            strings(
+                &mut session,
+                "SELECT CAST(data AS STRING), LENGTH(data), CAST(CAST(id AS STRING) AS UUID) = id,
+                    CAST('\\x0a0b' AS BLOB) = X'0A0B', CAST('ab' AS BLOB), CAST(id AS BLOB)
+                 FROM event WHERE data IS NOT NULL",
+            )?,
+            vec![vec![
+                "\\x00ff",
+                "2",
+                "TRUE",
+                "TRUE",
+                "\\x6162",
+                "\\x123e4567e89b12d3a456426614174000",
+            ]],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn large_values() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE doc (id INTEGER PRIMARY KEY, title VARCHAR(5), body TEXT, data BLOB)",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // Values larger than the storage chunk size round-trip through inserts and updates.
+        let body = "abcdefghij".repeat(2000);
+        session.execute(&format!(
+            "INSERT INTO doc VALUES (1, 'small', '{}', X'{}'), (2, NULL, 'short', NULL)",
+            body,
+            "00ff".repeat(5000)
+        ))?;
+        assert_eq!(
+            query(&mut session, "SELECT id, LENGTH(body), LENGTH(data) FROM doc")?,
+            vec![vec![i(1), i(20000), i(10000)], vec![i(2), i(5), Value::Null]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT body FROM doc WHERE id = 1")?,
+            vec![vec![s(&body)]]
+        );
+        session.execute("UPDATE doc SET body = CONCAT('x', body) WHERE id = 1")?;
+        assert_eq!(
+            query(&mut session, "SELECT title FROM doc WHERE body LIKE 'xabc%'")?,
+            vec![vec![s("small")]],
+        );
+
+        // Length limits apply to VARCHAR(n) columns only, and must be positive.
+        assert!(session.execute("INSERT INTO doc VALUES (3, 'toolong', NULL, NULL)").is_err());
+        session.execute("INSERT INTO doc VALUES (3, 'héllo', NULL, NULL)")?;
+        let create = "CREATE TABLE bad (id INTEGER PRIMARY KEY, v";
+        assert!(session.execute(&format!("{} VARCHAR(0))", create)).is_err());
+        assert!(session.execute(&format!("{} INTEGER(5))", create)).is_err());
+        let txn = session.engine.begin()?;
+        assert!(txn.must_read_table("doc")?.to_string().contains("title VARCHAR(5)"));
+        txn.rollback()?;
+
+        // Schema changes keep large values, also after shifting column positions.
+        session.execute("ALTER TABLE doc DROP COLUMN title")?;
+        session.execute("ALTER TABLE doc RENAME TO page")?;
+        session.execute("ALTER TABLE page ADD COLUMN extra TEXT")?;
+        assert_eq!(
+            query(&mut session, "SELECT LENGTH(body), LENGTH(data), extra FROM page WHERE id = 1")?,
+            vec![vec![i(20001), i(10000), Value::Null]],
+        );
+
+        // Deleting rows removes their large values.
+        session.execute("DELETE FROM page WHERE id = 1")?;
+        assert_eq!(query(&mut session, "SELECT id FROM page")?, vec![vec![i(2)], vec![i(3)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn json() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE event (id INTEGER PRIMARY KEY, doc JSON NOT NULL, raw STRING)",
+            r#"INSERT INTO event VALUES
+                (1, '{"user": {"name": "alice", "age": 30}, "tags": ["a", "b"]}', '[1, 2, 3]'),
+                (2, ' {"user": {"name": "bob"}, "tags": [], "n": null} ', NULL),
+                (3, JSON '[10, {"k v": true}]', '{"x": 1.5}')"#,
+        ])?;
+        let (i, s, j) = (Value::Integer, |v: &str| Value::String(v.to_string()), |v: &str| {
+            Value::Json(v.to_string())
+        });
+
+        // Documents are validated and normalized on insert.
+        assert!(session.execute("INSERT INTO event VALUES (4, '{\"a\": ', NULL)").is_err());
+        assert!(session.execute("INSERT INTO event VALUES (4, 1, NULL)").is_err());
+        assert_eq!(
+            query(&mut session, "SELECT doc FROM event WHERE id = 2")?,
+            vec![vec![j(r#"{"n":null,"tags":[],"user":{"name":"bob"}}"#)]],
+        );
+
+        // -> returns JSON and ->> returns text, also for documents stored as strings. Missing
+        // fields and elements yield NULL.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT doc -> 'user' -> 'name', doc -> 'user' ->> 'name', doc -> 'tags' -> -1
+                 FROM event ORDER BY id",
+            )?,
+            vec![
+                vec![j(r#""alice""#), s("alice"), j(r#""b""#)],
+                vec![j(r#""bob""#), s("bob"), Value::Null],
+                vec![Value::Null, Value::Null, Value::Null],
+            ],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT doc ->> 'n', raw -> 1, doc -> 0 FROM event ORDER BY id")?,
+            vec![
+                vec![Value::Null, j("2"), Value::Null],
+                vec![Value::Null, Value::Null, Value::Null],
+                vec![Value::Null, Value::Null, j("10")],
+            ],
+        );
+        assert!(query(&mut session, "SELECT doc -> TRUE FROM event").is_err());
+        assert!(query(&mut session, "SELECT id -> 'a' FROM event").is_err());
+
+        // JSON scalars compare with SQL values, so filters can reach into documents.
+        assert_eq!(
+            query(&mut session, "SELECT id FROM event WHERE doc -> 'user' -> 'age' >= 18")?,
+            vec![vec![i(1)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT id FROM event WHERE doc -> 'user' ->> 'name' = 'bob'")?,
+            vec![vec![i(2)]],
+        );
+        assert_eq!(
+            query(&mut session, r#"SELECT id FROM event WHERE doc -> 'user' = '{"name":"bob"}'"#)?,
+            vec![vec![i(2)]],
+        );
+
+        // JSON_EXTRACT takes a path, and JSON_ARRAY_LENGTH counts array elements.
+        assert_eq!(
+            query(
+                &mut session,
+                r#"SELECT JSON_EXTRACT(doc, '$.user.name'), JSON_EXTRACT(doc, '$[1]."k v"'),
+                    JSON_ARRAY_LENGTH(doc, '$.tags'), JSON_ARRAY_LENGTH(raw)
+                 FROM event ORDER BY id"#,
+            )?,
+            vec![
+                vec![j(r#""alice""#), Value::Null, i(2), i(3)],
+                vec![j(r#""bob""#), Value::Null, i(0), Value::Null],
+                vec![Value::Null, j("true"), Value::Null, i(0)],
+            ],
+        );
+        assert!(query(&mut session, "SELECT JSON_EXTRACT(doc, 'user') FROM event").is_err());
+        assert!(session.execute("SELECT JSON_ARRAY_LENGTH(1)").is_err());
+
+        // JSON scalars cast to SQL values and back.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT CAST(doc -> 0 AS FLOAT), CAST(doc AS STRING),
+                    CAST(2.5 AS JSON), CAST('[1,  2]' AS JSON)
+                 FROM event WHERE id = 3",
+            )?,
+            vec![vec![
+                Value::Float(10.0),
+                s(r#"[10,{"k v":true}]"#),
+                j("2.5"),
+                j("[1,2]"),
+            ]],
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn views() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE genre (id INTEGER PRIMARY KEY, name STRING NOT NULL)",
+            "CREATE TABLE movie (id INTEGER PRIMARY KEY, title STRING NOT NULL, \
+                genre_id INTEGER REFERENCES genre, rating FLOAT)",
+            "INSERT INTO genre VALUES (1, 'Science Fiction'), (2, 'Action')",
+            "INSERT INTO movie VALUES (1, 'Stalker', 1, 8.2), (2, 'Sicario', 2, 7.6), \
+                (3, 'Primer', 1, 6.9)",
+            "CREATE VIEW good AS SELECT id, title AS name, genre_id FROM movie \
+                WHERE rating > 7.0 ORDER BY id",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // Views are queried like tables, with their output columns as fields.
+        assert_eq!(
+            query(&mut session, "SELECT * FROM good")?,
+            vec![vec![i(1), s("Stalker"), i(1)], vec![i(2), s("Sicario"), i(2)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT good.name FROM good WHERE genre_id = 2")?,
+            vec![vec![s("Sicario")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT g.name, genre.name FROM good g JOIN genre ON g.genre_id = genre.id \
+                 ORDER BY g.id",
+            )?,
+            vec![vec![s("Stalker"), s("Science Fiction")], vec![s("Sicario"), s("Action")]],
+        );
+
+        // Views see the current table contents, and can be built on other views.
+        session.execute("INSERT INTO movie VALUES (4, 'Arrival', 1, 7.9)")?;
+        session.execute("CREATE VIEW scifi AS SELECT name FROM good WHERE genre_id = 1")?;
+        assert_eq!(
+            query(&mut session, "SELECT * FROM scifi")?,
+            vec![vec![s("Stalker")], vec![s("Arrival")]],
+        );
+
+        // Views are listed by the catalog.
+        let txn = session.engine.begin()?;
+        let views = txn.scan_views()?;
+        assert_eq!(views.iter().map(|v| v.name.as_str()).collect::<Vec<_>>(), ["good", "scifi"]);
+        assert_eq!(
+            views[1].to_string(),
+            "CREATE VIEW scifi AS SELECT name FROM good WHERE genre_id = 1"
+        );
+        txn.rollback()?;
+
+        // Names are shared with tables, and the query must be a valid SELECT.
+        assert!(session.execute("CREATE VIEW movie AS SELECT 1").is_err());
+        assert!(session.execute("CREATE TABLE good (id INTEGER PRIMARY KEY)").is_err());
+        assert!(session.execute("ALTER TABLE genre RENAME TO good").is_err());
+        assert!(session.execute("CREATE VIEW bad AS SELECT * FROM missing").is_err());
+        assert!(session.execute("CREATE VIEW bad AS DELETE FROM movie").is_err());
+        assert!(session.execute("INSERT INTO good VALUES (5, 'Heat', 2)").is_err());
+
+        // Dropped views can no longer be queried.
+        session.execute("DROP VIEW scifi")?;
+        assert!(session.execute("SELECT * FROM scifi").is_err());
+        assert!(session.execute("DROP VIEW scifi").is_err());
+        assert_eq!(query(&mut session, "SELECT COUNT(*) FROM good")?, vec![vec![i(3)]]);
+        Ok(())
+    }
+
+    #[test]
+    fn subqueries() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE genre (id INTEGER PRIMARY KEY, name STRING NOT NULL)",
+            "CREATE TABLE movie (id INTEGER PRIMARY KEY, title STRING NOT NULL, \
+                genre_id INTEGER REFERENCES genre, rating FLOAT)",
+            "INSERT INTO genre VALUES (1, 'Science Fiction'), (2, 'Action'), (3, 'Comedy')",
+            "INSERT INTO movie VALUES (1, 'Stalker', 1, 8.2), (2, 'Sicario', 2, 7.6), \
+                (3, 'Primer', 1, 6.9), (4, 'Heat', 2, 8.3), (5, 'Unknown', NULL, NULL)",
+        ])?;
+        let (i, s, f) = (Value::Integer, |v: &str| Value::String(v.to_string()), Value::Float);
+
+        // Scalar subqueries return the single value, or NULL if there are no rows.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT title FROM movie WHERE rating = (SELECT MAX(rating) FROM movie)",
+            )?,
+            vec![vec![s("Heat")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT (SELECT name FROM genre WHERE id = 9), 1")?,
+            vec![vec![Value::Null, i(1)]],
+        );
+        assert!(query(&mut session, "SELECT (SELECT id FROM genre)").is_err());
+
+        // Correlated scalar subqueries are evaluated for each row.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT title, (SELECT name FROM genre WHERE genre.id = movie.genre_id) \
+                 FROM movie WHERE id <= 3 ORDER BY id",
+            )?,
+            vec![
+                vec![s("Stalker"), s("Science Fiction")],
+                vec![s("Sicario"), s("Action")],
+                vec![s("Primer"), s("Science Fiction")],
+            ],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT m.title FROM movie m WHERE m.rating > \
+                 (SELECT AVG(rating) FROM movie WHERE genre_id = m.genre_id) ORDER BY m.id",
+            )?,
+            vec![vec![s("Stalker")], vec![s("Heat")]],
+        );
+
+        // IN and NOT IN subqueries, with SQL NULL semantics for NOT IN.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre WHERE id IN (SELECT genre_id FROM movie) ORDER BY id",
+            )?,
+            vec![vec![s("Science Fiction")], vec![s("Action")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre WHERE id NOT IN (SELECT genre_id FROM movie)",
+            )?,
+            Vec::<Vec<Value>>::new(),
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre WHERE id NOT IN \
+                 (SELECT genre_id FROM movie WHERE genre_id IS NOT NULL)",
+            )?,
+            vec![vec![s("Comedy")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre g WHERE 8.0 IN \
+                 (SELECT FLOOR(rating) FROM movie WHERE genre_id = g.id)",
+            )?,
+            vec![vec![s("Science Fiction")], vec![s("Action")]],
+        );
+
+        // EXISTS and NOT EXISTS.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre g WHERE EXISTS \
+                 (SELECT * FROM movie m WHERE m.genre_id = g.id AND m.rating > 8.0) ORDER BY id",
+            )?,
+            vec![vec![s("Science Fiction")], vec![s("Action")]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name FROM genre g WHERE NOT EXISTS \
+                 (SELECT * FROM movie m WHERE m.genre_id = g.id)",
+            )?,
+            vec![vec![s("Comedy")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT COUNT(*) FROM genre WHERE EXISTS (SELECT * FROM movie)")?,
+            vec![vec![i(3)]],
+        );
+
+        // Nested subqueries can reference any enclosing query, not just the directly enclosing
+        // one, also when they're planned as semi joins.
+        let q = "SELECT name FROM genre g WHERE EXISTS (SELECT * FROM movie m WHERE EXISTS \
+                 (SELECT * FROM genre g2 WHERE g2.id = m.genre_id AND g2.id = g.id \
+                 AND m.rating > 8.0)) ORDER BY id";
+        assert_eq!(query(&mut session, q)?, vec![vec![s("Science Fiction")], vec![s("Action")]]);
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT name, (SELECT COUNT(*) FROM movie m WHERE m.genre_id = g.id AND \
+                 m.rating > (SELECT MIN(rating) FROM movie WHERE genre_id = g.id)) \
+                 FROM genre g ORDER BY id",
+            )?,
+            vec![
+                vec![s("Science Fiction"), i(1)],
+                vec![s("Action"), i(1)],
+                vec![s("Comedy"), i(0)],
+            ],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT title FROM movie a WHERE NOT EXISTS (SELECT * FROM genre g WHERE \
+                 g.id = a.genre_id AND EXISTS (SELECT * FROM movie b WHERE b.genre_id = g.id \
+                 AND b.rating > a.rating AND EXISTS (SELECT 1 WHERE b.id <> a.id))) ORDER BY id",
+            )?,
+            vec![vec![s("Stalker")], vec![s("Heat")], vec![s("Unknown")]],
+        );
+
+        // Correlated EXISTS and IN subqueries in WHERE are planned as semi and anti joins.
+        assert!(plan(
+            &mut session,
+            "SELECT name FROM genre WHERE id IN (SELECT genre_id FROM movie)",
+        )?
+        .contains("HashJoin: semi on id = genre_id"));
+        assert!(plan(
+            &mut session,
+            "SELECT name FROM genre g WHERE NOT EXISTS \
+             (SELECT * FROM movie m WHERE m.genre_id = g.id)",
+        )?
+        .contains("HashJoin: anti on g.id = m.genre_id"));
+
+        // Subqueries in FROM must have an alias, and are queried like tables.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT g.name, r.best FROM genre g JOIN \
+                 (SELECT genre_id, MAX(rating) AS best FROM movie GROUP BY genre_id) AS r \
+                 ON r.genre_id = g.id ORDER BY g.id",
+            )?,
+            vec![vec![s("Science Fiction"), f(8.2)], vec![s("Action"), f(8.3)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT COUNT(*) FROM (SELECT id FROM movie WHERE id > 2) m")?,
+            vec![vec![i(3)]],
+        );
+        assert!(session.execute("SELECT * FROM (SELECT id FROM movie)").is_err());
+
+        // Subqueries in UPDATE and DELETE.
+        session.execute(
+            "UPDATE movie SET rating = (SELECT MIN(rating) FROM movie) \
+             WHERE genre_id IN (SELECT id FROM genre WHERE name = 'Action')",
+        )?;
+        assert_eq!(
+            query(&mut session, "SELECT rating FROM movie WHERE genre_id = 2")?,
+            vec![vec![f(6.9)], vec![f(6.9)]],
+        );
+        session.execute(
+            "DELETE FROM movie WHERE NOT EXISTS \
+             (SELECT * FROM genre WHERE genre.id = movie.genre_id)",
+        )?;
+        assert_eq!(query(&mut session, "SELECT COUNT(*) FROM movie")?, vec![vec![i(4)]]);
+
+        // Invalid subqueries.
+        assert!(session.execute("SELECT * FROM genre WHERE id IN (SELECT * FROM movie)").is_err());
+        assert!(session.execute("SELECT * FROM genre ORDER BY (SELECT 1)").is_err());
+        assert!(session.execute("SELECT * FROM genre WHERE id = (SELECT missing)").is_err());
+        assert!(session.execute("INSERT INTO genre VALUES ((SELECT 4), 'Drama')").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ctes() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE employee (id INTEGER PRIMARY KEY, name STRING NOT NULL, \
+                manager_id INTEGER REFERENCES employee)",
+            "INSERT INTO employee VALUES (1, 'Alice', NULL), (2, 'Bob', 1), (3, 'Carol', 1), \
+                (4, 'Dave', 2), (5, 'Eve', 4)",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // WITH queries are referenced like tables, and can reference earlier WITH queries.
+        assert_eq!(
+            query(
+                &mut session,
+                "WITH reports AS (SELECT manager_id, COUNT(*) AS n FROM employee \
+                 GROUP BY manager_id) \
+                 SELECT e.name, r.n FROM employee e JOIN reports r ON r.manager_id = e.id \
+                 ORDER BY e.id",
+            )?,
+            vec![vec![s("Alice"), i(2)], vec![s("Bob"), i(1)], vec![s("Dave"), i(1)]],
+        );
+        match session.execute(
+            "WITH a(x) AS (SELECT id FROM employee WHERE id > 3), b AS (SELECT x * 10 AS y FROM a) \
+             SELECT * FROM b",
+        )? {
+            ResultSet::Query { columns, rows } => {
+                assert_eq!(columns, vec![Column { name: Some("y".into()) }]);
+                assert_eq!(rows.collect::<Result<Vec<_>>>()?, vec![vec![i(40)], vec![i(50)]]);
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+
+        // WITH queries are visible in subqueries, and shadow tables.
+        assert_eq!(
+            query(
+                &mut session,
+                "WITH top AS (SELECT id FROM employee WHERE manager_id IS NULL) \
+                 SELECT name FROM employee WHERE manager_id IN (SELECT id FROM top) ORDER BY id",
+            )?,
+            vec![vec![s("Bob")], vec![s("Carol")]],
+        );
+        assert_eq!(
+            query(&mut session, "WITH employee AS (SELECT 1 AS id) SELECT * FROM employee")?,
+            vec![vec![i(1)]],
+        );
+
+        // WITH RECURSIVE repeats the recursive term until it returns no rows.
+        match session.execute(
+            "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < 5) \
+             SELECT * FROM t",
+        )? {
+            ResultSet::Query { columns, rows } => {
+                assert_eq!(columns, vec![Column { name: Some("n".into()) }]);
+                assert_eq!(
+                    rows.collect::<Result<Vec<_>>>()?,
+                    (1..=5).map(|n| vec![i(n)]).collect::<Vec<_>>()
+                );
+            }
+            r => panic!("Unexpected result {:?}", r),
+        }
+        let chain = "WITH RECURSIVE chain AS ( \
+                SELECT id, name, 0 AS depth FROM employee WHERE id = 1 \
+                UNION ALL \
+                SELECT e.id, e.name, c.depth + 1 FROM employee e JOIN chain c \
+                ON e.manager_id = c.id \
+            ) SELECT name, depth FROM chain ORDER BY depth, name";
+        assert_eq!(
+            query(&mut session, chain)?,
+            vec![
+                vec![s("Alice"), i(0)],
+                vec![s("Bob"), i(1)],
+                vec![s("Carol"), i(1)],
+                vec![s("Dave"), i(2)],
+                vec![s("Eve"), i(3)],
+            ],
+        );
+        let plan = plan(&mut session, chain)?;
+        assert!(plan.contains("Recursive: chain (union all)"), "{}", plan);
+        assert!(plan.contains("WorkingTable: chain"), "{}", plan);
+
+        // EXPLAIN ANALYZE accumulates the rows of the recursive term across iterations.
+        let lines: Vec<String> = query(&mut session, &format!("EXPLAIN ANALYZE {}", chain))?
+            .into_iter()
+            .map(|row| row[0].to_string())
+            .collect();
+        let working = lines.iter().find(|l| l.contains("WorkingTable: chain")).unwrap();
+        assert!(working.contains("(rows: 5, time: "), "{}", working);
+
+        // UNION discards duplicate rows, which ends cycles.
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "WITH RECURSIVE t(n) AS (SELECT 1 UNION SELECT n % 3 + 1 FROM t) SELECT * FROM t",
+            )?,
+            vec![vec![i(1)], vec![i(2)], vec![i(3)]],
+        );
+
+        // The number of iterations is capped.
+        let count = |n: i64| {
+            format!(
+                "WITH RECURSIVE t(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM t WHERE n < {}) \
+                 SELECT COUNT(*) FROM t",
+                n
+            )
+        };
+        assert!(session.execute(&count(i64::MAX)).is_err());
+        session.max_recursion = 10;
+        assert_eq!(query(&mut session, &count(10))?, vec![vec![i(10)]]);
+        assert!(session.execute(&count(11)).is_err());
+
+        // Invalid WITH queries.
+        assert!(session.execute("WITH t AS (SELECT 1), t AS (SELECT 2) SELECT * FROM t").is_err());
+        assert!(session.execute("WITH t(a, b) AS (SELECT 1) SELECT * FROM t").is_err());
+        assert!(session.execute("WITH t AS (SELECT * FROM t) SELECT * FROM t").is_err());
+        assert!(session
+            .execute("WITH RECURSIVE t AS (SELECT 1 UNION ALL SELECT 1, 2 FROM t) SELECT * FROM t")
+            .is_err());
+        assert!(session.execute("WITH t AS (SELECT 1) DELETE FROM employee").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn set_operations() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE a (id INTEGER PRIMARY KEY, v INTEGER, name STRING)",
+            "CREATE TABLE b (id INTEGER PRIMARY KEY, v FLOAT, name STRING)",
+            "INSERT INTO a VALUES (1, 1, 'x'), (2, 2, 'y'), (3, 2, 'y'), (4, NULL, NULL)",
+            "INSERT INTO b VALUES (1, 2.0, 'y'), (2, 3.0, 'z'), (3, NULL, NULL), (4, 2.0, 'y')",
+        ])?;
+        let (i, f, s) = (Value::Integer, Value::Float, |v: &str| Value::String(v.to_string()));
+        let null = Value::Null;
+
+        // UNION removes duplicates, also of NULLs, while UNION ALL keeps all rows.
+        assert_eq!(
+            query_sorted(&mut session, "SELECT name FROM a UNION SELECT name FROM b")?,
+            vec![vec![null.clone()], vec![s("x")], vec![s("y")], vec![s("z")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT name FROM a UNION ALL SELECT name FROM b")?.len(),
+            8
+        );
+
+        // INTERSECT and EXCEPT, with ALL keeping duplicates by their number of occurrences.
+        assert_eq!(
+            query_sorted(&mut session, "SELECT name FROM a INTERSECT SELECT name FROM b")?,
+            vec![vec![null.clone()], vec![s("y")]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT name FROM a INTERSECT ALL SELECT name FROM b WHERE id < 4"
+            )?,
+            vec![vec![null.clone()], vec![s("y")]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT name FROM a EXCEPT SELECT name FROM b")?,
+            vec![vec![s("x")]],
+        );
+        assert_eq!(
+            query_sorted(
+                &mut session,
+                "SELECT name FROM a EXCEPT ALL SELECT name FROM b WHERE id < 3"
+            )?,
+            vec![vec![null.clone()], vec![s("x")], vec![s("y")]],
+        );
+
+        // Numeric columns are cast to a common type, and INTERSECT binds tighter than UNION and
+        // EXCEPT, which are evaluated left to right. ORDER BY and LIMIT apply to the combined
+        // rows, by the column names of the first query.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT v FROM a UNION SELECT v FROM b ORDER BY v DESC LIMIT 2 OFFSET 1"
+            )?,
+            vec![vec![f(2.0)], vec![f(1.0)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT 1 AS n UNION SELECT 2 INTERSECT SELECT 3 EXCEPT SELECT 4 ORDER BY n"
+            )?,
+            vec![vec![i(1)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT 1 AS n UNION ALL SELECT 1 UNION SELECT 2 ORDER BY n")?,
+            vec![vec![i(1)], vec![i(2)]],
+        );
+        assert_eq!(
+            query(&mut session, "SELECT v FROM b WHERE id = 2 UNION ALL SELECT 1")?,
+            vec![vec![f(3.0)], vec![f(1.0)]],
+        );
+
+        // Parenthesized operands group set operations, and can have their own ORDER BY and LIMIT.
+        assert_eq!(
+            query(&mut session, "(SELECT 1 AS n UNION SELECT 2) INTERSECT (SELECT 2) ORDER BY n")?,
+            vec![vec![i(2)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "(SELECT id FROM a ORDER BY id DESC LIMIT 1) UNION ALL (SELECT id FROM b LIMIT 1)"
+            )?,
+            vec![vec![i(4)], vec![i(1)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT n FROM ((SELECT 1 AS n) UNION (SELECT 2)) AS t WHERE n > 1"
+            )?,
+            vec![vec![i(2)]],
+        );
+        assert_eq!(query(&mut session, "(SELECT 1)")?, vec![vec![i(1)]]);
+        assert!(session.execute("(SELECT 1 LIMIT 1) LIMIT 1").is_err());
+        assert!(session.execute("(SELECT 1 UNION SELECT 2").is_err());
+
+        // Column types must match when known, and numeric columns are cast to a common type.
+        assert_eq!(query(&mut session, "SELECT 1 UNION SELECT 1.0")?, vec![vec![f(1.0)]]);
+        assert!(session.execute("SELECT 1 UNION SELECT 'a'").is_err());
+        assert!(session.execute("SELECT 'a' EXCEPT SELECT id FROM a").is_err());
+        assert!(session.execute("SELECT TRUE UNION SELECT v > 1 FROM b UNION SELECT 2").is_err());
+        assert!(session.execute("SELECT CAST(1 AS STRING) UNION SELECT 1").is_err());
+
+        // Values whose types weren't cast at planning are compared numerically.
+        assert_eq!(query(&mut session, "SELECT 1 UNION SELECT 0.5 + 0.5")?, vec![vec![i(1)]]);
+        assert_eq!(query(&mut session, "SELECT 1 INTERSECT SELECT 0.5 * 2")?, vec![vec![i(1)]]);
+        assert!(query(&mut session, "SELECT 2 EXCEPT SELECT v FROM b")?.is_empty());
+        assert_eq!(
+            query(&mut session, "SELECT 1 AS n UNION SELECT CAST(1 AS DECIMAL) UNION SELECT -0.0")?,
+            vec![vec![Value::Decimal(1.into())], vec![f(-0.0)]],
+        );
+
+        // Compound queries can be used as subqueries, derived tables, WITH queries and views.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id FROM a WHERE name IN (SELECT name FROM b EXCEPT SELECT 'z') \
+                 ORDER BY id"
+            )?,
+            vec![vec![i(2)], vec![i(3)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT COUNT(*) FROM (SELECT id FROM a UNION SELECT id FROM b) AS ids"
+            )?,
+            vec![vec![i(4)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id FROM a WHERE EXISTS \
+                 (SELECT 1 FROM b WHERE b.v = a.v UNION ALL SELECT 1 WHERE a.id = 1) ORDER BY id"
+            )?,
+            vec![vec![i(1)], vec![i(2)], vec![i(3)]],
+        );
+        session.execute(
+            "CREATE VIEW names AS WITH n AS (SELECT name FROM a) \
+             SELECT name FROM n UNION SELECT name FROM b",
+        )?;
+        assert_eq!(query(&mut session, "SELECT COUNT(*) FROM names")?, vec![vec![i(4)]]);
+
+        let plan = plan(&mut session, "SELECT id FROM a UNION ALL SELECT id FROM b")?;
+        assert!(plan.contains("SetOperation: union all"), "{}", plan);
+        let analyze: Vec<String> =
+            query(&mut session, "EXPLAIN ANALYZE SELECT id FROM a UNION ALL SELECT id FROM b")?
+                .into_iter()
+                .map(|row| row[0].to_string())
+                .collect();
+        assert!(analyze[0].contains("(rows: 8, time: "), "{:?}", analyze);
+        assert!(analyze[1..].iter().all(|l| l.contains("(rows: 4, time: ")), "{:?}", analyze);
+
+        // Operands must have the same number of columns, and compatible types.
+        assert!(session.execute("SELECT id, v FROM a UNION SELECT id FROM b").is_err());
+        assert!(session.execute("SELECT id FROM a INTERSECT SELECT name FROM b").is_err());
+        assert!(session.execute("SELECT id FROM a EXCEPT SELECT id FROM b ORDER BY v").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn distinct() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE sale (id INTEGER PRIMARY KEY, region STRING, product STRING, \
+                amount INTEGER)",
+            "INSERT INTO sale VALUES (1, 'north', 'a', 10), (2, 'north', 'b', 30), \
+                (3, 'south', 'a', 20), (4, 'south', 'a', 5), (5, NULL, 'b', 15), \
+                (6, NULL, 'c', 25)",
+        ])?;
+        let (i, s) = (Value::Integer, |v: &str| Value::String(v.to_string()));
+
+        // DISTINCT removes duplicate rows, treating NULLs as equal.
+        assert_eq!(
+            query(&mut session, "SELECT DISTINCT region FROM sale ORDER BY region")?,
+            vec![vec![Value::Null], vec![s("north")], vec![s("south")]],
+        );
+        assert_eq!(
+            query_sorted(&mut session, "SELECT DISTINCT region, product FROM sale")?.len(),
+            5
+        );
+        assert_eq!(query(&mut session, "SELECT ALL region FROM sale")?.len(), 6);
+        assert_eq!(
+            query(&mut session, "SELECT COUNT(*) FROM (SELECT DISTINCT * FROM sale) AS s")?,
+            vec![vec![i(6)]],
+        );
+
+        // Columns only used for ordering are not compared.
+        assert_eq!(
+            query(&mut session, "SELECT DISTINCT product FROM sale ORDER BY amount DESC")?,
+            vec![vec![s("b")], vec![s("c")], vec![s("a")]],
+        );
+
+        // DISTINCT applies to aggregate results.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT DISTINCT COUNT(*) AS n FROM sale GROUP BY region HAVING MAX(amount) > 20 \
+                 ORDER BY n"
+            )?,
+            vec![vec![i(2)]],
+        );
+
+        // DISTINCT ON keeps the first row of each group, in ORDER BY order.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT DISTINCT ON (region) region, id, amount FROM sale \
+                 ORDER BY region, amount DESC"
+            )?,
+            vec![
+                vec![Value::Null, i(6), i(25)],
+                vec![s("north"), i(2), i(30)],
+                vec![s("south"), i(3), i(20)],
+            ],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT DISTINCT ON (product, amount > 10) id FROM sale ORDER BY id"
+            )?,
+            vec![vec![i(1)], vec![i(2)], vec![i(3)], vec![i(6)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT DISTINCT ON (product) * FROM sale ORDER BY amount LIMIT 2"
+            )?,
+            vec![
+                vec![i(4), s("south"), s("a"), i(5)],
+                vec![i(5), Value::Null, s("b"), i(15)],
+            ],
+        );
+
+        let plan = plan(&mut session, "SELECT DISTINCT ON (region) id FROM sale")?;
+        assert!(plan.contains("Distinct on sale.region"), "{}", plan);
+
+        // Invalid DISTINCT clauses.
+        assert!(session.execute("SELECT DISTINCT ON region id FROM sale").is_err());
+        assert!(session.execute("SELECT DISTINCT ON () id FROM sale").is_err());
+        assert!(session.execute("SELECT DISTINCT ON (missing) id FROM sale").is_err());
+        assert!(session.execute("SELECT DISTINCT ON ((SELECT 1)) id FROM sale").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn window_functions() -> Result<()> {
+        let (_dir, mut session) = setup(&[
+            "CREATE TABLE sale (id INTEGER PRIMARY KEY, region STRING, amount INTEGER)",
+            "INSERT INTO sale VALUES (1, 'north', 10), (2, 'north', 30), (3, 'north', 30), \
+                (4, 'north', 50), (5, 'south', 20), (6, 'south', NULL), (7, 'south', 40)",
+        ])?;
+        let (i, f, s) = (Value::Integer, Value::Float, |v: &str| Value::String(v.to_string()));
+        let null = Value::Null;
+
+        // Ranking functions number the rows of each partition, in window order.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id, ROW_NUMBER() OVER (PARTITION BY region ORDER BY amount DESC) AS n, \
+                 RANK() OVER (PARTITION BY region ORDER BY amount DESC), \
+                 DENSE_RANK() OVER (PARTITION BY region ORDER BY amount DESC) \
+                 FROM sale ORDER BY id"
+            )?,
+            vec![
+                vec![i(1), i(4), i(4), i(3)],
+                vec![i(2), i(2), i(2), i(2)],
+                vec![i(3), i(3), i(2), i(2)],
+                vec![i(4), i(1), i(1), i(1)],
+                vec![i(5), i(2), i(2), i(2)],
+                vec![i(6), i(3), i(3), i(3)],
+                vec![i(7), i(1), i(1), i(1)],
+            ],
+        );
+
+        // LAG and LEAD look at other rows of the partition, with an optional offset and default.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id, LAG(amount) OVER (PARTITION BY region ORDER BY id), \
+                 LEAD(amount, 2, 0) OVER (PARTITION BY region ORDER BY id), \
+                 FIRST_VALUE(id) OVER (PARTITION BY region ORDER BY amount) \
+                 FROM sale ORDER BY id"
+            )?,
+            vec![
+                vec![i(1), null.clone(), i(30), i(1)],
+                vec![i(2), i(10), i(50), i(1)],
+                vec![i(3), i(30), i(0), i(1)],
+                vec![i(4), i(30), i(0), i(1)],
+                vec![i(5), null.clone(), i(40), i(6)],
+                vec![i(6), i(20), i(0), i(6)],
+                vec![i(7), null.clone(), i(0), i(6)],
+            ],
+        );
+
+        // Aggregates over the default frame are running totals, including the current row's
+        // peers, or totals over the entire partition without ORDER BY.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id, SUM(amount) OVER (ORDER BY amount), \
+                 COUNT(*) OVER (PARTITION BY region), AVG(amount) OVER () \
+                 FROM sale WHERE region = 'north' ORDER BY id"
+            )?,
+            vec![
+                vec![i(1), i(10), i(4), f(30.0)],
+                vec![i(2), i(70), i(4), f(30.0)],
+                vec![i(3), i(70), i(4), f(30.0)],
+                vec![i(4), i(120), i(4), f(30.0)],
+            ],
+        );
+
+        // ROWS frames count rows, while RANGE frames compare ORDER BY values.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT id, \
+                 SUM(amount) OVER (ORDER BY id ROWS BETWEEN 1 PRECEDING AND 1 FOLLOWING), \
+                 SUM(amount) OVER (ORDER BY id ROWS 1 PRECEDING), \
+                 COUNT(*) OVER (ORDER BY amount RANGE BETWEEN 10 PRECEDING AND 20 FOLLOWING), \
+                 SUM(amount) OVER (ORDER BY amount DESC \
+                     RANGE BETWEEN CURRENT ROW AND UNBOUNDED FOLLOWING) \
+                 FROM sale WHERE region = 'north' ORDER BY id"
+            )?,
+            vec![
+                vec![i(1), i(40), i(10), i(3), i(10)],
+                vec![i(2), i(70), i(40), i(3), i(70)],
+                vec![i(3), i(110), i(60), i(3), i(70)],
+                vec![i(4), i(80), i(80), i(1), i(120)],
+            ],
+        );
+
+        // Window functions are computed after aggregation and HAVING, and can be ordered by.
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT region, SUM(amount) AS total, RANK() OVER (ORDER BY SUM(amount)) AS r \
+                 FROM sale GROUP BY region HAVING COUNT(amount) > 1 ORDER BY r DESC"
+            )?,
+            vec![vec![s("north"), i(120), i(2)], vec![s("south"), i(60), i(1)]],
+        );
+        assert_eq!(
+            query(
+                &mut session,
+                "SELECT region, RANK() OVER (ORDER BY COUNT(*) DESC) FROM sale GROUP BY region \
+                 HAVING COUNT(*) < 4"
+            )?,
+            vec![vec![s("south"), i(1)]],
+        );
+
+        // Calls with the same window share a Window node.
+        let plan = plan(
+            &mut session,
+            "SELECT ROW_NUMBER() OVER (ORDER BY id), LAG(id) OVER (ORDER BY id), \
+             SUM(amount) OVER (PARTITION BY region ROWS UNBOUNDED PRECEDING) FROM sale",
+        )?;
+        assert!(
+            plan.contains("Window: row_number(), lag(id, 1, NULL) order by id asc"),
+            "{}",
+            plan
+        );
+        assert!(
+            plan.contains(
+                "Window: sum(amount) rows between unbounded preceding and current row \
+                 partition by region"
+            ),
+            "{}",
+            plan
+        );
+
+        // Invalid window functions.
+        assert!(session.execute("SELECT id FROM sale WHERE RANK() OVER () > 1").is_err());
+        assert!(session.execute("SELECT RANK(id) OVER () FROM sale").is_err());
+        assert!(session.execute("SELECT UPPER(region) OVER () FROM sale").is_err());
+        assert!(session.execute("SELECT SUM(RANK() OVER ()) OVER () FROM sale").is_err());
+        assert!(session.execute("SELECT LAG(id, -1) OVER () FROM sale").is_err());
+        assert_eq!(
+            session.execute("SELECT COUNT(DISTINCT region) OVER () FROM sale").err(),
+            Some(Error::Value("DISTINCT is not supported in window functions".into()))
+        );
+        assert!(session
+            .execute("SELECT SUM(id) OVER (ROWS BETWEEN CURRENT ROW AND 1 PRECEDING) FROM sale")
+            .is_err());
+        assert!(session
+            .execute("SELECT SUM(id) OVER (RANGE BETWEEN 1 PRECEDING AND CURRENT ROW) FROM sale")
+            .is_err());
+        assert!(session.execute("SELECT id, RANK() OVER () FROM sale GROUP BY region").is_err());
+        Ok(())
+    }
+}
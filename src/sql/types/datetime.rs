@@ -0,0 +1,151 @@
+//! Date and timestamp conversions. Dates are stored as days since 1970-01-01, and timestamps as
+//! microseconds since 1970-01-01 00:00:00 UTC, such that both order chronologically. Dates use the
+//! proleptic Gregorian calendar, and timestamps have no time zone.
+
+use crate::error::{Error, Result};
+
+/// The number of microseconds in a day.
+pub const MICROS_PER_DAY: i64 = 86_400_000_000;
+
+/// Converts a calendar date to days since the epoch, using Howard Hinnant's days_from_civil
+/// algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+/// Converts days since the epoch to a calendar date (year, month, day), using Howard Hinnant's
+/// civil_from_days algorithm.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Parses a fixed-width, unsigned decimal number.
+fn parse_digits(s: &str, width: usize) -> Option<i64> {
+    if s.len() != width || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// Parses a YYYY-MM-DD date into days since the epoch.
+fn parse_date_parts(s: &str) -> Option<i64> {
+    let mut parts = s.splitn(3, '-');
+    let year = parse_digits(parts.next()?, 4)?;
+    let month = parse_digits(parts.next()?, 2)?;
+    let day = parse_digits(parts.next()?, 2)?;
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return None,
+    };
+    if day < 1 || day > days_in_month {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+/// Parses a HH:MM[:SS[.ffffff]] time into microseconds since midnight.
+fn parse_time_parts(s: &str) -> Option<i64> {
+    let (s, fraction) = match s.split_once('.') {
+        Some((s, fraction)) if (1..=6).contains(&fraction.len()) => {
+            (s, parse_digits(fraction, fraction.len())? * 10_i64.pow(6 - fraction.len() as u32))
+        }
+        Some(_) => return None,
+        None => (s, 0),
+    };
+    let mut parts = s.splitn(3, ':');
+    let hour = parse_digits(parts.next()?, 2)?;
+    let minute = parse_digits(parts.next()?, 2)?;
+    let second = parts.next().map(|s| parse_digits(s, 2)).unwrap_or(Some(0))?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return None;
+    }
+    Some(((hour * 60 + minute) * 60 + second) * 1_000_000 + fraction)
+}
+
+/// Parses a date given as YYYY-MM-DD.
+pub fn parse_date(s: &str) -> Result<i32> {
+    parse_date_parts(s.trim())
+        .and_then(|days| days.try_into().ok())
+        .ok_or_else(|| Error::Value(format!("Invalid date {}", s)))
+}
+
+/// Parses a timestamp given as YYYY-MM-DD[ HH:MM[:SS[.ffffff]]], with an optional T separator and
+/// trailing Z.
+pub fn parse_timestamp(s: &str) -> Result<i64> {
+    let trimmed = s.trim();
+    let trimmed = trimmed.strip_suffix(['Z', 'z']).unwrap_or(trimmed);
+    let (date, time) = match trimmed.split_once([' ', 'T', 't']) {
+        Some((date, time)) => (date, Some(time.trim_start())),
+        None => (trimmed, None),
+    };
+    parse_date_parts(date)
+        .zip(time.map(parse_time_parts).unwrap_or(Some(0)))
+        .and_then(|(days, micros)| days.checked_mul(MICROS_PER_DAY)?.checked_add(micros))
+        .ok_or_else(|| Error::Value(format!("Invalid timestamp {}", s)))
+}
+
+/// Formats a date as YYYY-MM-DD.
+pub fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats a timestamp as YYYY-MM-DD HH:MM:SS, with fractional seconds if any.
+pub fn format_timestamp(micros: i64) -> String {
+    let (year, month, day) = civil_from_days(micros.div_euclid(MICROS_PER_DAY));
+    let time = micros.rem_euclid(MICROS_PER_DAY);
+    let (seconds, fraction) = (time / 1_000_000, time % 1_000_000);
+    let mut s = format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    if fraction != 0 {
+        s += format!(".{:06}", fraction).trim_end_matches('0');
+    }
+    s
+}
+
+/// Adds a number of days to a date.
+pub fn add_days(date: i32, days: i64) -> Result<i32> {
+    (date as i64)
+        .checked_add(days)
+        .and_then(|date| date.try_into().ok())
+        .ok_or_else(|| Error::Value("Date out of range".into()))
+}
+
+/// Converts a date to a timestamp at midnight.
+pub fn date_to_timestamp(date: i32) -> Result<i64> {
+    (date as i64)
+        .checked_mul(MICROS_PER_DAY)
+        .ok_or_else(|| Error::Value("Timestamp out of range".into()))
+}
+
+/// Converts a timestamp to its date, discarding the time of day.
+pub fn timestamp_to_date(timestamp: i64) -> i32 {
+    // i64::MAX microseconds is about 106 million days, which fits in an i32.
+    timestamp.div_euclid(MICROS_PER_DAY) as i32
+}
@@ -0,0 +1,203 @@
+//! Fixed-point decimal numbers, for exact arithmetic on e.g. money.
+
+use crate::error::{Error, Result};
+
+use serde_derive::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// A decimal number with 18 fractional digits, stored as an integer scaled by 10^18. Since the
+/// scale is fixed, the integer order is the numeric order, which allows decimals to be encoded
+/// order-preserving in keys. The range is about ±1.7 * 10^20. Results are rounded half away from
+/// zero to 18 fractional digits.
+#[derive(
+    Clone, Copy, Debug, Default, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[serde(transparent)]
+pub struct Decimal(i128);
+
+impl Decimal {
+    /// The number of fractional digits.
+    pub const SCALE: u32 = 18;
+
+    /// The scaled representation of 1.
+    const ONE: i128 = 10_i128.pow(Self::SCALE);
+
+    /// Converts a float to a decimal, via its shortest exact string representation.
+    pub fn from_float(f: f64) -> Result<Self> {
+        if !f.is_finite() {
+            return Err(Error::Value(format!("Can't convert {} to a decimal", f)));
+        }
+        f.to_string().parse()
+    }
+
+    /// Converts the decimal to the nearest float.
+    pub fn to_float(self) -> f64 {
+        self.0 as f64 / Self::ONE as f64
+    }
+
+    /// Rounds the decimal to the nearest integer.
+    pub fn to_integer(self) -> Result<i64> {
+        (self.round(0)?.0 / Self::ONE).try_into().map_err(|_| overflow())
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self> {
+        self.0.checked_add(other.0).map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self> {
+        self.0.checked_sub(other.0).map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_mul(self, other: Self) -> Result<Self> {
+        mul_div(self.0, other.0, Self::ONE).map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_div(self, other: Self) -> Result<Self> {
+        if other.0 == 0 {
+            return Err(Error::Value("Can't divide by zero".into()));
+        }
+        mul_div(self.0, Self::ONE, other.0).map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_rem(self, other: Self) -> Result<Self> {
+        if other.0 == 0 {
+            return Err(Error::Value("Can't divide by zero".into()));
+        }
+        self.0.checked_rem(other.0).map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_neg(self) -> Result<Self> {
+        self.0.checked_neg().map(Self).ok_or_else(overflow)
+    }
+
+    pub fn checked_abs(self) -> Result<Self> {
+        self.0.checked_abs().map(Self).ok_or_else(overflow)
+    }
+
+    /// Rounds up to the nearest integer.
+    pub fn ceil(self) -> Result<Self> {
+        self.checked_neg()?.floor()?.checked_neg()
+    }
+
+    /// Rounds down to the nearest integer.
+    pub fn floor(self) -> Result<Self> {
+        self.0.div_euclid(Self::ONE).checked_mul(Self::ONE).map(Self).ok_or_else(overflow)
+    }
+
+    /// Rounds to the given number of fractional digits, which may be negative to round to a
+    /// multiple of a power of ten. Ties round away from zero.
+    pub fn round(self, digits: i64) -> Result<Self> {
+        if digits >= Self::SCALE as i64 {
+            return Ok(self);
+        }
+        // 10^38 is the largest power of ten that fits in an i128.
+        let factor = 10_i128.pow((Self::SCALE as i64).saturating_sub(digits).min(38) as u32);
+        let half = self.0.signum() * (factor / 2);
+        match self.0.checked_add(half) {
+            Some(i) => Ok(Self(i / factor * factor)),
+            None => Err(overflow()),
+        }
+    }
+}
+
+impl From<i64> for Decimal {
+    fn from(i: i64) -> Self {
+        // Can't overflow, since i64::MAX * 10^18 < i128::MAX.
+        Self(i as i128 * Self::ONE)
+    }
+}
+
+impl FromStr for Decimal {
+    type Err = Error;
+
+    /// Parses a decimal of the form [+-]digits[.digits].
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::Value(format!("Invalid decimal {}", s));
+        let trimmed = s.trim();
+        let (negative, unsigned) = match trimmed.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        if integer.is_empty() && fraction.is_empty()
+            || !integer.bytes().chain(fraction.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let mut value: i128 = 0;
+        let digits = integer.bytes().chain(fraction.bytes().chain(std::iter::repeat(b'0')));
+        for digit in digits.take(integer.len() + Self::SCALE as usize) {
+            value = value
+                .checked_mul(10)
+                .and_then(|v| v.checked_add((digit - b'0') as i128))
+                .ok_or_else(overflow)?;
+        }
+        // Round away from zero on any discarded digits of 5 or more.
+        if fraction.as_bytes().get(Self::SCALE as usize).is_some_and(|digit| *digit >= b'5') {
+            value = value.checked_add(1).ok_or_else(overflow)?;
+        }
+        Ok(Self(if negative { -value } else { value }))
+    }
+}
+
+impl std::fmt::Display for Decimal {
+    /// Formats the decimal without trailing fractional zeros, e.g. 1.5 or 2.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0 < 0 {
+            f.write_str("-")?;
+        }
+        let (integer, fraction) = (self.0 / Self::ONE, self.0 % Self::ONE);
+        write!(f, "{}", integer.unsigned_abs())?;
+        if fraction != 0 {
+            let fraction = format!("{:018}", fraction.unsigned_abs());
+            write!(f, ".{}", fraction.trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+fn overflow() -> Error {
+    Error::Value("Decimal overflow".into())
+}
+
+/// Computes a * b / d rounded half away from zero, using a 256-bit intermediate product. Returns
+/// None if the result doesn't fit in an i128 or d is 0.
+fn mul_div(a: i128, b: i128, d: i128) -> Option<i128> {
+    const MASK: u128 = u64::MAX as u128;
+    let negative = (a < 0) ^ (b < 0) ^ (d < 0);
+    let (a, b, d) = (a.unsigned_abs(), b.unsigned_abs(), d.unsigned_abs());
+    if d == 0 {
+        return None;
+    }
+
+    // Multiply the 64-bit halves into a 256-bit product (high, low).
+    let (a_high, a_low, b_high, b_low) = (a >> 64, a & MASK, b >> 64, b & MASK);
+    let (low_low, low_high) = (a_low * b_low, a_low * b_high);
+    let (high_low, high_high) = (a_high * b_low, a_high * b_high);
+    let middle = (low_low >> 64) + (low_high & MASK) + (high_low & MASK);
+    let low = (low_low & MASK) | (middle << 64);
+    let high = high_high + (low_high >> 64) + (high_low >> 64) + (middle >> 64);
+
+    // Divide by d using binary long division. The quotient must fit in 128 bits, i.e. the high
+    // half must be less than d.
+    if high >= d {
+        return None;
+    }
+    let (mut quotient, mut remainder) = (0_u128, high);
+    for i in (0..128).rev() {
+        let carry = remainder >> 127;
+        remainder = (remainder << 1) | ((low >> i) & 1);
+        quotient <<= 1;
+        if carry == 1 || remainder >= d {
+            remainder = remainder.wrapping_sub(d);
+            quotient |= 1;
+        }
+    }
+    if remainder >= d - remainder {
+        quotient = quotient.checked_add(1)?;
+    }
+
+    let quotient = i128::try_from(quotient).ok()?;
+    Some(if negative { -quotient } else { quotient })
+}
@@ -1,7 +1,11 @@
-use super::{Row, Value};
+use super::{datetime, decimal, json, parse_uuid, DataType, Row, Rows, Value};
 use crate::error::{Error, Result};
+use crate::sql::plan::Node;
 
 use serde_derive::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
 use std::fmt::{self, Display};
 use std::mem::replace;
 
@@ -10,6 +14,9 @@ pub enum Expression {
     // Values
     Constant(Value),
     Field(usize, Option<(Option<String>, String)>),
+    // A field of an enclosing query's current row, referenced by a correlated subquery. The
+    // first value is the number of query levels up, 1 for the directly enclosing query.
+    OuterField(usize, usize, Option<(Option<String>, String)>),
 
     // Logical operations
     And(Box<Expression>, Box<Expression>),
@@ -19,8 +26,11 @@ pub enum Expression {
     // Comparisons operations (GTE, LTE, and NEQ are composite operations)
     Equal(Box<Expression>, Box<Expression>),
     GreaterThan(Box<Expression>, Box<Expression>),
+    ILike(Box<Expression>, Box<Expression>),
     IsNull(Box<Expression>),
     LessThan(Box<Expression>, Box<Expression>),
+    Like(Box<Expression>, Box<Expression>),
+    Regex(Box<Expression>, Box<Expression>),
 
     // Mathematical operations
     Add(Box<Expression>, Box<Expression>),
@@ -32,6 +42,20 @@ pub enum Expression {
     Multiply(Box<Expression>, Box<Expression>),
     Negate(Box<Expression>),
     Subtract(Box<Expression>, Box<Expression>),
+
+    // JSON operations
+    JsonGet(Box<Expression>, Box<Expression>),
+    JsonGetText(Box<Expression>, Box<Expression>),
+
+    // Scalar functions
+    Function(Function, Vec<Expression>),
+
+    // Subqueries, given as query plans. These must be resolved into their results by the executor
+    // before evaluation, see resolve_subqueries(). IN subqueries resolve to an InList.
+    Exists(Box<Node>),
+    InList(Box<Expression>, Vec<Value>),
+    InSubquery(Box<Expression>, Box<Node>),
+    Subquery(Box<Node>),
 }
 
 impl Expression {
@@ -41,6 +65,12 @@ impl Expression {
             // Constant values
             Self::Constant(c) => c.clone(),
             Self::Field(i, _) => row.and_then(|row| row.get(*i).cloned()).unwrap_or(Null),
+            Self::OuterField(..) => {
+                return Err(Error::Internal(format!("Unbound outer field {}", self)))
+            }
+            Self::Exists(_) | Self::InSubquery(..) | Self::Subquery(_) => {
+                return Err(Error::Internal("Unresolved subquery".into()))
+            }
 
             // Logical operations
             Self::And(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
@@ -77,9 +107,7 @@ impl Expression {
                 (Float(lhs), Float(rhs)) => Boolean(lhs == rhs),
                 (String(lhs), String(rhs)) => Boolean(lhs == rhs),
                 (Null, _) | (_, Null) => Null,
-                (lhs, rhs) => {
-                    return Err(Error::Value(format!("Can't compare {} and {}", lhs, rhs)))
-                }
+                (lhs, rhs) => Boolean(compare(lhs, rhs)? == Some(Ordering::Equal)),
             },
             Self::GreaterThan(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 #[allow(clippy::bool_comparison)]
@@ -90,9 +118,7 @@ impl Expression {
                 (Float(lhs), Float(rhs)) => Boolean(lhs > rhs),
                 (String(lhs), String(rhs)) => Boolean(lhs > rhs),
                 (Null, _) | (_, Null) => Null,
-                (lhs, rhs) => {
-                    return Err(Error::Value(format!("Can't compare {} and {}", lhs, rhs)))
-                }
+                (lhs, rhs) => Boolean(compare(lhs, rhs)? == Some(Ordering::Greater)),
             },
             Self::LessThan(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
                 #[allow(clippy::bool_comparison)]
@@ -103,17 +129,51 @@ impl Expression {
                 (Float(lhs), Float(rhs)) => Boolean(lhs < rhs),
                 (String(lhs), String(rhs)) => Boolean(lhs < rhs),
                 (Null, _) | (_, Null) => Null,
-                (lhs, rhs) => {
-                    return Err(Error::Value(format!("Can't compare {} and {}", lhs, rhs)))
-                }
+                (lhs, rhs) => Boolean(compare(lhs, rhs)? == Some(Ordering::Less)),
             },
             Self::IsNull(expr) => match expr.evaluate(row)? {
                 Null => Boolean(true),
                 _ => Boolean(false),
             },
+            // Like a chain of = and OR, this is NULL if nothing matches but a value is NULL.
+            Self::InList(lhs, values) => match lhs.evaluate(row)? {
+                Null => Null,
+                lhs => {
+                    let mut result = Boolean(false);
+                    for value in values {
+                        let lhs = Self::Constant(lhs.clone()).into();
+                        match Self::Equal(lhs, Self::Constant(value.clone()).into())
+                            .evaluate(None)?
+                        {
+                            Boolean(true) => return Ok(Boolean(true)),
+                            Null => result = Null,
+                            _ => {}
+                        }
+                    }
+                    result
+                }
+            },
+            Self::Like(lhs, rhs) | Self::ILike(lhs, rhs) | Self::Regex(lhs, rhs) => {
+                match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+                    (String(lhs), String(rhs)) => {
+                        let regex = match self {
+                            Self::Like(_, _) => like_regex(&rhs, false)?,
+                            Self::ILike(_, _) => like_regex(&rhs, true)?,
+                            _ => regex::Regex::new(&rhs).map_err(|err| {
+                                Error::Value(format!("Invalid regular expression {}: {}", rhs, err))
+                            })?,
+                        };
+                        Boolean(regex.is_match(&lhs))
+                    }
+                    (String(_), Null) | (Null, String(_)) | (Null, Null) => Null,
+                    (lhs, rhs) => {
+                        return Err(Error::Value(format!("Can't match {} and {}", lhs, rhs)))
+                    }
+                }
+            }
 
             // Mathematical operations
-            Self::Add(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+            Self::Add(lhs, rhs) => match arithmetic_operands(lhs, rhs, row)? {
                 (Integer(lhs), Integer(rhs)) => Integer(
                     lhs.checked_add(rhs).ok_or_else(|| Error::Value("Integer overflow".into()))?,
                 ),
@@ -125,16 +185,30 @@ impl Expression {
                 (Null, Float(_)) => Null,
                 (Null, Integer(_)) => Null,
                 (Null, Null) => Null,
+                (Decimal(lhs), Decimal(rhs)) => Decimal(lhs.checked_add(rhs)?),
+                (Decimal(lhs), Integer(rhs)) => Decimal(lhs.checked_add(rhs.into())?),
+                (Integer(lhs), Decimal(rhs)) => Decimal(rhs.checked_add(lhs.into())?),
+                (Decimal(lhs), Float(rhs)) => Float(lhs.to_float() + rhs),
+                (Float(lhs), Decimal(rhs)) => Float(lhs + rhs.to_float()),
+                (Date(date), Integer(days)) | (Integer(days), Date(date)) => {
+                    Date(datetime::add_days(date, days)?)
+                }
+                (Timestamp(lhs), Integer(rhs)) | (Integer(rhs), Timestamp(lhs)) => {
+                    Timestamp(lhs.checked_add(rhs).ok_or_else(timestamp_overflow)?)
+                }
+                (Decimal(_) | Date(_) | Timestamp(_), Null)
+                | (Null, Decimal(_) | Date(_) | Timestamp(_)) => Null,
                 (lhs, rhs) => return Err(Error::Value(format!("Can't add {} and {}", lhs, rhs))),
             },
             Self::Assert(expr) => match expr.evaluate(row)? {
                 Float(f) => Float(f),
                 Integer(i) => Integer(i),
+                Decimal(d) => Decimal(d),
                 Null => Null,
                 expr => return Err(Error::Value(format!("Can't take the positive of {}", expr))),
             },
-            Self::Divide(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
-                (Integer(_), Integer(rhs)) if rhs == 0 => {
+            Self::Divide(lhs, rhs) => match arithmetic_operands(lhs, rhs, row)? {
+                (Integer(_), Integer(0)) => {
                     return Err(Error::Value("Can't divide by zero".into()))
                 }
                 (Integer(lhs), Integer(rhs)) => Integer(lhs / rhs),
@@ -146,6 +220,14 @@ impl Expression {
                 (Null, Float(_)) => Null,
                 (Null, Integer(_)) => Null,
                 (Null, Null) => Null,
+                (Decimal(lhs), Decimal(rhs)) => Decimal(lhs.checked_div(rhs)?),
+                (Decimal(lhs), Integer(rhs)) => Decimal(lhs.checked_div(rhs.into())?),
+                (Integer(lhs), Decimal(rhs)) => {
+                    Decimal(decimal::Decimal::from(lhs).checked_div(rhs)?)
+                }
+                (Decimal(lhs), Float(rhs)) => Float(lhs.to_float() / rhs),
+                (Float(lhs), Decimal(rhs)) => Float(lhs / rhs.to_float()),
+                (Decimal(_), Null) | (Null, Decimal(_)) => Null,
                 (lhs, rhs) => {
                     return Err(Error::Value(format!("Can't divide {} and {}", lhs, rhs)))
                 }
@@ -176,8 +258,8 @@ impl Expression {
                 Null => Null,
                 value => return Err(Error::Value(format!("Can't take factorial of {}", value))),
             },
-            Self::Modulo(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
-                (Integer(_), Integer(rhs)) if rhs == 0 => {
+            Self::Modulo(lhs, rhs) => match arithmetic_operands(lhs, rhs, row)? {
+                (Integer(_), Integer(0)) => {
                     return Err(Error::Value("Can't divide by zero".into()))
                 }
                 (Integer(lhs), Integer(rhs)) => Integer(lhs % rhs),
@@ -189,11 +271,19 @@ impl Expression {
                 (Null, Float(_)) => Null,
                 (Null, Integer(_)) => Null,
                 (Null, Null) => Null,
+                (Decimal(lhs), Decimal(rhs)) => Decimal(lhs.checked_rem(rhs)?),
+                (Decimal(lhs), Integer(rhs)) => Decimal(lhs.checked_rem(rhs.into())?),
+                (Integer(lhs), Decimal(rhs)) => {
+                    Decimal(decimal::Decimal::from(lhs).checked_rem(rhs)?)
+                }
+                (Decimal(lhs), Float(rhs)) => Float(lhs.to_float() % rhs),
+                (Float(lhs), Decimal(rhs)) => Float(lhs % rhs.to_float()),
+                (Decimal(_), Null) | (Null, Decimal(_)) => Null,
                 (lhs, rhs) => {
                     return Err(Error::Value(format!("Can't take modulo of {} and {}", lhs, rhs)))
                 }
             },
-            Self::Multiply(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+            Self::Multiply(lhs, rhs) => match arithmetic_operands(lhs, rhs, row)? {
                 (Integer(lhs), Integer(rhs)) => Integer(
                     lhs.checked_mul(rhs).ok_or_else(|| Error::Value("Integer overflow".into()))?,
                 ),
@@ -205,6 +295,12 @@ impl Expression {
                 (Null, Float(_)) => Null,
                 (Null, Integer(_)) => Null,
                 (Null, Null) => Null,
+                (Decimal(lhs), Decimal(rhs)) => Decimal(lhs.checked_mul(rhs)?),
+                (Decimal(lhs), Integer(rhs)) => Decimal(lhs.checked_mul(rhs.into())?),
+                (Integer(lhs), Decimal(rhs)) => Decimal(rhs.checked_mul(lhs.into())?),
+                (Decimal(lhs), Float(rhs)) => Float(lhs.to_float() * rhs),
+                (Float(lhs), Decimal(rhs)) => Float(lhs * rhs.to_float()),
+                (Decimal(_), Null) | (Null, Decimal(_)) => Null,
                 (lhs, rhs) => {
                     return Err(Error::Value(format!("Can't multiply {} and {}", lhs, rhs)))
                 }
@@ -212,10 +308,13 @@ impl Expression {
             Self::Negate(expr) => match expr.evaluate(row)? {
                 Integer(i) => Integer(-i),
                 Float(f) => Float(-f),
+                Decimal(d) => Decimal(d.checked_neg()?),
                 Null => Null,
                 value => return Err(Error::Value(format!("Can't negate {}", value))),
             },
-            Self::Subtract(lhs, rhs) => match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+            Self::Function(function, args) => function
+                .evaluate(args.iter().map(|arg| arg.evaluate(row)).collect::<Result<_>>()?)?,
+            Self::Subtract(lhs, rhs) => match arithmetic_operands(lhs, rhs, row)? {
                 (Integer(lhs), Integer(rhs)) => Integer(
                     lhs.checked_sub(rhs).ok_or_else(|| Error::Value("Integer overflow".into()))?,
                 ),
@@ -227,10 +326,45 @@ impl Expression {
                 (Null, Float(_)) => Null,
                 (Null, Integer(_)) => Null,
                 (Null, Null) => Null,
+                (Decimal(lhs), Decimal(rhs)) => Decimal(lhs.checked_sub(rhs)?),
+                (Decimal(lhs), Integer(rhs)) => Decimal(lhs.checked_sub(rhs.into())?),
+                (Integer(lhs), Decimal(rhs)) => {
+                    Decimal(decimal::Decimal::from(lhs).checked_sub(rhs)?)
+                }
+                (Decimal(lhs), Float(rhs)) => Float(lhs.to_float() - rhs),
+                (Float(lhs), Decimal(rhs)) => Float(lhs - rhs.to_float()),
+                (Date(date), Integer(days)) => Date(datetime::add_days(
+                    date,
+                    days.checked_neg().ok_or_else(|| Error::Value("Date out of range".into()))?,
+                )?),
+                (Date(lhs), Date(rhs)) => Integer(lhs as i64 - rhs as i64),
+                (Timestamp(lhs), Integer(rhs)) => {
+                    Timestamp(lhs.checked_sub(rhs).ok_or_else(timestamp_overflow)?)
+                }
+                (Timestamp(lhs), Timestamp(rhs)) => {
+                    Integer(lhs.checked_sub(rhs).ok_or_else(timestamp_overflow)?)
+                }
+                (Decimal(_) | Date(_) | Timestamp(_), Null)
+                | (Null, Decimal(_) | Date(_) | Timestamp(_)) => Null,
                 (lhs, rhs) => {
                     return Err(Error::Value(format!("Can't subtract {} and {}", lhs, rhs)))
                 }
             },
+
+            // JSON operations, where -> returns JSON and ->> returns text
+            Self::JsonGet(lhs, rhs) | Self::JsonGetText(lhs, rhs) => {
+                match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+                    (Null, _) | (_, Null) => Null,
+                    (Json(doc) | String(doc), key) => match json::get(json::parse(&doc)?, &key)? {
+                        Some(value) if matches!(self, Self::JsonGet(..)) => Json(value.to_string()),
+                        Some(value) => json::to_text(value),
+                        None => Null,
+                    },
+                    (lhs, rhs) => {
+                        return Err(Error::Value(format!("Can't get {} from {}", rhs, lhs)))
+                    }
+                }
+            }
         })
     }
 
@@ -238,6 +372,110 @@ impl Expression {
         !self.walk(&|e| !visitor(e))
     }
 
+    /// Returns the indexes of the fields referenced by the expression, including the outer
+    /// fields referenced by correlated subqueries.
+    pub fn fields(&self) -> BTreeSet<usize> {
+        let fields = RefCell::new(BTreeSet::new());
+        self.walk(&|e| {
+            match e {
+                Self::Field(i, _) => {
+                    fields.borrow_mut().insert(*i);
+                }
+                Self::Exists(node) | Self::InSubquery(_, node) | Self::Subquery(node) => {
+                    fields.borrow_mut().extend(node.outer_fields());
+                }
+                _ => {}
+            }
+            true
+        });
+        fields.into_inner()
+    }
+
+    /// Returns whether the expression contains any subqueries.
+    pub fn has_subqueries(&self) -> bool {
+        self.contains(&|e| matches!(e, Self::Exists(_) | Self::InSubquery(..) | Self::Subquery(_)))
+    }
+
+    /// Transforms the plan of a subquery expression with the given closure, returning other
+    /// expressions as-is. This doesn't descend into child expressions, use transform() for that.
+    pub fn transform_subquery<F: Fn(Node) -> Result<Node>>(self, f: &F) -> Result<Self> {
+        Ok(match self {
+            Self::Exists(node) => Self::Exists(f(*node)?.into()),
+            Self::InSubquery(lhs, node) => Self::InSubquery(lhs, f(*node)?.into()),
+            Self::Subquery(node) => Self::Subquery(f(*node)?.into()),
+            e => e,
+        })
+    }
+
+    /// Replaces subqueries with their results, using the closure to execute their plans: EXISTS
+    /// with whether the subquery returns any rows, IN with an InList of the returned values, and
+    /// scalar subqueries with the returned value or NULL if there are no rows. If a row is given,
+    /// the outer fields of correlated subqueries are bound to its values before execution,
+    /// otherwise correlated subqueries are left as-is.
+    pub fn resolve_subqueries<F: Fn(Node) -> Result<Rows>>(
+        self,
+        row: Option<&Row>,
+        execute: &F,
+    ) -> Result<Self> {
+        let run = |node: Box<Node>| -> Result<Option<Rows>> {
+            match row {
+                Some(row) => Ok(Some(execute(node.bind_outer(row)?)?)),
+                None if node.outer_fields().is_empty() => Ok(Some(execute(*node)?)),
+                None => Ok(None),
+            }
+        };
+        self.transform(&Ok, &|e| match e {
+            Self::Exists(node) => match run(node.clone())? {
+                Some(mut rows) => {
+                    Ok(Self::Constant(Value::Boolean(rows.next().transpose()?.is_some())))
+                }
+                None => Ok(Self::Exists(node)),
+            },
+            Self::InSubquery(lhs, node) => match run(node.clone())? {
+                Some(rows) => {
+                    let values = rows
+                        .map(|r| r.map(|row| row.into_iter().next().unwrap_or(Value::Null)))
+                        .collect::<Result<_>>()?;
+                    Ok(Self::InList(lhs, values))
+                }
+                None => Ok(Self::InSubquery(lhs, node)),
+            },
+            Self::Subquery(node) => match run(node.clone())? {
+                Some(mut rows) => {
+                    let value = match rows.next().transpose()? {
+                        Some(row) => row.into_iter().next().unwrap_or(Value::Null),
+                        None => Value::Null,
+                    };
+                    if rows.next().is_some() {
+                        return Err(Error::Value("Subquery returned more than one row".into()));
+                    }
+                    Ok(Self::Constant(value))
+                }
+                None => Ok(Self::Subquery(node)),
+            },
+            e => Ok(e),
+        })
+    }
+
+    /// Returns whether the expression calls a volatile function, e.g. NEXTVAL().
+    pub fn is_volatile(&self) -> bool {
+        self.contains(&|e| matches!(e, Self::Function(f, _) if f.is_volatile()))
+    }
+
+    /// Replaces NEXTVAL() calls with the next value of their sequence, as given by the closure.
+    /// The sequence name must be a constant, which the planner guarantees.
+    pub fn resolve_sequences<F: Fn(&str) -> Result<i64>>(self, next_value: &F) -> Result<Self> {
+        self.transform(&Ok, &|e| match e {
+            Self::Function(Function::NextVal, args) => match args.as_slice() {
+                [Self::Constant(Value::String(sequence))] => {
+                    Ok(Self::Constant(Value::Integer(next_value(sequence)?)))
+                }
+                _ => Err(Error::Value("NEXTVAL requires a constant sequence name".into())),
+            },
+            e => Ok(e),
+        })
+    }
+
     /// Replaces the expression with result of the closure. Helper function for transform().
     fn replace_with<F: Fn(Self) -> Result<Self>>(&mut self, f: F) -> Result<()> {
         // Temporarily replace expression with a null value, in case closure panics. May consider
@@ -261,10 +499,15 @@ impl Expression {
             | Self::Equal(lhs, rhs)
             | Self::Exponentiate(lhs, rhs)
             | Self::GreaterThan(lhs, rhs)
+            | Self::ILike(lhs, rhs)
+            | Self::JsonGet(lhs, rhs)
+            | Self::JsonGetText(lhs, rhs)
             | Self::LessThan(lhs, rhs)
+            | Self::Like(lhs, rhs)
             | Self::Modulo(lhs, rhs)
             | Self::Multiply(lhs, rhs)
             | Self::Or(lhs, rhs)
+            | Self::Regex(lhs, rhs)
             | Self::Subtract(lhs, rhs) => {
                 Self::replace_with(lhs, |e| e.transform(before, after))?;
                 Self::replace_with(rhs, |e| e.transform(before, after))?;
@@ -272,11 +515,24 @@ impl Expression {
 
             Self::Assert(expr)
             | Self::Factorial(expr)
+            | Self::InList(expr, _)
+            | Self::InSubquery(expr, _)
             | Self::IsNull(expr)
             | Self::Negate(expr)
             | Self::Not(expr) => Self::replace_with(expr, |e| e.transform(before, after))?,
 
-            Self::Constant(_) | Self::Field(_, _) => {}
+            Self::Function(_, args) => {
+                for arg in args {
+                    Self::replace_with(arg, |e| e.transform(before, after))?;
+                }
+            }
+
+            // Subquery plans have their own fields, so they're not descended into.
+            Self::Constant(_)
+            | Self::Field(_, _)
+            | Self::OuterField(..)
+            | Self::Exists(_)
+            | Self::Subquery(_) => {}
         };
         after(self)
     }
@@ -290,19 +546,32 @@ impl Expression {
                 | Self::Equal(lhs, rhs)
                 | Self::Exponentiate(lhs, rhs)
                 | Self::GreaterThan(lhs, rhs)
+                | Self::ILike(lhs, rhs)
+                | Self::JsonGet(lhs, rhs)
+                | Self::JsonGetText(lhs, rhs)
                 | Self::LessThan(lhs, rhs)
+                | Self::Like(lhs, rhs)
                 | Self::Modulo(lhs, rhs)
                 | Self::Multiply(lhs, rhs)
                 | Self::Or(lhs, rhs)
+                | Self::Regex(lhs, rhs)
                 | Self::Subtract(lhs, rhs) => lhs.walk(visitor) && rhs.walk(visitor),
 
                 Self::Assert(expr)
                 | Self::Factorial(expr)
+                | Self::InList(expr, _)
+                | Self::InSubquery(expr, _)
                 | Self::IsNull(expr)
                 | Self::Negate(expr)
                 | Self::Not(expr) => expr.walk(visitor),
 
-                Self::Constant(_) | Self::Field(_, _) => true,
+                Self::Function(_, args) => args.iter().all(|arg| arg.walk(visitor)),
+
+                Self::Constant(_)
+                | Self::Field(_, _)
+                | Self::OuterField(..)
+                | Self::Exists(_)
+                | Self::Subquery(_) => true,
             }
     }
 
@@ -426,13 +695,19 @@ impl Expression {
     }
 
     // Checks if the expression is a field lookup, and returns the list of values looked up.
-    // Expressions must be a combination of =, IS NULL, OR to be converted.
+    // Expressions must be a combination of =, IS NULL, OR to be converted. Since = NULL never
+    // matches anything, it looks up no values.
     pub fn as_lookup(&self, field: usize) -> Option<Vec<Value>> {
         use Expression::*;
         // FIXME This should use a single match level, but since the child expressions are boxed
         // that would require box patterns, which are unstable.
         match &self {
             Equal(lhs, rhs) => match (&**lhs, &**rhs) {
+                (Field(i, _), Constant(Value::Null)) | (Constant(Value::Null), Field(i, _))
+                    if i == &field =>
+                {
+                    Some(vec![])
+                }
                 (Field(i, _), Constant(v)) if i == &field => Some(vec![v.clone()]),
                 (Constant(v), Field(i, _)) if i == &field => Some(vec![v.clone()]),
                 (_, _) => None,
@@ -479,6 +754,36 @@ impl Expression {
     }
 }
 
+/// Compiles a LIKE pattern into an anchored regular expression. % matches any sequence of
+/// characters and _ matches a single character, while a backslash matches the following character
+/// literally (e.g. `\%` matches a percent sign).
+fn like_regex(pattern: &str, case_insensitive: bool) -> Result<regex::Regex> {
+    let mut regex = String::from("^");
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '\\' => match chars.next() {
+                Some(c) => regex.push_str(&regex::escape(&c.to_string())),
+                None => {
+                    return Err(Error::Value(format!(
+                        "Invalid LIKE pattern {}: trailing escape character",
+                        pattern
+                    )))
+                }
+            },
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex::RegexBuilder::new(&regex)
+        .case_insensitive(case_insensitive)
+        .dot_matches_new_line(true)
+        .build()
+        .map_err(|err| Error::Value(format!("Invalid LIKE pattern {}: {}", pattern, err)))
+}
+
 impl Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
@@ -486,6 +791,11 @@ impl Display for Expression {
             Self::Field(i, None) => format!("#{}", i),
             Self::Field(_, Some((None, name))) => name.to_string(),
             Self::Field(_, Some((Some(table), name))) => format!("{}.{}", table, name),
+            Self::OuterField(_, i, None) => format!("outer #{}", i),
+            Self::OuterField(_, _, Some((None, name))) => format!("outer {}", name),
+            Self::OuterField(_, _, Some((Some(table), name))) => {
+                format!("outer {}.{}", table, name)
+            }
 
             Self::And(lhs, rhs) => format!("{} AND {}", lhs, rhs),
             Self::Or(lhs, rhs) => format!("{} OR {}", lhs, rhs),
@@ -495,6 +805,14 @@ impl Display for Expression {
             Self::GreaterThan(lhs, rhs) => format!("{} > {}", lhs, rhs),
             Self::LessThan(lhs, rhs) => format!("{} < {}", lhs, rhs),
             Self::IsNull(expr) => format!("{} IS NULL", expr),
+            Self::InList(lhs, values) => format!(
+                "{} IN ({})",
+                lhs,
+                values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")
+            ),
+            Self::Like(lhs, rhs) => format!("{} LIKE {}", lhs, rhs),
+            Self::ILike(lhs, rhs) => format!("{} ILIKE {}", lhs, rhs),
+            Self::Regex(lhs, rhs) => format!("{} ~ {}", lhs, rhs),
 
             Self::Add(lhs, rhs) => format!("{} + {}", lhs, rhs),
             Self::Assert(expr) => expr.to_string(),
@@ -505,7 +823,466 @@ impl Display for Expression {
             Self::Multiply(lhs, rhs) => format!("{} * {}", lhs, rhs),
             Self::Negate(expr) => format!("-{}", expr),
             Self::Subtract(lhs, rhs) => format!("{} - {}", lhs, rhs),
+
+            Self::JsonGet(lhs, rhs) => format!("{} -> {}", lhs, rhs),
+            Self::JsonGetText(lhs, rhs) => format!("{} ->> {}", lhs, rhs),
+
+            Self::Exists(_) => "EXISTS (subquery)".to_string(),
+            Self::InSubquery(lhs, _) => format!("{} IN (subquery)", lhs),
+            Self::Subquery(_) => "(subquery)".to_string(),
+
+            Self::Function(Function::Cast(datatype), args) => format!(
+                "CAST({} AS {})",
+                args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", "),
+                datatype
+            ),
+            Self::Function(function, args) => format!(
+                "{}({})",
+                function,
+                args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", ")
+            ),
         };
         write!(f, "{}", s)
     }
 }
+
+/// A scalar function, i.e. one that computes a single value from its arguments.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Function {
+    // String functions
+    Concat,
+    Length,
+    Lower,
+    Replace,
+    Substr,
+    Trim,
+    Upper,
+
+    // Mathematical functions
+    Abs,
+    Ceil,
+    Floor,
+    Round,
+    Sqrt,
+
+    // NULL handling
+    Coalesce,
+    IfNull,
+    NullIf,
+
+    // JSON functions
+    JsonArrayLength,
+    JsonExtract,
+
+    // Type conversion
+    Cast(DataType),
+
+    // Sequences
+    NextVal,
+}
+
+impl Function {
+    /// Looks up a function by its (lowercase) name. CAST is parsed separately, since its target
+    /// type is not an expression.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "abs" => Self::Abs,
+            "ceil" | "ceiling" => Self::Ceil,
+            "coalesce" => Self::Coalesce,
+            "concat" => Self::Concat,
+            "floor" => Self::Floor,
+            "ifnull" => Self::IfNull,
+            "json_array_length" => Self::JsonArrayLength,
+            "json_extract" => Self::JsonExtract,
+            "length" => Self::Length,
+            "lower" => Self::Lower,
+            "nextval" => Self::NextVal,
+            "nullif" => Self::NullIf,
+            "replace" => Self::Replace,
+            "round" => Self::Round,
+            "sqrt" => Self::Sqrt,
+            "substr" | "substring" => Self::Substr,
+            "trim" => Self::Trim,
+            "upper" => Self::Upper,
+            _ => return None,
+        })
+    }
+
+    /// Returns the minimum and maximum number of arguments, where None is unbounded.
+    fn arity(&self) -> (usize, Option<usize>) {
+        match self {
+            Self::Abs
+            | Self::Cast(_)
+            | Self::Ceil
+            | Self::Floor
+            | Self::Length
+            | Self::Lower
+            | Self::NextVal
+            | Self::Sqrt
+            | Self::Trim
+            | Self::Upper => (1, Some(1)),
+            Self::IfNull | Self::JsonExtract | Self::NullIf => (2, Some(2)),
+            Self::Replace => (3, Some(3)),
+            Self::JsonArrayLength | Self::Round => (1, Some(2)),
+            Self::Substr => (2, Some(3)),
+            Self::Coalesce | Self::Concat => (1, None),
+        }
+    }
+
+    /// Returns whether the function accepts a value of the given type as argument i.
+    fn accepts(&self, i: usize, datatype: &DataType) -> bool {
+        use DataType::*;
+        match (self, datatype) {
+            (Self::Length | Self::Lower | Self::Replace | Self::Trim | Self::Upper, String) => true,
+            (Self::Length, Blob) => true,
+            (Self::NextVal, String) => true,
+            (Self::JsonArrayLength | Self::JsonExtract, String) => true,
+            (Self::JsonArrayLength | Self::JsonExtract, Json) => i == 0,
+            (Self::Substr, String) => i == 0,
+            (Self::Substr, Integer) => i > 0,
+            (Self::Abs | Self::Ceil | Self::Floor | Self::Sqrt, Integer | Float | Decimal) => true,
+            (Self::Round, Integer) => true,
+            (Self::Round, Float | Decimal) => i == 0,
+            (Self::Cast(_) | Self::Coalesce | Self::Concat | Self::IfNull | Self::NullIf, _) => {
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks the number and types of arguments, for use at plan time. Argument types are given
+    /// as None when they can't be known before execution.
+    pub fn check(&self, datatypes: &[Option<DataType>]) -> Result<()> {
+        let (min, max) = self.arity();
+        if datatypes.len() < min || max.is_some_and(|max| datatypes.len() > max) {
+            return Err(Error::Value(match max {
+                Some(max) if max == min => format!("{} takes {} arguments", self, min),
+                Some(max) => format!("{} takes {} to {} arguments", self, min, max),
+                None => format!("{} takes at least {} arguments", self, min),
+            }));
+        }
+        for (i, datatype) in datatypes.iter().enumerate() {
+            match datatype {
+                Some(datatype) if !self.accepts(i, datatype) => {
+                    return Err(Error::Value(format!(
+                        "{} can't take {} as argument {}",
+                        self,
+                        datatype,
+                        i + 1
+                    )))
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether the function has side effects, such that it must be evaluated exactly
+    /// once per row by the executor rather than e.g. folded at plan time.
+    pub fn is_volatile(&self) -> bool {
+        matches!(self, Self::NextVal)
+    }
+
+    /// Evaluates the function with the given arguments, which must match its arity.
+    pub fn evaluate(&self, mut args: Vec<Value>) -> Result<Value> {
+        use Value::*;
+        let invalid = |args: &[Value]| {
+            Error::Value(format!(
+                "Can't call {} with {}",
+                self,
+                args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>().join(", ")
+            ))
+        };
+        Ok(match (self, args.as_mut_slice()) {
+            // Sequences are resolved by the executor, see Expression::resolve_sequences().
+            (Self::NextVal, _) => {
+                return Err(Error::Value(
+                    "NEXTVAL can only be used in SELECT, INSERT and UPDATE expressions".into(),
+                ))
+            }
+
+            // NULL handling
+            (Self::Coalesce | Self::IfNull, args) => args
+                .iter_mut()
+                .find(|arg| arg != &&Null)
+                .map(|arg| replace(arg, Null))
+                .unwrap_or(Null),
+            (Self::NullIf, [lhs, rhs]) => {
+                let lhs = replace(lhs, Null);
+                match Expression::Equal(
+                    Expression::Constant(lhs.clone()).into(),
+                    Expression::Constant(replace(rhs, Null)).into(),
+                )
+                .evaluate(None)
+                {
+                    Ok(Boolean(true)) => Null,
+                    _ => lhs,
+                }
+            }
+
+            // Type conversion
+            (Self::Cast(datatype), [value]) => cast(replace(value, Null), datatype)?,
+
+            // Any other function yields NULL given a NULL argument, except CONCAT which skips them.
+            (Self::Concat, args) => {
+                String(args.iter().filter(|arg| arg != &&Null).map(|arg| arg.to_string()).collect())
+            }
+            (_, args) if args.contains(&Null) => Null,
+
+            // String functions
+            (Self::Length, [String(s)]) => Integer(s.chars().count() as i64),
+            (Self::Length, [Blob(b)]) => Integer(b.len() as i64),
+            (Self::Lower, [String(s)]) => String(s.to_lowercase()),
+            (Self::Replace, [String(s), String(from), String(to)]) => {
+                if from.is_empty() {
+                    String(std::mem::take(s))
+                } else {
+                    String(s.replace(from.as_str(), to))
+                }
+            }
+            (Self::Substr, [String(s), Integer(start)]) => {
+                String(s.chars().skip((*start).max(1) as usize - 1).collect())
+            }
+            (Self::Substr, [String(s), Integer(start), Integer(length)]) => {
+                if *length < 0 {
+                    return Err(Error::Value(format!("Negative substring length {}", length)));
+                }
+                // Positions are 1-based, and the length counts from start even if it's below 1.
+                let end = start.saturating_add(*length);
+                let start = (*start).max(1);
+                String(
+                    s.chars()
+                        .skip(start as usize - 1)
+                        .take(end.saturating_sub(start) as usize)
+                        .collect(),
+                )
+            }
+            (Self::Trim, [String(s)]) => String(s.trim().to_string()),
+            (Self::Upper, [String(s)]) => String(s.to_uppercase()),
+
+            // JSON functions, which also take documents as strings
+            (Self::JsonExtract, [Json(doc) | String(doc), String(path)]) => {
+                match json::extract(json::parse(doc)?, path)? {
+                    Some(value) => Json(value.to_string()),
+                    None => Null,
+                }
+            }
+            (Self::JsonArrayLength, [Json(doc) | String(doc)]) => {
+                json_array_length(Some(json::parse(doc)?))
+            }
+            (Self::JsonArrayLength, [Json(doc) | String(doc), String(path)]) => {
+                json_array_length(json::extract(json::parse(doc)?, path)?)
+            }
+
+            // Mathematical functions
+            (Self::Abs, [Integer(i)]) => {
+                Integer(i.checked_abs().ok_or_else(|| Error::Value("Integer overflow".into()))?)
+            }
+            (Self::Abs, [Float(f)]) => Float(f.abs()),
+            (Self::Abs, [Decimal(d)]) => Decimal(d.checked_abs()?),
+            (Self::Ceil, [Decimal(d)]) => Decimal(d.ceil()?),
+            (Self::Floor, [Decimal(d)]) => Decimal(d.floor()?),
+            (Self::Round, [Decimal(d)]) => Decimal(d.round(0)?),
+            (Self::Round, [Decimal(d), Integer(digits)]) => Decimal(d.round(*digits)?),
+            (Self::Ceil | Self::Floor | Self::Round, [Integer(i)]) => Integer(*i),
+            (Self::Ceil, [Float(f)]) => Float(f.ceil()),
+            (Self::Floor, [Float(f)]) => Float(f.floor()),
+            (Self::Round, [Float(f)]) => Float(f.round()),
+            (Self::Round, [Float(f), Integer(digits)]) => {
+                let factor = 10_f64.powi((*digits).clamp(i32::MIN as i64, i32::MAX as i64) as i32);
+                let (scaled, exact) = (*f * factor, 2_f64.powi(f64::MANTISSA_DIGITS as i32));
+                if factor == 0.0 {
+                    // Rounds to a power of ten larger than any float.
+                    Float(0_f64.copysign(*f))
+                } else if !scaled.is_finite() || scaled.abs() >= exact {
+                    // The float has no fractional digits beyond the given ones.
+                    Float(*f)
+                } else {
+                    Float(scaled.round() / factor)
+                }
+            }
+            (Self::Round, [Integer(i), Integer(digits)]) if *digits >= 0 => Integer(*i),
+            (Self::Round, [Integer(i), Integer(digits)]) => {
+                // Rounds to the nearest multiple of 10^-digits, away from zero on ties.
+                let (i, factor) = (*i as i128, 10_i128.pow(digits.unsigned_abs().min(20) as u32));
+                let rounded = (i + i.signum() * (factor / 2)) / factor * factor;
+                Integer(rounded.try_into().map_err(|_| Error::Value("Integer overflow".into()))?)
+            }
+            (Self::Sqrt, [Integer(i)]) if *i >= 0 => Float((*i as f64).sqrt()),
+            (Self::Sqrt, [Float(f)]) if *f >= 0.0 => Float(f.sqrt()),
+            (Self::Sqrt, [Decimal(d)]) if d.to_float() >= 0.0 => Float(d.to_float().sqrt()),
+
+            (_, args) => return Err(invalid(args)),
+        })
+    }
+}
+
+/// Casts a value to the given data type. NULL casts to NULL.
+pub fn cast(value: Value, datatype: &DataType) -> Result<Value> {
+    use Value::*;
+    Ok(match (value, datatype) {
+        (Null, _) => Null,
+        (value, datatype) if value.datatype().as_ref() == Some(datatype) => value,
+        (value, DataType::String) => String(value.to_string()),
+
+        (Integer(i), DataType::Boolean) => Boolean(i != 0),
+        (Float(f), DataType::Boolean) => Boolean(f != 0.0),
+        (String(s), DataType::Boolean) => match s.trim().to_lowercase().as_str() {
+            "true" | "t" | "1" => Boolean(true),
+            "false" | "f" | "0" => Boolean(false),
+            _ => return Err(Error::Value(format!("Can't cast {} to {}", s, datatype))),
+        },
+
+        (Boolean(b), DataType::Integer) => Integer(b as i64),
+        (Float(f), DataType::Integer) if f.is_finite() && f.abs() < i64::MAX as f64 => {
+            Integer(f.round() as i64)
+        }
+        (String(s), DataType::Integer) => Integer(
+            s.trim()
+                .parse()
+                .map_err(|_| Error::Value(format!("Can't cast {} to {}", s, datatype)))?,
+        ),
+
+        (Boolean(b), DataType::Float) => Float(if b { 1.0 } else { 0.0 }),
+        (Integer(i), DataType::Float) => Float(i as f64),
+        (String(s), DataType::Float) => Float(
+            s.trim()
+                .parse()
+                .map_err(|_| Error::Value(format!("Can't cast {} to {}", s, datatype)))?,
+        ),
+        (Decimal(d), DataType::Integer) => Integer(d.to_integer()?),
+        (Decimal(d), DataType::Float) => Float(d.to_float()),
+
+        (Integer(i), DataType::Decimal) => Decimal(i.into()),
+        (Float(f), DataType::Decimal) => Decimal(decimal::Decimal::from_float(f)?),
+        (String(s), DataType::Decimal) => Decimal(s.parse()?),
+
+        (String(s), DataType::Date) => Date(datetime::parse_date(&s)?),
+        (Timestamp(t), DataType::Date) => Date(datetime::timestamp_to_date(t)),
+        (String(s), DataType::Timestamp) => Timestamp(datetime::parse_timestamp(&s)?),
+        (Date(d), DataType::Timestamp) => Timestamp(datetime::date_to_timestamp(d)?),
+
+        // Strings with a \x prefix are hex-encoded, like blobs are formatted.
+        (String(s), DataType::Blob) => match s.strip_prefix("\\x") {
+            Some(h) => Blob(
+                hex::decode(h)
+                    .map_err(|_| Error::Value(format!("Can't cast {} to {}", s, datatype)))?,
+            ),
+            None => Blob(s.into_bytes()),
+        },
+        (Uuid(u), DataType::Blob) => Blob(u.to_be_bytes().to_vec()),
+
+        (String(s), DataType::Uuid) => Uuid(parse_uuid(&s)?),
+
+        // Strings are parsed as JSON documents, while other scalars become JSON scalars. JSON
+        // scalars cast like the corresponding values.
+        (String(s), DataType::Json) => Json(json::normalize(&s)?),
+        (value @ (Boolean(_) | Integer(_) | Float(_) | Decimal(_)), DataType::Json) => {
+            Json(json::from_value(&value).expect("scalar value"))
+        }
+        (Json(j), datatype) => match json::to_value(json::parse(&j)?) {
+            Json(j) => return Err(Error::Value(format!("Can't cast {} to {}", j, datatype))),
+            value => cast(value, datatype)?,
+        },
+        (Blob(b), DataType::Uuid) if b.len() == 16 => {
+            Uuid(u128::from_be_bytes(b.as_slice().try_into()?))
+        }
+
+        (value, datatype) => {
+            return Err(Error::Value(format!("Can't cast {} to {}", value, datatype)))
+        }
+    })
+}
+
+/// Compares values where at least one of them has a date, timestamp, decimal, blob, UUID or JSON
+/// type. A JSON scalar compared with a non-JSON value is compared as the corresponding value.
+/// A string compared with a date, timestamp, decimal, UUID or JSON document is cast to its type,
+/// such that e.g. `date > '2024-01-01'` works. Returns None for unordered values (i.e. NaN) and
+/// JSON nulls.
+fn compare(lhs: Value, rhs: Value) -> Result<Option<Ordering>> {
+    use Value::*;
+    let (lhs, rhs) = match (lhs, rhs) {
+        (Json(j), rhs) if !matches!(rhs, Json(_)) => (json::to_value(json::parse(&j)?), rhs),
+        (lhs, Json(j)) if !matches!(lhs, Json(_)) => (lhs, json::to_value(json::parse(&j)?)),
+        (lhs, rhs) => (lhs, rhs),
+    };
+    let (lhs, rhs) = match (lhs, rhs) {
+        (String(s), rhs @ (Date(_) | Timestamp(_) | Decimal(_) | Uuid(_) | Json(_))) => {
+            (cast(String(s), &rhs.datatype().expect("non-null"))?, rhs)
+        }
+        (lhs @ (Date(_) | Timestamp(_) | Decimal(_) | Uuid(_) | Json(_)), String(s)) => {
+            let datatype = lhs.datatype().expect("non-null");
+            (lhs, cast(String(s), &datatype)?)
+        }
+        (lhs, rhs) => (lhs, rhs),
+    };
+    match (&lhs, &rhs) {
+        (Date(_) | Timestamp(_), Date(_) | Timestamp(_))
+        | (Decimal(_), Integer(_) | Float(_) | Decimal(_))
+        | (Integer(_) | Float(_), Decimal(_))
+        | (Blob(_), Blob(_))
+        | (Uuid(_), Uuid(_))
+        | (Json(_), Json(_))
+        | (Boolean(_), Boolean(_))
+        | (Integer(_) | Float(_), Integer(_) | Float(_))
+        | (String(_), String(_)) => Ok(lhs.partial_cmp(&rhs)),
+        (Null, _) | (_, Null) => Ok(None),
+        _ => Err(Error::Value(format!("Can't compare {} and {}", lhs, rhs))),
+    }
+}
+
+/// Returns the length of a JSON array, 0 for other JSON values, or NULL if there is no value.
+fn json_array_length(json: Option<serde_json::Value>) -> Value {
+    match json {
+        Some(serde_json::Value::Array(array)) => Value::Integer(array.len() as i64),
+        Some(_) => Value::Integer(0),
+        None => Value::Null,
+    }
+}
+
+/// Evaluates the operands of an arithmetic operator. When one of them is a decimal, a float
+/// operand is converted to a decimal such that e.g. `price * 1.1` stays exact. Floats without an
+/// exact decimal value, e.g. NaN or 1e-30, are left as-is, and the arithmetic is done on floats.
+fn arithmetic_operands(
+    lhs: &Expression,
+    rhs: &Expression,
+    row: Option<&Row>,
+) -> Result<(Value, Value)> {
+    use Value::*;
+    let decimal = |f: f64| decimal::Decimal::from_float(f).ok().filter(|d| d.to_float() == f);
+    Ok(match (lhs.evaluate(row)?, rhs.evaluate(row)?) {
+        (Decimal(lhs), Float(rhs)) => (Decimal(lhs), decimal(rhs).map_or(Float(rhs), Decimal)),
+        (Float(lhs), Decimal(rhs)) => (decimal(lhs).map_or(Float(lhs), Decimal), Decimal(rhs)),
+        operands => operands,
+    })
+}
+
+fn timestamp_overflow() -> Error {
+    Error::Value("Timestamp out of range".into())
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Abs => "ABS",
+            Self::Cast(_) => "CAST",
+            Self::Ceil => "CEIL",
+            Self::Coalesce => "COALESCE",
+            Self::Concat => "CONCAT",
+            Self::Floor => "FLOOR",
+            Self::IfNull => "IFNULL",
+            Self::JsonArrayLength => "JSON_ARRAY_LENGTH",
+            Self::JsonExtract => "JSON_EXTRACT",
+            Self::Length => "LENGTH",
+            Self::Lower => "LOWER",
+            Self::NextVal => "NEXTVAL",
+            Self::NullIf => "NULLIF",
+            Self::Replace => "REPLACE",
+            Self::Round => "ROUND",
+            Self::Sqrt => "SQRT",
+            Self::Substr => "SUBSTR",
+            Self::Trim => "TRIM",
+            Self::Upper => "UPPER",
+        })
+    }
+}
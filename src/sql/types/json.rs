@@ -0,0 +1,106 @@
+//! JSON documents. Documents are stored as compact JSON text with object keys in sorted order,
+//! such that equal documents have equal text, and are parsed when accessed.
+
+use super::Value;
+use crate::error::{Error, Result};
+
+use serde_json::Value as Json;
+
+/// Parses and normalizes a JSON document.
+pub fn normalize(s: &str) -> Result<String> {
+    Ok(parse(s)?.to_string())
+}
+
+/// Parses a JSON document.
+pub fn parse(s: &str) -> Result<Json> {
+    serde_json::from_str(s).map_err(|err| Error::Value(format!("Invalid JSON {}: {}", s, err)))
+}
+
+/// Converts a scalar value to a JSON document.
+pub fn from_value(value: &Value) -> Option<String> {
+    let json = match value {
+        Value::Boolean(b) => Json::from(*b),
+        Value::Integer(i) => Json::from(*i),
+        Value::Float(f) => Json::from(*f),
+        Value::Decimal(d) => serde_json::from_str(&d.to_string()).ok()?,
+        Value::String(s) => Json::from(s.as_str()),
+        _ => return None,
+    };
+    Some(json.to_string())
+}
+
+/// Converts a JSON value to an SQL value: JSON null is NULL, strings, booleans and numbers map to
+/// the corresponding types, and arrays and objects are returned as JSON documents.
+pub fn to_value(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Boolean(b),
+        Json::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64().unwrap_or(f64::NAN)),
+        },
+        Json::String(s) => Value::String(s),
+        json => Value::Json(json.to_string()),
+    }
+}
+
+/// Returns the text of a JSON value, with strings unquoted. JSON null is NULL.
+pub fn to_text(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::String(s) => Value::String(s),
+        json => Value::String(json.to_string()),
+    }
+}
+
+/// Looks up an object field by name, or an array element by index. Negative indexes count from
+/// the end of the array. Returns None if there is no such field or element.
+pub fn get(json: Json, key: &Value) -> Result<Option<Json>> {
+    Ok(match (json, key) {
+        (Json::Object(mut object), Value::String(name)) => object.remove(name),
+        (Json::Array(mut array), Value::Integer(i)) => {
+            let index = if *i < 0 { array.len() as i64 + i } else { *i };
+            if index >= 0 && (index as usize) < array.len() {
+                Some(array.swap_remove(index as usize))
+            } else {
+                None
+            }
+        }
+        (_, Value::String(_) | Value::Integer(_)) => None,
+        (_, key) => return Err(Error::Value(format!("Invalid JSON key {}", key))),
+    })
+}
+
+/// Looks up a path of the form $.field[index]..., where $ is the document itself. Field names
+/// can be double-quoted if they contain other characters than letters, digits and underscores.
+/// Returns None if the path doesn't exist in the document.
+pub fn extract(json: Json, path: &str) -> Result<Option<Json>> {
+    let invalid = || Error::Value(format!("Invalid JSON path {}", path));
+    let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+    let mut json = json;
+    while !rest.is_empty() {
+        let key = if let Some(r) = rest.strip_prefix(".\"") {
+            let end = r.find('"').ok_or_else(invalid)?;
+            rest = &r[end + 1..];
+            Value::String(r[..end].to_string())
+        } else if let Some(r) = rest.strip_prefix('.') {
+            let end = r.find(|c: char| !c.is_alphanumeric() && c != '_').unwrap_or(r.len());
+            if end == 0 {
+                return Err(invalid());
+            }
+            rest = &r[end..];
+            Value::String(r[..end].to_string())
+        } else if let Some(r) = rest.strip_prefix('[') {
+            let end = r.find(']').ok_or_else(invalid)?;
+            rest = &r[end + 1..];
+            Value::Integer(r[..end].trim().parse().map_err(|_| invalid())?)
+        } else {
+            return Err(invalid());
+        };
+        match get(json, &key)? {
+            Some(value) => json = value,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(json))
+}
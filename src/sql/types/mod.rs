@@ -1,6 +1,10 @@
+pub mod datetime;
+pub mod decimal;
 pub mod expression;
+pub mod json;
 
 use crate::error::{Error, Result};
+use decimal::Decimal;
 
 use serde_derive::{Deserialize, Serialize};
 use std::borrow::Cow;
@@ -14,6 +18,12 @@ pub enum DataType {
     Integer,
     Float,
     String,
+    Date,
+    Timestamp,
+    Decimal,
+    Blob,
+    Uuid,
+    Json,
 }
 
 impl std::fmt::Display for DataType {
@@ -23,6 +33,12 @@ impl std::fmt::Display for DataType {
             Self::Integer => "INTEGER",
             Self::Float => "FLOAT",
             Self::String => "STRING",
+            Self::Date => "DATE",
+            Self::Timestamp => "TIMESTAMP",
+            Self::Decimal => "DECIMAL",
+            Self::Blob => "BLOB",
+            Self::Uuid => "UUID",
+            Self::Json => "JSON",
         })
     }
 }
@@ -35,6 +51,15 @@ pub enum Value {
     Integer(i64),
     Float(f64),
     String(String),
+    /// 自 1970-01-01 以来的天数
+    Date(i32),
+    /// 自 1970-01-01 00:00:00 UTC 以来的微秒数
+    Timestamp(i64),
+    Decimal(Decimal),
+    Blob(#[serde(with = "serde_bytes")] Vec<u8>),
+    Uuid(u128),
+    /// 规范化的 JSON 文本，即紧凑格式且对象的键有序
+    Json(String),
 }
 
 impl std::cmp::Eq for Value {}
@@ -44,11 +69,17 @@ impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.datatype().hash(state);
         match self {
-            Value::Null => self.hash(state),
+            Value::Null => {}
             Value::Boolean(v) => v.hash(state),
             Value::Integer(v) => v.hash(state),
             Value::Float(v) => v.to_be_bytes().hash(state),
             Value::String(v) => v.hash(state),
+            Value::Date(v) => v.hash(state),
+            Value::Timestamp(v) => v.hash(state),
+            Value::Decimal(v) => v.hash(state),
+            Value::Blob(v) => v.hash(state),
+            Value::Uuid(v) => v.hash(state),
+            Value::Json(v) => v.hash(state),
         }
     }
 }
@@ -74,6 +105,12 @@ impl Value {
             Self::Integer(_) => Some(DataType::Integer),
             Self::Float(_) => Some(DataType::Float),
             Self::String(_) => Some(DataType::String),
+            Self::Date(_) => Some(DataType::Date),
+            Self::Timestamp(_) => Some(DataType::Timestamp),
+            Self::Decimal(_) => Some(DataType::Decimal),
+            Self::Blob(_) => Some(DataType::Blob),
+            Self::Uuid(_) => Some(DataType::Uuid),
+            Self::Json(_) => Some(DataType::Json),
         }
     }
 
@@ -108,6 +145,46 @@ impl Value {
             v => Err(Error::Value(format!("Not a string: {:?}", v))),
         }
     }
+
+    /// 返回用于哈希比较的规范化取值，使数值相等的 INTEGER、FLOAT 和 DECIMAL
+    /// 得到相同的表示：整数值统一为 INTEGER（-0.0 即 0），可精确表示的小数统一为 DECIMAL。
+    /// 其他的值保持不变
+    pub fn normalize(&self) -> Cow<'_, Value> {
+        match self {
+            Self::Float(f) if f.fract() == 0.0 && *f >= i64::MIN as f64 && *f < i64::MAX as f64 => {
+                Cow::Owned(Self::Integer(*f as i64))
+            }
+            Self::Float(f) => match Decimal::from_float(*f) {
+                Ok(d) if d.to_float() == *f => Cow::Owned(Self::Decimal(d)),
+                _ => Cow::Borrowed(self),
+            },
+            Self::Decimal(d) => match d.to_integer() {
+                Ok(i) if Decimal::from(i) == *d => Cow::Owned(Self::Integer(i)),
+                _ => Cow::Borrowed(self),
+            },
+            value => Cow::Borrowed(value),
+        }
+    }
+
+    /// 赋值给列时进行隐式类型转换：字符串可以转换为
+    /// DATE、TIMESTAMP、DECIMAL、UUID 和 JSON（同时校验 JSON 格式），
+    /// 数值可以转换为 DECIMAL，DATE 可以转换为 TIMESTAMP。
+    /// 其他的值保持不变，由列的校验来检查类型
+    pub fn coerce(self, datatype: &DataType) -> Result<Self> {
+        match (&self, datatype) {
+            (
+                Self::String(_),
+                DataType::Date
+                | DataType::Timestamp
+                | DataType::Decimal
+                | DataType::Uuid
+                | DataType::Json,
+            )
+            | (Self::Integer(_) | Self::Float(_), DataType::Decimal)
+            | (Self::Date(_), DataType::Timestamp) => expression::cast(self, datatype),
+            _ => Ok(self),
+        }
+    }
 }
 
 impl std::fmt::Display for Value {
@@ -120,12 +197,46 @@ impl std::fmt::Display for Value {
                 Self::Integer(i) => i.to_string(),
                 Self::Float(f) => f.to_string(),
                 Self::String(s) => s.clone(),
+                Self::Date(d) => datetime::format_date(*d),
+                Self::Timestamp(t) => datetime::format_timestamp(*t),
+                Self::Decimal(d) => d.to_string(),
+                Self::Blob(b) => format!("\\x{}", hex::encode(b)),
+                Self::Uuid(u) => format_uuid(*u),
+                Self::Json(j) => j.clone(),
             }
             .as_ref(),
         )
     }
 }
 
+/// 格式化主键，单列主键直接输出取值，组合主键输出为 (a, b)
+pub fn format_key(key: &[Value]) -> String {
+    match key {
+        [value] => value.to_string(),
+        key => format!("({})", key.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// 返回行的规范化取值，用于按值对行去重，见 [`Value::normalize`]
+pub fn normalize_row(row: &[Value]) -> Row {
+    row.iter().map(|v| v.normalize().into_owned()).collect()
+}
+
+/// 解析 UUID，格式为 32 个十六进制数字，可以用 - 分隔
+pub fn parse_uuid(s: &str) -> Result<u128> {
+    let digits: String = s.trim().chars().filter(|c| *c != '-').collect();
+    if digits.len() != 32 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(Error::Value(format!("Invalid UUID {}", s)));
+    }
+    Ok(u128::from_str_radix(&digits, 16)?)
+}
+
+/// 格式化 UUID，例如 123e4567-e89b-12d3-a456-426614174000
+pub fn format_uuid(uuid: u128) -> String {
+    let s = format!("{:032x}", uuid);
+    format!("{}-{}-{}-{}-{}", &s[..8], &s[8..12], &s[12..16], &s[16..20], &s[20..])
+}
+
 /// 实现元素之前的比较，实现 broadcast
 impl PartialOrd for Value {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
@@ -139,6 +250,22 @@ impl PartialOrd for Value {
             (Self::Integer(a), Self::Float(b)) => (*a as f64).partial_cmp(b),
             (Self::Integer(a), Self::Integer(b)) => a.partial_cmp(b),
             (Self::String(a), Self::String(b)) => a.partial_cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.partial_cmp(b),
+            (Self::Timestamp(a), Self::Timestamp(b)) => a.partial_cmp(b),
+            (Self::Date(a), Self::Timestamp(b)) => {
+                (*a as i128 * datetime::MICROS_PER_DAY as i128).partial_cmp(&(*b as i128))
+            }
+            (Self::Timestamp(a), Self::Date(b)) => {
+                (*a as i128).partial_cmp(&(*b as i128 * datetime::MICROS_PER_DAY as i128))
+            }
+            (Self::Decimal(a), Self::Decimal(b)) => a.partial_cmp(b),
+            (Self::Decimal(a), Self::Integer(b)) => a.partial_cmp(&Decimal::from(*b)),
+            (Self::Integer(a), Self::Decimal(b)) => Decimal::from(*a).partial_cmp(b),
+            (Self::Decimal(a), Self::Float(b)) => a.to_float().partial_cmp(b),
+            (Self::Float(a), Self::Decimal(b)) => a.partial_cmp(&b.to_float()),
+            (Self::Blob(a), Self::Blob(b)) => a.partial_cmp(b),
+            (Self::Uuid(a), Self::Uuid(b)) => a.partial_cmp(b),
+            (Self::Json(a), Self::Json(b)) => a.partial_cmp(b),
             (_, _) => None,
         }
     }
@@ -162,6 +289,12 @@ impl From<i64> for Value {
     }
 }
 
+impl From<Decimal> for Value {
+    fn from(v: Decimal) -> Self {
+        Value::Decimal(v)
+    }
+}
+
 impl From<String> for Value {
     fn from(v: String) -> Self {
         Value::String(v)
@@ -80,7 +80,7 @@ impl Engine for Bitcask {
 
         let size = self.keydir
                             .iter()
-                            .fold(0 as u64, |size, (key, (_, value_len))| size + key.len() as u64 + *value_len as u64);
+                            .fold(0_u64, |size, (key, (_, value_len))| size + key.len() as u64 + *value_len as u64);
         
         let total_disk_size = self.log.total_size()?;
 
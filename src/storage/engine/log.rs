@@ -14,7 +14,7 @@ impl Log {
         if let Some(path) = path.parent() {
             fs::create_dir_all(path)?;
         }
-        let file = std::fs::OpenOptions::new().read(true).write(true).create(true).open(&path)?;
+        let file = std::fs::OpenOptions::new().read(true).write(true).create(true).truncate(false).open(&path)?;
         Ok(Log { path, file })
     }
 
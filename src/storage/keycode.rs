@@ -3,7 +3,10 @@
 /// 
 /// bool:   0x00 表示 false，0x01 表示 true
 /// u64:    使用大尾数表示
+/// i32:    使用大尾数表示，将符号位翻转
 /// i64:    使用大尾数表示，将符号位翻转
+/// i128:   使用大尾数表示，将符号位翻转
+/// u128:   使用大尾数表示
 /// f64:    使用大尾数表示，如果是负数的话，全部翻转
 /// Vec<u8>:使用 0x00ff 表示 0x00，使用 0x0000 表示结尾
 /// String: 与 Vec<u8> 差不多
@@ -41,7 +44,7 @@ struct Serializer {
     output: Vec<u8>,
 }
 
-impl<'a> serde::Serializer for &'a mut Serializer {
+impl serde::Serializer for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -67,8 +70,12 @@ impl<'a> serde::Serializer for &'a mut Serializer {
         unimplemented!()
     }
 
-    fn serialize_i32(self, _: i32) -> Result<()> {
-        unimplemented!()
+    /// i32 is encoded like i64, with the sign bit flipped.
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        let mut bytes = v.to_be_bytes();
+        bytes[0] ^= 1 << 7; // flip sign bit
+        self.output.extend(bytes);
+        Ok(())
     }
 
     /// i64 uses the big-endian two's completement encoding, but flips the
@@ -85,6 +92,14 @@ impl<'a> serde::Serializer for &'a mut Serializer {
         Ok(())
     }
 
+    /// i128 is encoded like i64, with the sign bit flipped.
+    fn serialize_i128(self, v: i128) -> Result<()> {
+        let mut bytes = v.to_be_bytes();
+        bytes[0] ^= 1 << 7; // flip sign bit
+        self.output.extend(bytes);
+        Ok(())
+    }
+
     fn serialize_u8(self, _: u8) -> Result<()> {
         unimplemented!()
     }
@@ -103,6 +118,12 @@ impl<'a> serde::Serializer for &'a mut Serializer {
         Ok(())
     }
 
+    /// u128 simply uses the big-endian encoding.
+    fn serialize_u128(self, v: u128) -> Result<()> {
+        self.output.extend(v.to_be_bytes());
+        Ok(())
+    }
+
     fn serialize_f32(self, _: f32) -> Result<()> {
         unimplemented!()
     }
@@ -238,7 +259,7 @@ impl<'a> serde::Serializer for &'a mut Serializer {
 }
 
 // Sequences simply concatenate the serialized elements, with no external structure.
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl ser::SerializeSeq for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -252,7 +273,7 @@ impl<'a> ser::SerializeSeq for &'a mut Serializer {
 }
 
 // Tuples, like sequences, simply concatenate the serialized elements.
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl ser::SerializeTuple for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -266,7 +287,7 @@ impl<'a> ser::SerializeTuple for &'a mut Serializer {
 }
 
 // Tuples, like sequences, simply concatenate the serialized elements.
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
+impl ser::SerializeTupleVariant for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
@@ -330,7 +351,7 @@ impl<'de> Deserializer<'de> {
 }
 
 // For details on serialization formats, see Serializer.
-impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
+impl<'de> serde::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
     fn deserialize_any<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value> {
@@ -353,8 +374,10 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         unimplemented!()
     }
 
-    fn deserialize_i32<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value> {
-        unimplemented!()
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut bytes = self.take_bytes(4)?.to_vec();
+        bytes[0] ^= 1 << 7; // flip sign bit
+        visitor.visit_i32(i32::from_be_bytes(bytes.as_slice().try_into()?))
     }
 
     fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -363,6 +386,12 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_i64(i64::from_be_bytes(bytes.as_slice().try_into()?))
     }
 
+    fn deserialize_i128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut bytes = self.take_bytes(16)?.to_vec();
+        bytes[0] ^= 1 << 7; // flip sign bit
+        visitor.visit_i128(i128::from_be_bytes(bytes.as_slice().try_into()?))
+    }
+
     fn deserialize_u8<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value> {
         unimplemented!()
     }
@@ -379,6 +408,10 @@ impl<'de, 'a> serde::Deserializer<'de> for &'a mut Deserializer<'de> {
         visitor.visit_u64(u64::from_be_bytes(self.take_bytes(8)?.try_into()?))
     }
 
+    fn deserialize_u128<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u128(u128::from_be_bytes(self.take_bytes(16)?.try_into()?))
+    }
+
     fn deserialize_f32<V: de::Visitor<'de>>(self, _: V) -> Result<V::Value> {
         unimplemented!()
     }
@@ -638,6 +671,11 @@ mod tests {
         f64_inf: f64::INFINITY => "fff0000000000000",
         // We don't test NAN here, since NAN != NAN.
 
+        i32_min: i32::MIN => "00000000",
+        i32_neg_1: -1i32 => "7fffffff",
+        i32_0: 0i32 => "80000000",
+        i32_max: i32::MAX => "ffffffff",
+
         i64_min: i64::MIN => "0000000000000000",
         i64_neg_65535: -65535i64 => "7fffffffffff0001",
         i64_neg_1: -1i64 => "7fffffffffffffff",
@@ -646,11 +684,20 @@ mod tests {
         i64_65535: 65535i64 => "800000000000ffff",
         i64_max: i64::MAX => "ffffffffffffffff",
 
+        i128_min: i128::MIN => "00000000000000000000000000000000",
+        i128_neg_1: -1i128 => "7fffffffffffffffffffffffffffffff",
+        i128_0: 0i128 => "80000000000000000000000000000000",
+        i128_max: i128::MAX => "ffffffffffffffffffffffffffffffff",
+
         u64_min: u64::MIN => "0000000000000000",
         u64_1: 1_u64 => "0000000000000001",
         u64_65535: 65535_u64 => "000000000000ffff",
         u64_max: u64::MAX => "ffffffffffffffff",
 
+        u128_min: u128::MIN => "00000000000000000000000000000000",
+        u128_65535: 65535_u128 => "0000000000000000000000000000ffff",
+        u128_max: u128::MAX => "ffffffffffffffffffffffffffffffff",
+
         bytes: ByteBuf::from(vec![0x01, 0xff]) => "01ff0000",
         bytes_empty: ByteBuf::new() => "0000",
         bytes_escape: ByteBuf::from(vec![0x00, 0x01, 0x02]) => "00ff01020000",
@@ -683,12 +730,9 @@ mod tests {
         f32: 0f32,
         i8: 0i8,
         i16: 0i16,
-        i32: 0i32,
-        i128: 0i128,
         u8: 0u8,
         u16: 0u16,
         u32: 0u32,
-        u128: 0u128,
         some: Some(true),
         none: Option::<bool>::None,
         vec_u8: vec![0u8],
@@ -701,12 +745,12 @@ mod tests {
         f32: "00000000" as f32,
         i8: "00" as i8,
         i16: "0000" as i16,
-        i32: "00000000" as i32,
-        i128: "00000000000000000000000000000000" as i128,
+        i32_partial: "0000" as i32,
+        i128_partial: "0000000000000000" as i128,
         u16: "0000" as u16,
         u32: "00000000" as u32,
         u64_partial: "0000" as u64,
-        u128: "00000000000000000000000000000000" as u128,
+        u128_partial: "0000000000000000" as u128,
         option: "00" as Option::<bool>,
         string_utf8_invalid: "c0" as String,
         tuple_partial: "0001" as (bool, bool, bool),
@@ -7,7 +7,7 @@ use crate::{error::Result, storage::keycode};
 /// 表示一个事物的逻辑时间戳
 pub type Version = u64;
 
-/// 
+/// MVCC 层使用的 key
 #[derive(Debug, Deserialize, Serialize)]
 pub enum Key<'a> {
     /// 下一个可用的 Version
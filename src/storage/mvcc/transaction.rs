@@ -21,7 +21,7 @@ pub struct TransactionState {
 
 impl TransactionState {
     pub fn is_visible(&self, version: Version) -> bool {
-        if self.active.get(&version).is_some() {
+        if self.active.contains(&version) {
             // 事物还没有提交，所以不可见
             false
         } else if self.read_only {
@@ -149,7 +149,7 @@ impl<E: Engine> Transaction<E> {
         for key in remove {
             match Key::decode(&key)? {
                 Key::TxnWrite(_, key) => {
-                    let version = Key::Version(key.into(), self.version());
+                    let version = Key::Version(key, self.version());
                     session.delete(&version.encode()?)?;
                 },
                 key => return Err(Error::Internal(format!("Expected TxnWrite, got {:?}", key))),
@@ -218,7 +218,7 @@ impl<E: Engine> Transaction<E> {
         session.set(&Key::Version(key.into(), self.st.version).encode()?, bincode::serialize(&value)?)
     }
 
-    pub fn scan<R: RangeBounds<Vec<u8>>>(&self, range: R) -> Result<Scan<E>> {
+    pub fn scan<R: RangeBounds<Vec<u8>>>(&self, range: R) -> Result<Scan<'_, E>> {
         let start = match range.start_bound() {
             Bound::Excluded(k) => Bound::Excluded(Key::Version(k.into(), u64::MAX).encode()?),
             Bound::Included(k) => Bound::Included(Key::Version(k.into(), 0).encode()?),
@@ -233,7 +233,7 @@ impl<E: Engine> Transaction<E> {
     }
 
     /// Scans keys under a given prefix.
-    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Scan<E>> {
+    pub fn scan_prefix(&self, prefix: &[u8]) -> Result<Scan<'_, E>> {
         // Normally, KeyPrefix::Version will only match all versions of the
         // exact given key. We want all keys maching the prefix, so we chop off
         // the KeyCode byte slice terminator 0x0000 at the end.